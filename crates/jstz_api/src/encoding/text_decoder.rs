@@ -8,6 +8,7 @@ use boa_gc::{Finalize, GcRefMut, Trace};
 use encoding_rs::{Decoder, DecoderResult, Encoding};
 use jstz_core::{
     accessor,
+    gas::{self, schedule},
     native::{register_global_class, Accessor, ClassBuilder, NativeClass},
     value::{IntoJs, TryFromJs},
 };
@@ -318,6 +319,13 @@ impl TextDecoderClass {
             None => text_decoder.decode(None, options),
         }?;
 
+        gas::charge(
+            context,
+            "TextDecoder.decode",
+            schedule::TEXT_DECODE,
+            result.len(),
+        )?;
+
         Ok(js_string!(result).into())
     }
 }
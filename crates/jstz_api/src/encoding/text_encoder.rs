@@ -12,6 +12,7 @@ use boa_gc::{Finalize, GcRefMut, Trace};
 use encoding_rs::UTF_8;
 use jstz_core::{
     accessor,
+    gas::{self, schedule},
     native::{
         register_global_class, Accessor, ClassBuilder, JsNativeObject, NativeClass,
     },
@@ -210,6 +211,13 @@ impl TextEncoderClass {
 
         let result = TextEncoder::encode(input)?;
 
+        gas::charge(
+            context,
+            "TextEncoder.encode",
+            schedule::TEXT_ENCODE,
+            result.len(),
+        )?;
+
         let uint8_array = JsUint8Array::from_array_buffer(
             JsArrayBuffer::from_byte_block(result, context)?,
             context,
@@ -235,6 +243,14 @@ impl TextEncoderClass {
 
         let dst: JsUint8Array = args.get_or_undefined(1).try_js_into(context)?;
 
+        // Each UTF-16 code unit is encoded into at most 3 bytes
+        gas::charge(
+            context,
+            "TextEncoder.encodeInto",
+            schedule::TEXT_ENCODE,
+            src.len() * 3,
+        )?;
+
         let array_buffer_data = dst.to_array_buffer_data(context)?;
         let mut dst_slice = array_buffer_data.as_slice_mut();
 
@@ -19,6 +19,7 @@ use boa_engine::{
     Context, JsError, JsNativeError, JsResult, JsString, JsValue,
 };
 use boa_gc::{Finalize, Trace};
+use jstz_core::gas::{self, schedule};
 
 pub type HttpBody = Option<Vec<u8>>;
 
@@ -61,6 +62,13 @@ impl Inner {
         }
     }
 
+    fn len(&self) -> usize {
+        match self {
+            Inner::Text(string) => string.len(),
+            Inner::Bytes(bytes) => bytes.len(),
+        }
+    }
+
     fn into_array_buffer(self, context: &mut Context<'_>) -> JsResult<JsArrayBuffer> {
         JsArrayBuffer::from_byte_block(self.bytes(), context)
     }
@@ -84,6 +92,11 @@ impl Body {
             Inner::Bytes(bytes) => bytes.clone(),
         })
     }
+
+    /// Size of the body's content, or 0 if the body is null
+    pub fn size(&self) -> usize {
+        self.inner.as_ref().map_or(0, Inner::len)
+    }
 }
 
 impl Body {
@@ -131,6 +144,12 @@ impl Body {
     /// [spec] https://fetch.spec.whatwg.org/#dom-body-arraybuffer
     pub fn array_buffer(&mut self, context: &mut Context<'_>) -> JsResult<JsPromise> {
         let inner = self.inner()?;
+        gas::charge(
+            context,
+            "Body.arrayBuffer",
+            schedule::BODY_READ,
+            inner.len(),
+        )?;
         JsPromise::resolve(inner.into_array_buffer(context)?, context)
     }
 
@@ -142,6 +161,7 @@ impl Body {
     /// [spec] https://fetch.spec.whatwg.org/#dom-body-text
     pub fn text(&mut self, context: &mut Context<'_>) -> JsResult<JsPromise> {
         let inner = self.inner()?;
+        gas::charge(context, "Body.text", schedule::BODY_READ, inner.len())?;
         JsPromise::resolve(inner.text()?, context)
     }
 
@@ -153,6 +173,7 @@ impl Body {
     /// [spec] https://fetch.spec.whatwg.org/#dom-body-json
     pub fn json(&mut self, context: &mut Context<'_>) -> JsResult<JsPromise> {
         let inner = self.inner()?;
+        gas::charge(context, "Body.json", schedule::BODY_READ, inner.len())?;
         let json: serde_json::Value =
             serde_json::from_str(&inner.string()?).map_err(|_| {
                 JsError::from_native(
@@ -285,6 +285,10 @@ impl Request {
         &self.url
     }
 
+    pub fn body(&self) -> &Body {
+        self.request.body()
+    }
+
     pub fn headers(&self) -> &JsNativeObject<Headers> {
         &self.headers
     }
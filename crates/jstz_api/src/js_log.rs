@@ -1,5 +1,6 @@
 use boa_engine::{Context, JsNativeError, JsResult};
 use clap::ValueEnum;
+use jstz_core::gas::{self, schedule};
 use serde::{Deserialize, Serialize};
 use std::cell::Cell;
 
@@ -56,6 +57,13 @@ pub fn set_js_logger(logger: &'static dyn JsLog) {
 }
 
 pub(crate) fn log(log_data: LogData, context: &mut Context<'_>) -> JsResult<()> {
+    gas::charge(
+        context,
+        "console",
+        schedule::CONSOLE_LOG,
+        log_data.text.len(),
+    )?;
+
     CONSOLE_LOGGER.with(|logger| {
         if let Some(logger) = logger.get() {
             logger.log(log_data, context);
@@ -5,7 +5,13 @@ use boa_engine::{
     JsNativeError, JsResult, JsString, JsValue, NativeFunction,
 };
 use boa_gc::{Finalize, Trace};
-use jstz_core::{host::HostRuntime, kv::Transaction, runtime, Result};
+use jstz_core::{
+    gas::{self, schedule},
+    host::HostRuntime,
+    journal,
+    kv::Transaction,
+    runtime, Result,
+};
 use jstz_crypto::public_key_hash::PublicKeyHash;
 use serde::{Deserialize, Serialize};
 use tezos_smart_rollup::storage::path::{self, OwnedPath, RefPath};
@@ -22,6 +28,26 @@ const KV_PATH: RefPath = RefPath::assert_from(b"/jstz_kv");
 #[serde(try_from = "String", into = "String")]
 pub struct KvValue(pub serde_json::Value);
 
+impl KvValue {
+    /// Approximate size of the value in bytes, without serializing it: the
+    /// length of its strings and keys, and 8 bytes per other scalar
+    pub fn size(&self) -> usize {
+        fn size(value: &serde_json::Value) -> usize {
+            match value {
+                serde_json::Value::String(string) => string.len(),
+                serde_json::Value::Array(values) => values.iter().map(size).sum(),
+                serde_json::Value::Object(entries) => entries
+                    .iter()
+                    .map(|(key, value)| key.len() + size(value))
+                    .sum(),
+                _ => 8,
+            }
+        }
+
+        size(&self.0)
+    }
+}
+
 impl From<KvValue> for String {
     fn from(val: KvValue) -> Self {
         val.0.to_string()
@@ -48,7 +74,9 @@ impl Kv {
     }
 
     pub fn set(&self, tx: &mut Transaction, key: &str, value: KvValue) -> Result<()> {
-        tx.insert(self.key_path(key)?, value)
+        tx.insert(self.key_path(key)?, value)?;
+        journal::record_kv_write(&self.prefix, key);
+        Ok(())
     }
 
     pub fn get<'a>(
@@ -61,7 +89,9 @@ impl Kv {
     }
 
     pub fn delete(&self, tx: &mut Transaction, key: &str) -> Result<()> {
-        tx.remove(self.key_path(key)?)
+        tx.remove(self.key_path(key)?)?;
+        journal::record_kv_write(&self.prefix, key);
+        Ok(())
     }
 
     pub fn has(
@@ -109,6 +139,9 @@ impl KvApi {
 
         let value = KvValue(args.get_or_undefined(1).to_json(context)?);
 
+        let bytes = key.len() + value.size();
+        gas::charge(context, "Kv.set", schedule::KV_SET, bytes)?;
+
         runtime::with_js_tx(|tx| this.set(tx, &key, value))?;
 
         Ok(JsValue::undefined())
@@ -119,8 +152,17 @@ impl KvApi {
 
         runtime::with_js_hrt_and_tx(|hrt, tx| -> JsResult<JsValue> {
             match this.get(hrt.deref(), tx, &key)? {
-                Some(value) => JsValue::from_json(&value.0, context),
-                None => Ok(JsValue::null()),
+                Some(value) => {
+                    let bytes = key.len() + value.size();
+                    gas::charge(context, "Kv.get", schedule::KV_GET, bytes)?;
+
+                    JsValue::from_json(&value.0, context)
+                }
+                None => {
+                    gas::charge(context, "Kv.get", schedule::KV_GET, key.len())?;
+
+                    Ok(JsValue::null())
+                }
             }
         })
     }
@@ -128,22 +170,22 @@ impl KvApi {
     fn delete(
         this: &JsValue,
         args: &[JsValue],
-        _context: &mut Context,
+        context: &mut Context,
     ) -> JsResult<JsValue> {
         preamble!(this, args, key);
 
+        gas::charge(context, "Kv.delete", schedule::KV_DELETE, key.len())?;
+
         runtime::with_js_tx(|tx| this.delete(tx, &key))?;
 
         Ok(JsValue::undefined())
     }
 
-    fn has(
-        this: &JsValue,
-        args: &[JsValue],
-        _context: &mut Context,
-    ) -> JsResult<JsValue> {
+    fn has(this: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
         preamble!(this, args, key);
 
+        gas::charge(context, "Kv.has", schedule::KV_HAS, key.len())?;
+
         let result = runtime::with_js_hrt(|hrt| {
             runtime::with_js_tx(|tx| this.has(hrt.deref(), tx, &key))
         })?;
@@ -1,14 +1,15 @@
 use bip39::{Language, Mnemonic, MnemonicType};
-use clap::Subcommand;
+use clap::{Subcommand, ValueEnum};
 use dialoguer::{Confirm, Input};
-use jstz_crypto::keypair_from_passphrase;
+use jstz_crypto::{keypair_from_passphrase_with_key_type, KeyType as CryptoKeyType};
 use jstz_proto::context::account::Address;
 use log::{debug, info, warn};
 use std::collections::hash_map::Entry;
 
 use crate::{
     config::{Account, Config, NetworkName, SmartFunction, User},
-    error::{bail_user_error, user_error, Result},
+    error::{anyhow, bail_user_error, user_error, Result},
+    term::styles,
     utils::AddressOrAlias,
 };
 
@@ -17,9 +18,38 @@ fn generate_passphrase() -> String {
     mnemonic.to_string()
 }
 
+#[derive(ValueEnum, Debug, Default, Clone, Copy)]
+pub enum KeyType {
+    /// tz1 account
+    #[default]
+    Ed25519,
+    /// tz2 account
+    Secp256k1,
+    /// tz3 account
+    P256,
+}
+
+impl From<KeyType> for CryptoKeyType {
+    fn from(key_type: KeyType) -> Self {
+        match key_type {
+            KeyType::Ed25519 => CryptoKeyType::Ed25519,
+            KeyType::Secp256k1 => CryptoKeyType::Secp256k1,
+            KeyType::P256 => CryptoKeyType::P256,
+        }
+    }
+}
+
 impl User {
     pub fn from_passphrase(passphrase: String) -> Result<Self> {
-        let (sk, pk) = keypair_from_passphrase(passphrase.as_str())?;
+        Self::from_passphrase_with_key_type(passphrase, KeyType::default())
+    }
+
+    pub fn from_passphrase_with_key_type(
+        passphrase: String,
+        key_type: KeyType,
+    ) -> Result<Self> {
+        let (sk, pk) =
+            keypair_from_passphrase_with_key_type(key_type.into(), passphrase.as_str())?;
 
         let address = Address::try_from(&pk)?;
 
@@ -49,7 +79,11 @@ fn add_smart_function(alias: String, address: Address) -> Result<()> {
     Ok(())
 }
 
-fn create_account(alias: String, passphrase: Option<String>) -> Result<()> {
+fn create_account(
+    alias: String,
+    passphrase: Option<String>,
+    key_type: KeyType,
+) -> Result<()> {
     let mut cfg = Config::load()?;
 
     if cfg.accounts.contains(&alias) {
@@ -68,7 +102,7 @@ fn create_account(alias: String, passphrase: Option<String>) -> Result<()> {
         }
     };
 
-    let user = User::from_passphrase(passphrase)?;
+    let user = User::from_passphrase_with_key_type(passphrase, key_type)?;
 
     debug!("User created: {:?}", user);
     info!("User created with address: {}", user.address);
@@ -180,6 +214,29 @@ pub fn login_quick(cfg: &mut Config) -> Result<()> {
     Ok(())
 }
 
+/// Logs in if needed, returning the alias and the keys of the current user.
+/// Operations cannot be sent to the dev network unless the sandbox is running.
+pub fn login_for_operation(
+    cfg: &mut Config,
+    network: &Option<NetworkName>,
+) -> Result<(String, User)> {
+    if cfg.network_name(network)? == NetworkName::Dev && cfg.sandbox.is_none() {
+        bail_user_error!(
+            "No sandbox is currently running. Please run {}.",
+            styles::command("jstz sandbox start")
+        );
+    }
+
+    login_quick(cfg)?;
+    cfg.reload()?;
+    let (user_name, user) = cfg.accounts.current_user().ok_or(anyhow!(
+        "Failed to setup the account. Please run `{}`.",
+        styles::command("jstz login")
+    ))?;
+
+    Ok((user_name.to_string(), user.clone()))
+}
+
 pub fn logout() -> Result<()> {
     let mut cfg = Config::load()?;
 
@@ -290,6 +347,9 @@ pub enum Command {
         /// User passphrase. If undefined, a random passphrase will be generated.
         #[arg(short, long)]
         passphrase: Option<String>,
+        /// Signature scheme of the account's key pair.
+        #[arg(short, long, value_enum, default_value_t = KeyType::Ed25519)]
+        key_type: KeyType,
     },
     /// ❌ Deletes an account (user or smart function).
     Delete {
@@ -338,7 +398,11 @@ pub enum Command {
 pub async fn exec(command: Command) -> Result<()> {
     match command {
         Command::Alias { alias, address } => add_smart_function(alias, address),
-        Command::Create { alias, passphrase } => create_account(alias, passphrase),
+        Command::Create {
+            alias,
+            passphrase,
+            key_type,
+        } => create_account(alias, passphrase, key_type),
         Command::Delete { alias } => delete_account(alias),
         Command::List { long } => list_accounts(long),
         Command::Code { account, network } => get_code(account, network).await,
@@ -0,0 +1,66 @@
+use jstz_proto::{
+    context::asset::Asset,
+    operation::{Approve, Content},
+    receipt::Content as ReceiptContent,
+};
+use log::info;
+
+use crate::{
+    account,
+    config::{Config, NetworkName},
+    error::{bail, bail_user_error, user_error, Result},
+    utils::AddressOrAlias,
+};
+
+pub async fn exec(
+    spender: AddressOrAlias,
+    amount: u64,
+    asset: Option<String>,
+    valid_until_level: Option<u32>,
+    network: Option<NetworkName>,
+) -> Result<()> {
+    let mut cfg = Config::load()?;
+
+    let asset = asset
+        .map(|asset| {
+            asset
+                .parse::<Asset>()
+                .map_err(|_| user_error!("Invalid asset '{}'.", asset))
+        })
+        .transpose()?;
+
+    let (user_name, user) = account::login_for_operation(&mut cfg, &network)?;
+
+    let spender = spender.resolve(&cfg)?;
+
+    let jstz_client = cfg.jstz_client(&network)?;
+
+    let receipt = jstz_client
+        .post_operation_content(
+            &user,
+            Content::Approve(Approve {
+                spender: spender.clone(),
+                amount,
+                asset,
+            }),
+            valid_until_level,
+        )
+        .await?;
+
+    match receipt.inner {
+        Ok(ReceiptContent::Approve(_)) => (),
+        Ok(_) => {
+            bail!("Expected an `Approve` receipt, but got something else.")
+        }
+        Err(err) => {
+            bail_user_error!("Failed to approve spender with error {err}.")
+        }
+    };
+
+    info!(
+        "Allowed {} to transfer up to {} from {}",
+        spender, amount, user_name
+    );
+
+    Ok(())
+}
@@ -47,7 +47,7 @@ pub fn exec(
             "deposit",
             &format!(
                 "(Pair 0x{} {})",
-                hex::encode_upper(to_pkh.as_bytes()),
+                hex::encode_upper(to_pkh.to_bytes()),
                 amount,
             ),
         )
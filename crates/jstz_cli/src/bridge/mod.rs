@@ -1,6 +1,8 @@
 use clap::Subcommand;
+use jstz_proto::context::account::Address;
 
 mod deposit;
+mod withdraw;
 
 use crate::{config::NetworkName, error::Result, utils::AddressOrAlias};
 
@@ -22,9 +24,25 @@ pub enum Command {
         #[arg(short, long, default_value = None)]
         network: Option<NetworkName>,
     },
+    /// 💸 Withdraws CTEZ from the current jstz account to a Tezos L1 address.
+    Withdraw {
+        /// Tezos L1 address to withdraw to.
+        #[arg(short, long)]
+        to: Address,
+        /// The amount in CTEZ to transfer.
+        #[arg(short, long)]
+        amount: u64,
+        /// Last level at which the operation can be included. If undefined, the operation never expires.
+        #[arg(long, value_name = "LEVEL", default_value = None)]
+        valid_until_level: Option<u32>,
+        /// Specifies the network from the config file, defaulting to the configured default network.
+        /// Use `dev` for the local sandbox.
+        #[arg(short, long, default_value = None)]
+        network: Option<NetworkName>,
+    },
 }
 
-pub fn exec(command: Command) -> Result<()> {
+pub async fn exec(command: Command) -> Result<()> {
     match command {
         Command::Deposit {
             from,
@@ -32,5 +50,11 @@ pub fn exec(command: Command) -> Result<()> {
             amount,
             network,
         } => deposit::exec(from, to, amount, network),
+        Command::Withdraw {
+            to,
+            amount,
+            valid_until_level,
+            network,
+        } => withdraw::exec(to, amount, valid_until_level, network).await,
     }
 }
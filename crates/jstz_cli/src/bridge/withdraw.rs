@@ -0,0 +1,58 @@
+use jstz_proto::{
+    context::account::Address,
+    operation::{Content, Withdraw},
+    receipt::Content as ReceiptContent,
+};
+use log::info;
+
+use crate::{
+    account,
+    config::{Config, NetworkName},
+    error::{bail, bail_user_error, Result},
+};
+
+pub async fn exec(
+    to: Address,
+    amount: u64,
+    valid_until_level: Option<u32>,
+    network: Option<NetworkName>,
+) -> Result<()> {
+    let mut cfg = Config::load()?;
+
+    if amount == 0 {
+        bail_user_error!("The amount to withdraw must be greater than 0.");
+    }
+
+    let (user_name, user) = account::login_for_operation(&mut cfg, &network)?;
+
+    let jstz_client = cfg.jstz_client(&network)?;
+
+    let receipt = jstz_client
+        .post_operation_content(
+            &user,
+            Content::Withdraw(Withdraw {
+                receiver: to.clone(),
+                amount,
+            }),
+            valid_until_level,
+        )
+        .await?;
+
+    match receipt.inner {
+        Ok(ReceiptContent::Withdraw) => (),
+        Ok(_) => {
+            bail!("Expected a `Withdraw` receipt, but got something else.")
+        }
+        Err(err) => {
+            bail_user_error!("Failed to withdraw CTEZ with error {err}.")
+        }
+    };
+
+    info!("Withdrew {} CTEZ from {} to {}", amount, user_name, to);
+    info!(
+        "The CTEZ will be transferred to {} once the rollup's outbox message has been executed on L1.",
+        to
+    );
+
+    Ok(())
+}
@@ -1,44 +1,55 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
 use boa_engine::JsError;
 use jstz_proto::{
-    context::account::ParsedCode,
-    operation::{Content, DeployFunction, Operation, SignedOperation},
+    context::account::{Modules, ParsedCode},
+    executor::smart_function::Script,
+    operation::{Content, DeployFunction},
     receipt::Content as ReceiptContent,
+    reveal::MAX_REVEALED_SIZE,
 };
 use log::{debug, info};
 
 use crate::{
     account,
     config::{Config, NetworkName, SmartFunction},
-    error::{anyhow, bail, bail_user_error, user_error, Result},
+    error::{bail, bail_user_error, user_error, Result},
     term::styles,
-    utils::read_file_or_input_or_piped,
+    utils::{read_file_or_input_or_piped, AddressOrAlias},
 };
 
+/// Maximum size of code sent within a single external message. Larger code is
+/// sent in chunks by the jstz node.
+pub const MAX_CODE_LENGTH: usize = 3915;
+
+/// Reads the code of a smart function from a file, the argument or stdin
+pub fn read_code(code_op: Option<String>, max_length: usize) -> Result<ParsedCode> {
+    let code = read_file_or_input_or_piped(code_op)?
+        .ok_or(user_error!("No function code supplied. Please provide a filename or pipe the file contents into stdin."))?;
+
+    if code.bytes().len() > max_length {
+        bail_user_error!(
+            "Smart functions are currently restricted to {max_length} bytes"
+        );
+    }
+
+    debug!("Code: {}", code);
+
+    code.try_into().map_err(|err: JsError| user_error!("{err}"))
+}
+
 pub async fn exec(
     code_op: Option<String>,
     balance: u64,
     name: Option<String>,
+    admin: Option<AddressOrAlias>,
+    salt: Option<String>,
+    modules: Vec<String>,
+    valid_until_level: Option<u32>,
     network: Option<NetworkName>,
 ) -> Result<()> {
-    // maximum size of code until the DAL is implemented
-    const MAX_CODE_LENGTH: usize = 3915;
-
     let mut cfg = Config::load()?;
-    // Load sandbox if the selected network is Dev and sandbox is not already loaded
-    if cfg.network_name(&network)? == NetworkName::Dev && cfg.sandbox.is_none() {
-        bail_user_error!(
-            "No sandbox is currently running. Please run {}.",
-            styles::command("jstz sandbox start")
-        );
-    }
-
-    // Get the current user and check if we are logged in
-    account::login_quick(&mut cfg)?;
-    cfg.reload()?;
-    let (user_name, user) = cfg.accounts.current_user().ok_or(anyhow!(
-        "Failed to setup the account. Please run `{}`.",
-        styles::command("jstz login")
-    ))?;
+    let (user_name, user) = account::login_for_operation(&mut cfg, &network)?;
 
     // 1. Check if smart function account already exists
     if let Some(name) = &name {
@@ -50,50 +61,54 @@ pub async fn exec(
         }
     }
 
+    let admin = admin.map(|admin| admin.resolve(&cfg)).transpose()?;
+
     // 2. Construct operation
     let jstz_client = cfg.jstz_client(&network)?;
 
-    let nonce = jstz_client.get_nonce(&user.address).await?;
-
-    debug!("Nonce: {:?}", nonce);
-
-    let code = read_file_or_input_or_piped(code_op)?
-        .ok_or(user_error!("No function code supplied. Please provide a filename or pipe the file contents into stdin."))?;
-
-    if code.bytes().len() > MAX_CODE_LENGTH {
-        bail_user_error!("The data availability layer is not yet available. Smart functions are currently restricted to {MAX_CODE_LENGTH} bytes");
+    // Modules are read relative to the directory of the function's file
+    let modules_dir = code_op
+        .as_deref()
+        .map(Path::new)
+        .filter(|path| path.is_file())
+        .and_then(Path::parent)
+        .unwrap_or(Path::new(""))
+        .to_path_buf();
+
+    let code = read_code(code_op, MAX_REVEALED_SIZE)?;
+
+    let modules = modules
+        .into_iter()
+        .map(|path| {
+            let code = fs::read_to_string(modules_dir.join(&path)).map_err(|err| {
+                user_error!("Failed to read module '{}': {}", path, err)
+            })?;
+            let code: ParsedCode = code
+                .try_into()
+                .map_err(|err: JsError| user_error!("{}: {}", path, err))?;
+            Ok((path, code))
+        })
+        .collect::<Result<BTreeMap<_, _>>>()?;
+    let modules = Modules(modules);
+
+    // Salted deployments have a known address before the operation is applied
+    if let Some(salt) = &salt {
+        let address = Script::salted_address(&user.address, salt, &code, &modules)?;
+        info!("Smart function will be deployed at address: {}", address);
     }
 
-    debug!("Code: {}", code);
-
-    let code: ParsedCode = code
-        .try_into()
-        .map_err(|err: JsError| user_error!("{err}"))?;
-    let op = Operation {
-        source: user.address.clone(),
-        nonce,
-        content: Content::DeployFunction(DeployFunction {
-            function_code: code,
-            account_credit: balance,
-        }),
-    };
-
-    debug!("Operation: {:?}", op);
-
-    let hash = op.hash();
-
-    debug!("Operation hash: {}", hash.to_string());
-
-    let signed_op =
-        SignedOperation::new(user.public_key.clone(), user.secret_key.sign(&hash)?, op);
-
-    debug!("Signed operation: {:?}", signed_op);
+    let content = Content::DeployFunction(DeployFunction {
+        function_code: code,
+        account_credit: balance,
+        admin,
+        salt,
+        modules,
+    });
 
     // 3. Send operation to jstz-node
-    jstz_client.post_operation(&signed_op).await?;
-    let receipt = jstz_client.wait_for_operation_receipt(&hash).await?;
-
-    debug!("Receipt: {:?}", receipt);
+    let receipt = jstz_client
+        .post_operation_content(&user, content, valid_until_level)
+        .await?;
 
     let address = match receipt.inner {
         Ok(ReceiptContent::DeployFunction(deploy)) => deploy.address,
@@ -101,7 +116,7 @@ pub async fn exec(
             bail!("Expected a `DeployFunction` receipt, but got something else.")
         }
         Err(err) => {
-            bail_user_error!("Failed to deploy smart function with error {err:?}.")
+            bail_user_error!("Failed to deploy smart function with error {err}.")
         }
     };
 
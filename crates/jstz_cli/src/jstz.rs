@@ -4,15 +4,16 @@ use anyhow::{bail, Result};
 use jstz_api::KvValue;
 use jstz_proto::{
     context::account::{Address, Nonce},
-    operation::{OperationHash, SignedOperation},
+    operation::{Content, Operation, OperationHash, SignedOperation},
     receipt::Receipt,
 };
 use log::debug;
 use reqwest::StatusCode;
 use reqwest_eventsource::EventSource;
+use tezos_crypto_rs::hash::SmartRollupHash;
 use tokio::time::sleep;
 
-use crate::error::bail_user_error;
+use crate::{config::User, error::bail_user_error};
 
 pub struct JstzClient {
     endpoint: String,
@@ -71,6 +72,20 @@ impl JstzClient {
         }
     }
 
+    pub async fn get_rollup_address(&self) -> Result<SmartRollupHash> {
+        let response = self
+            .get(&format!("{}/operations/rollup_address", self.endpoint))
+            .await?;
+
+        match response.status() {
+            StatusCode::OK => {
+                let address = response.json::<String>().await?;
+                Ok(SmartRollupHash::from_base58_check(&address)?)
+            }
+            _ => bail!("Failed to get the rollup address"),
+        }
+    }
+
     pub async fn get_code(&self, address: &Address) -> Result<Option<String>> {
         let response = self
             .get(&format!("{}/accounts/{}/code", self.endpoint, address))
@@ -192,6 +207,49 @@ impl JstzClient {
         }
     }
 
+    /// Signs `content` as the next operation of `user`, posts it and waits for
+    /// its receipt. The operation expires after `valid_until_level`, if given.
+    pub async fn post_operation_content(
+        &self,
+        user: &User,
+        content: Content,
+        valid_until_level: Option<u32>,
+    ) -> Result<Receipt> {
+        let nonce = self.get_nonce(&user.address).await?;
+        let rollup_address = self.get_rollup_address().await?;
+
+        debug!("Nonce: {:?}", nonce);
+
+        let op = Operation {
+            source: user.address.clone(),
+            nonce,
+            content,
+            rollup_address,
+            valid_until_level,
+        };
+
+        debug!("Operation: {:?}", op);
+
+        let hash = op.hash();
+
+        debug!("Operation hash: {}", hash.to_string());
+
+        let signed_op = SignedOperation::new(
+            user.public_key.clone(),
+            user.secret_key.sign(&hash)?,
+            op,
+        );
+
+        debug!("Signed operation: {:?}", signed_op);
+
+        self.post_operation(&signed_op).await?;
+        let receipt = self.wait_for_operation_receipt(&hash).await?;
+
+        debug!("Receipt: {:?}", receipt);
+
+        Ok(receipt)
+    }
+
     async fn get(&self, url: &str) -> Result<reqwest::Response> {
         Ok(self.client.get(url).send().await?)
     }
@@ -2,6 +2,7 @@ use clap::Parser;
 use clap_complete::Shell;
 
 mod account;
+mod approve;
 mod bridge;
 mod completions;
 mod config;
@@ -15,6 +16,8 @@ mod repl;
 mod run;
 mod sandbox;
 mod term;
+mod transfer;
+mod upgrade;
 mod utils;
 
 use config::{Config, NetworkName};
@@ -37,6 +40,37 @@ enum Command {
         /// Name (or alias) of the function.
         #[arg(long, default_value = None)]
         name: Option<String>,
+        /// Address or alias of the account allowed to upgrade the function.
+        /// If undefined, the function cannot be upgraded.
+        #[arg(long, value_name = "ADDRESS|ALIAS", default_value = None)]
+        admin: Option<AddressOrAlias>,
+        /// Salt of the deployment. If given, the function is deployed at an address
+        /// determined by the deployer, the salt and the code, which is printed up front.
+        #[arg(long, default_value = None)]
+        salt: Option<String>,
+        /// Path of a module imported by the function, relative to the function's file.
+        /// Can be given several times.
+        #[arg(long = "module", value_name = "PATH")]
+        modules: Vec<String>,
+        /// Last level at which the operation can be included. If undefined, the operation never expires.
+        #[arg(long, value_name = "LEVEL", default_value = None)]
+        valid_until_level: Option<u32>,
+        /// Specifies the network from the config file, defaulting to the configured default network.
+        /// Use `dev` for the local sandbox.
+        #[arg(short, long, default_value = None)]
+        network: Option<NetworkName>,
+    },
+    /// ⬆️  Upgrades the code of a deployed smart function
+    Upgrade {
+        /// Address or alias of the smart function.
+        #[arg(value_name = "ADDRESS|ALIAS")]
+        address: AddressOrAlias,
+        /// New function code.
+        #[arg(value_name = "CODE|PATH", default_value = None, value_hint = clap::ValueHint::FilePath)]
+        code: Option<String>,
+        /// Last level at which the operation can be included. If undefined, the operation never expires.
+        #[arg(long, value_name = "LEVEL", default_value = None)]
+        valid_until_level: Option<u32>,
         /// Specifies the network from the config file, defaulting to the configured default network.
         /// Use `dev` for the local sandbox.
         #[arg(short, long, default_value = None)]
@@ -56,6 +90,9 @@ enum Command {
         /// The JSON data in the request body.
         #[arg(name = "data", short, long, default_value = None, value_hint = clap::ValueHint::FilePath)]
         json_data: Option<String>,
+        /// Last level at which the operation can be included. If undefined, the operation never expires.
+        #[arg(long, value_name = "LEVEL", default_value = None)]
+        valid_until_level: Option<u32>,
         /// Specifies the network from the config file, defaulting to the configured default network.
         ///  Use `dev` for the local sandbox.
         #[arg(short, long, default_value = None)]
@@ -64,6 +101,41 @@ enum Command {
         #[arg(short, long)]
         trace: bool,
     },
+    /// 💸 Transfers CTEZ from the current jstz account to another jstz account
+    Transfer {
+        /// jstz address or alias to transfer to.
+        #[arg(value_name = "ADDRESS|ALIAS")]
+        to: AddressOrAlias,
+        /// The amount in CTEZ to transfer.
+        #[arg(short, long)]
+        amount: u64,
+        /// Last level at which the operation can be included. If undefined, the operation never expires.
+        #[arg(long, value_name = "LEVEL", default_value = None)]
+        valid_until_level: Option<u32>,
+        /// Specifies the network from the config file, defaulting to the configured default network.
+        /// Use `dev` for the local sandbox.
+        #[arg(short, long, default_value = None)]
+        network: Option<NetworkName>,
+    },
+    /// ✅ Allows an account or smart function to transfer funds from the current jstz account
+    Approve {
+        /// jstz address or alias of the spender.
+        #[arg(value_name = "ADDRESS|ALIAS")]
+        spender: AddressOrAlias,
+        /// The amount the spender may transfer, replacing any previous allowance.
+        #[arg(short, long)]
+        amount: u64,
+        /// The asset of the allowance, defaulting to CTEZ.
+        #[arg(long, value_name = "ASSET", default_value = None)]
+        asset: Option<String>,
+        /// Last level at which the operation can be included. If undefined, the operation never expires.
+        #[arg(long, value_name = "LEVEL", default_value = None)]
+        valid_until_level: Option<u32>,
+        /// Specifies the network from the config file, defaulting to the configured default network.
+        /// Use `dev` for the local sandbox.
+        #[arg(short, long, default_value = None)]
+        network: Option<NetworkName>,
+    },
     /// 🌉 Move CTEZ between L1 and jstz with the jstz bridge {n}
     #[command(subcommand)]
     Bridge(bridge::Command),
@@ -115,22 +187,69 @@ async fn exec(command: Command) -> Result<()> {
         Command::Docs => docs::exec(),
         Command::Completions { shell } => completions::exec(shell),
         Command::Sandbox(sandbox_command) => sandbox::exec(sandbox_command).await,
-        Command::Bridge(bridge_command) => bridge::exec(bridge_command),
+        Command::Bridge(bridge_command) => bridge::exec(bridge_command).await,
         Command::Account(account_command) => account::exec(account_command).await,
         Command::Deploy {
             code,
             balance,
             name,
+            admin,
+            salt,
+            modules,
+            valid_until_level,
+            network,
+        } => {
+            deploy::exec(
+                code,
+                balance,
+                name,
+                admin,
+                salt,
+                modules,
+                valid_until_level,
+                network,
+            )
+            .await
+        }
+        Command::Upgrade {
+            address,
+            code,
+            valid_until_level,
             network,
-        } => deploy::exec(code, balance, name, network).await,
+        } => upgrade::exec(address, code, valid_until_level, network).await,
         Command::Run {
             url,
             http_method,
             gas_limit,
             json_data,
+            valid_until_level,
             network,
             trace,
-        } => run::exec(url, http_method, gas_limit, json_data, network, trace).await,
+        } => {
+            run::exec(
+                url,
+                http_method,
+                gas_limit,
+                json_data,
+                valid_until_level,
+                network,
+                trace,
+            )
+            .await
+        }
+        Command::Transfer {
+            to,
+            amount,
+            valid_until_level,
+            network,
+        } => transfer::exec(to, amount, valid_until_level, network).await,
+        Command::Approve {
+            spender,
+            amount,
+            asset,
+            valid_until_level,
+            network,
+        } => approve::exec(spender, amount, asset, valid_until_level, network).await,
         Command::Repl { account } => repl::exec(account),
         Command::Logs(logs) => logs::exec(logs).await,
         Command::Login { alias } => account::login(alias),
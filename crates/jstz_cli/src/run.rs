@@ -5,7 +5,7 @@ use anyhow::bail;
 use http::{HeaderMap, Method, Uri};
 use jstz_proto::context::account::Address;
 use jstz_proto::{
-    operation::{Content as OperationContent, Operation, RunFunction, SignedOperation},
+    operation::{Content as OperationContent, RunFunction},
     receipt::Content as ReceiptContent,
 };
 use log::{debug, info};
@@ -32,6 +32,7 @@ pub async fn exec(
     http_method: String,
     gas_limit: u32,
     json_data: Option<String>,
+    valid_until_level: Option<u32>,
     network: Option<NetworkName>,
     trace: bool,
 ) -> Result<()> {
@@ -69,9 +70,7 @@ pub async fn exec(
 
     debug!("Resolved URL: {}", url_object.to_string());
 
-    // 3. Construct the signed operation
-    let nonce = jstz_client.get_nonce(&user.address).await?;
-
+    // 3. Construct the operation
     // SAFETY: `url` is a valid URI since URLs are a subset of  URIs and `url_object` is a valid URL.
     let url: Uri = url_object
         .to_string()
@@ -87,30 +86,15 @@ pub async fn exec(
 
     debug!("Body: {:?}", body);
 
-    let op = Operation {
-        source: user.address.clone(),
-        nonce,
-        content: OperationContent::RunFunction(RunFunction {
-            uri: url,
-            method,
-            headers: HeaderMap::default(),
-            body,
-            gas_limit: gas_limit
-                .try_into()
-                .map_err(|_| anyhow!("Invalid gas limit."))?,
-        }),
-    };
-
-    debug!("Operation: {:?}", op);
-
-    let hash = op.hash();
-
-    debug!("Operation hash: {}", hash.to_string());
-
-    let signed_op =
-        SignedOperation::new(user.public_key.clone(), user.secret_key.sign(&hash)?, op);
-
-    debug!("Signed operation: {:?}", signed_op);
+    let content = OperationContent::RunFunction(RunFunction {
+        uri: url,
+        method,
+        headers: HeaderMap::default(),
+        body,
+        gas_limit: gas_limit
+            .try_into()
+            .map_err(|_| anyhow!("Invalid gas limit."))?,
+    });
 
     // 4. Send message to jstz node
     println!(
@@ -129,31 +113,63 @@ pub async fn exec(
         spawn_trace(&address, &jstz_client).await?;
     }
 
-    jstz_client.post_operation(&signed_op).await?;
-    let receipt = jstz_client.wait_for_operation_receipt(&hash).await?;
+    let receipt = jstz_client
+        .post_operation_content(user, content, valid_until_level)
+        .await?;
 
-    debug!("Receipt: {:?}", receipt);
-    let (status_code, headers, body) = match receipt.inner {
-        Ok(ReceiptContent::RunFunction(run_function)) => (
-            run_function.status_code,
-            run_function.headers,
-            run_function.body,
-        ),
+    let run_function = match receipt.inner {
+        Ok(ReceiptContent::RunFunction(run_function)) => run_function,
         Ok(_) => bail!("Expected a `RunFunction` receipt, but got something else."),
 
         Err(err) => bail_user_error!("{err}"),
     };
 
+    let status_code = run_function.status_code;
     if let Some(spinner) = spinner.as_mut() {
         spinner.stop_with_symbol(&format!("Status code: {}", status_code));
     } else {
         info!("Status code: {}", status_code);
     }
 
-    info!("Headers: {:?}", headers);
-    if let Some(body) = body {
+    info!("Headers: {:?}", run_function.headers);
+    if let Some(body) = run_function.body {
         info!("Body: {}", String::from_utf8_lossy(&body));
     }
+    info!("Gas used: {}", run_function.gas_used);
+
+    for address in run_function.calls {
+        info!("Called: {}", address);
+    }
+    for kv_write in run_function.kv_writes {
+        info!("KV write: {} {}", kv_write.address, kv_write.key);
+    }
+    for transfer in run_function.transfers {
+        match transfer.asset {
+            Some(asset) => info!(
+                "Transfer: {} -> {} ({} {})",
+                transfer.from, transfer.to, transfer.amount, asset
+            ),
+            None => info!(
+                "Transfer: {} -> {} ({} mutez)",
+                transfer.from, transfer.to, transfer.amount
+            ),
+        }
+    }
+    for approval in run_function.approvals {
+        match approval.asset {
+            Some(asset) => info!(
+                "Approval: {} -> {} ({} {})",
+                approval.owner, approval.spender, approval.amount, asset
+            ),
+            None => info!(
+                "Approval: {} -> {} ({} mutez)",
+                approval.owner, approval.spender, approval.amount
+            ),
+        }
+    }
+    for event in run_function.events {
+        info!("Event: {} {} {}", event.address, event.topic, event.payload);
+    }
 
     cfg.save()?;
 
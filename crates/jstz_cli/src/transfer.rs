@@ -0,0 +1,59 @@
+use jstz_proto::{
+    operation::{Content, Transfer},
+    receipt::Content as ReceiptContent,
+};
+use log::info;
+
+use crate::{
+    account,
+    config::{Config, NetworkName},
+    error::{bail, bail_user_error, Result},
+    utils::AddressOrAlias,
+};
+
+pub async fn exec(
+    to: AddressOrAlias,
+    amount: u64,
+    valid_until_level: Option<u32>,
+    network: Option<NetworkName>,
+) -> Result<()> {
+    let mut cfg = Config::load()?;
+
+    if amount == 0 {
+        bail_user_error!("The amount to transfer must be greater than 0.");
+    }
+
+    let (user_name, user) = account::login_for_operation(&mut cfg, &network)?;
+
+    let destination = to.resolve(&cfg)?;
+
+    let jstz_client = cfg.jstz_client(&network)?;
+
+    let receipt = jstz_client
+        .post_operation_content(
+            &user,
+            Content::Transfer(Transfer {
+                destination: destination.clone(),
+                amount,
+            }),
+            valid_until_level,
+        )
+        .await?;
+
+    match receipt.inner {
+        Ok(ReceiptContent::Transfer(_)) => (),
+        Ok(_) => {
+            bail!("Expected a `Transfer` receipt, but got something else.")
+        }
+        Err(err) => {
+            bail_user_error!("Failed to transfer CTEZ with error {err}.")
+        }
+    };
+
+    info!(
+        "Transferred {} CTEZ from {} to {}",
+        amount, user_name, destination
+    );
+
+    Ok(())
+}
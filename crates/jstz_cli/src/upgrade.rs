@@ -0,0 +1,56 @@
+use jstz_proto::{
+    operation::{Content, UpgradeFunction},
+    receipt::Content as ReceiptContent,
+};
+use log::info;
+
+use crate::{
+    account,
+    config::{Config, NetworkName},
+    deploy::{read_code, MAX_CODE_LENGTH},
+    error::{bail, bail_user_error, Result},
+    utils::AddressOrAlias,
+};
+
+pub async fn exec(
+    address_or_alias: AddressOrAlias,
+    code_op: Option<String>,
+    valid_until_level: Option<u32>,
+    network: Option<NetworkName>,
+) -> Result<()> {
+    let mut cfg = Config::load()?;
+    let (user_name, user) = account::login_for_operation(&mut cfg, &network)?;
+
+    let address = address_or_alias.resolve(&cfg)?;
+
+    // Upgrades are not revealed from preimages, so the code must fit in an
+    // external message
+    let code = read_code(code_op, MAX_CODE_LENGTH)?;
+
+    let content = Content::UpgradeFunction(UpgradeFunction {
+        address: address.clone(),
+        function_code: code,
+    });
+
+    let receipt = cfg
+        .jstz_client(&network)?
+        .post_operation_content(&user, content, valid_until_level)
+        .await?;
+
+    let version = match receipt.inner {
+        Ok(ReceiptContent::UpgradeFunction(upgrade)) => upgrade.version,
+        Ok(_) => {
+            bail!("Expected an `UpgradeFunction` receipt, but got something else.")
+        }
+        Err(err) => {
+            bail_user_error!("Failed to upgrade smart function with error {err}.")
+        }
+    };
+
+    info!(
+        "Smart function at address {} upgraded by {} to version {}",
+        address, user_name, version
+    );
+
+    Ok(())
+}
@@ -24,7 +24,10 @@ impl FromStr for AddressOrAlias {
     type Err = Error;
 
     fn from_str(address_or_alias: &str) -> Result<Self> {
-        if address_or_alias.starts_with("tz1") {
+        if ["tz1", "tz2", "tz3", "KT1"]
+            .iter()
+            .any(|prefix| address_or_alias.starts_with(prefix))
+        {
             Ok(Self::Address(address_or_alias.parse()?))
         } else {
             Ok(Self::Alias(address_or_alias.to_string()))
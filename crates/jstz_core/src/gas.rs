@@ -0,0 +1,240 @@
+//! Gas metering
+//!
+//! Script execution is metered by boa's instruction counter, where each
+//! instruction costs 1 unit of gas. Host APIs additionally charge gas according
+//! to the [`schedule`], proportionally to the number of bytes they touch.
+//! Both are accounted for against the same gas limit.
+
+use std::cell::RefCell;
+
+use boa_engine::{Context, JsNativeError, JsResult};
+
+/// The cost of a host API call: a fixed base cost plus a cost per byte touched
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GasCost {
+    pub base: usize,
+    pub per_byte: usize,
+}
+
+impl GasCost {
+    pub const fn new(base: usize, per_byte: usize) -> Self {
+        Self { base, per_byte }
+    }
+
+    pub fn cost(&self, bytes: usize) -> usize {
+        self.base
+            .saturating_add(self.per_byte.saturating_mul(bytes))
+    }
+}
+
+/// Gas schedule of the host APIs
+pub mod schedule {
+    use super::GasCost;
+
+    pub const KV_GET: GasCost = GasCost::new(200, 2);
+    pub const KV_SET: GasCost = GasCost::new(400, 4);
+    pub const KV_DELETE: GasCost = GasCost::new(200, 2);
+    pub const KV_HAS: GasCost = GasCost::new(100, 2);
+
+    pub const LEDGER_BALANCE: GasCost = GasCost::new(100, 0);
+    pub const LEDGER_TRANSFER: GasCost = GasCost::new(300, 0);
+    pub const LEDGER_WITHDRAW: GasCost = GasCost::new(500, 0);
+    pub const LEDGER_APPROVE: GasCost = GasCost::new(200, 0);
+    pub const LEDGER_ALLOWANCE: GasCost = GasCost::new(100, 0);
+    pub const LEDGER_TRANSFER_FROM: GasCost = GasCost::new(400, 0);
+
+    pub const SMART_FUNCTION_CREATE: GasCost = GasCost::new(1000, 4);
+    pub const SMART_FUNCTION_UPGRADE: GasCost = GasCost::new(1000, 4);
+    pub const SMART_FUNCTION_CALL: GasCost = GasCost::new(500, 1);
+    pub const MODULE_IMPORT: GasCost = GasCost::new(500, 1);
+
+    pub const JSTZ_SCHEDULE: GasCost = GasCost::new(1000, 1);
+    pub const JSTZ_SCHEDULE_RUN: GasCost = GasCost::new(200, 0);
+    pub const JSTZ_EMIT: GasCost = GasCost::new(300, 2);
+
+    pub const TEXT_ENCODE: GasCost = GasCost::new(10, 1);
+    pub const TEXT_DECODE: GasCost = GasCost::new(10, 1);
+    pub const BODY_READ: GasCost = GasCost::new(10, 1);
+    pub const CONSOLE_LOG: GasCost = GasCost::new(10, 1);
+}
+
+#[derive(Debug)]
+struct GasMeter {
+    limit: usize,
+    host_gas_used: usize,
+    exhausted_by: Option<&'static str>,
+}
+
+/// Gas charged by host APIs during a metered execution
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct HostGas {
+    /// Gas charged by host APIs
+    pub used: usize,
+    /// The host API that exceeded the gas limit, if any
+    pub exhausted_by: Option<&'static str>,
+}
+
+thread_local! {
+    /// Thread-local gas meter
+    static GAS_METER: RefCell<Option<GasMeter>> = RefCell::new(None);
+}
+
+/// Runs `f`, metering the gas charged by host APIs against `limit`.
+/// `limit` should be the instruction limit of the context running the script.
+pub fn enter_gas_context<F, R>(limit: usize, f: F) -> (R, HostGas)
+where
+    F: FnOnce() -> R,
+{
+    GAS_METER.with(|meter| {
+        *meter.borrow_mut() = Some(GasMeter {
+            limit,
+            host_gas_used: 0,
+            exhausted_by: None,
+        })
+    });
+
+    let result = f();
+
+    let meter = GAS_METER
+        .with(|meter| meter.borrow_mut().take())
+        .expect("`GAS_METER` should be set");
+
+    (
+        result,
+        HostGas {
+            used: meter.host_gas_used,
+            exhausted_by: meter.exhausted_by,
+        },
+    )
+}
+
+fn gas_limit_exceeded(api: &str) -> JsNativeError {
+    JsNativeError::eval().with_message(format!("GasLimitExceeded: {}", api))
+}
+
+/// Charges the gas for a call to the host API `api` touching `bytes` bytes.
+///
+/// Fails if the gas used by the script so far exceeds the gas limit. Once the
+/// gas limit is exceeded, all subsequent charges fail. Outside of a gas
+/// context, charging is a no-op.
+pub fn charge(
+    context: &Context<'_>,
+    api: &'static str,
+    cost: GasCost,
+    bytes: usize,
+) -> JsResult<()> {
+    GAS_METER.with(|meter| {
+        let mut meter = meter.borrow_mut();
+        let Some(meter) = meter.as_mut() else {
+            return Ok(());
+        };
+
+        if let Some(api) = meter.exhausted_by {
+            return Err(gas_limit_exceeded(api).into());
+        }
+
+        meter.host_gas_used = meter.host_gas_used.saturating_add(cost.cost(bytes));
+
+        let instructions_used =
+            meter.limit.saturating_sub(context.instructions_remaining());
+        if instructions_used.saturating_add(meter.host_gas_used) > meter.limit {
+            meter.exhausted_by = Some(api);
+            return Err(gas_limit_exceeded(api).into());
+        }
+
+        Ok(())
+    })
+}
+
+/// Checks that the gas used by the script so far does not exceed the gas limit,
+/// reporting `api` as exceeding it otherwise.
+///
+/// Scripts can catch the errors of host APIs, so the effects of a script must
+/// only be committed once this check succeeds: it fails if any charge failed
+/// before, or if the script's instructions exceeded the gas left by the host
+/// APIs.
+pub fn check(context: &Context<'_>, api: &'static str) -> JsResult<()> {
+    charge(context, api, GasCost::new(0, 0), 0)
+}
+
+#[cfg(test)]
+mod test {
+    use boa_engine::Context;
+
+    use super::*;
+
+    const COST: GasCost = GasCost::new(10, 1);
+
+    fn context(limit: usize) -> Context<'static> {
+        Context::builder()
+            .instructions_remaining(limit)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn charge_is_noop_outside_gas_context() {
+        let context = context(0);
+
+        assert!(charge(&context, "test", COST, 1000).is_ok());
+    }
+
+    #[test]
+    fn charges_are_proportional_to_bytes() {
+        let context = context(100);
+
+        let (result, host_gas) = enter_gas_context(100, || {
+            charge(&context, "a", COST, 5)?;
+            charge(&context, "b", COST, 20)
+        });
+
+        assert!(result.is_ok());
+        assert_eq!(
+            host_gas,
+            HostGas {
+                used: 45,
+                exhausted_by: None
+            }
+        );
+    }
+
+    #[test]
+    fn exceeding_limit_reports_api() {
+        let context = context(100);
+
+        let (result, host_gas) = enter_gas_context(100, || {
+            charge(&context, "a", COST, 50)?;
+            charge(&context, "b", COST, 50)
+        });
+
+        assert!(result.is_err());
+        assert_eq!(host_gas.exhausted_by, Some("b"));
+    }
+
+    #[test]
+    fn charges_fail_once_limit_exceeded() {
+        let context = context(100);
+
+        let (result, host_gas) = enter_gas_context(100, || {
+            let _ = charge(&context, "a", COST, 100);
+            charge(&context, "b", COST, 0)
+        });
+
+        assert!(result.is_err());
+        assert_eq!(host_gas.exhausted_by, Some("a"));
+    }
+
+    #[test]
+    fn check_fails_once_limit_exceeded() {
+        let context = context(100);
+
+        let (result, host_gas) = enter_gas_context(100, || {
+            check(&context, "check")?;
+            let _ = charge(&context, "a", COST, 100);
+            check(&context, "check")
+        });
+
+        assert!(result.is_err());
+        assert_eq!(host_gas.exhausted_by, Some("a"));
+    }
+}
@@ -0,0 +1,211 @@
+//! Execution journal
+//!
+//! Records what a script execution did: the smart functions it called, the
+//! KV keys it wrote, and the effects recorded by the crates built on `jstz_core`
+//! (e.g. ledger transfers), as opaque [`Record`]s. The journal mirrors the
+//! transaction structure of the execution, such that the effects (KV writes and
+//! records) recorded within a rolled back transaction are discarded.
+//! Calls are always kept, since the call took place regardless of its outcome.
+
+use std::{any::Any, cell::RefCell, fmt::Debug};
+
+/// A write (insertion or removal) of a key in a smart function's KV store
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KvWrite {
+    /// Address of the smart function owning the KV store
+    pub address: String,
+    pub key: String,
+}
+
+/// An effect recorded by [`record`], whose type is defined by the recording
+/// crate
+pub trait Record: Any + Debug {
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T> Record for T
+where
+    T: Any + Debug,
+{
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// The calls and committed effects recorded during a journaled execution
+#[derive(Debug, Default)]
+pub struct Journal {
+    /// Addresses of the smart functions called, in call order
+    pub calls: Vec<String>,
+    pub kv_writes: Vec<KvWrite>,
+    /// Effects recorded by [`record`], in recording order
+    pub records: Vec<Box<dyn Record>>,
+}
+
+impl Journal {
+    /// Returns the records of type `T`, in recording order
+    pub fn records<T: Record>(&self) -> impl Iterator<Item = &T> {
+        self.records
+            .iter()
+            .filter_map(|record| record.as_ref().as_any().downcast_ref::<T>())
+    }
+}
+
+#[derive(Debug, Default)]
+struct Recorder {
+    journal: Journal,
+    checkpoints: Vec<Checkpoint>,
+}
+
+/// The number of effects of each kind at the beginning of a transaction
+#[derive(Debug)]
+struct Checkpoint {
+    kv_writes: usize,
+    records: usize,
+}
+
+thread_local! {
+    /// Thread-local journal recorder
+    static RECORDER: RefCell<Option<Recorder>> = RefCell::new(None);
+}
+
+/// Runs `f`, recording the journal of its execution.
+pub fn enter_journal_context<F, R>(f: F) -> (R, Journal)
+where
+    F: FnOnce() -> R,
+{
+    RECORDER.with(|recorder| *recorder.borrow_mut() = Some(Recorder::default()));
+
+    let result = f();
+
+    let recorder = RECORDER
+        .with(|recorder| recorder.borrow_mut().take())
+        .expect("`RECORDER` should be set");
+
+    (result, recorder.journal)
+}
+
+fn with_recorder(f: impl FnOnce(&mut Recorder)) {
+    RECORDER.with(|recorder| {
+        if let Some(recorder) = recorder.borrow_mut().as_mut() {
+            f(recorder)
+        }
+    })
+}
+
+/// Marks the beginning of a transaction. Outside of a journal context, this is
+/// a no-op (as are all other recording functions).
+pub fn begin() {
+    with_recorder(|recorder| {
+        let checkpoint = Checkpoint {
+            kv_writes: recorder.journal.kv_writes.len(),
+            records: recorder.journal.records.len(),
+        };
+        recorder.checkpoints.push(checkpoint)
+    })
+}
+
+/// Marks the commit of the current transaction, keeping its effects.
+pub fn commit() {
+    with_recorder(|recorder| {
+        recorder.checkpoints.pop();
+    })
+}
+
+/// Marks the rollback of the current transaction, discarding its effects.
+pub fn rollback() {
+    with_recorder(|recorder| {
+        if let Some(checkpoint) = recorder.checkpoints.pop() {
+            recorder.journal.kv_writes.truncate(checkpoint.kv_writes);
+            recorder.journal.records.truncate(checkpoint.records);
+        }
+    })
+}
+
+/// Records a call to the smart function at `address`
+pub fn record_call(address: impl ToString) {
+    with_recorder(|recorder| recorder.journal.calls.push(address.to_string()))
+}
+
+/// Records a write of `key` in the KV store of the smart function at `address`
+pub fn record_kv_write(address: impl ToString, key: impl ToString) {
+    with_recorder(|recorder| {
+        recorder.journal.kv_writes.push(KvWrite {
+            address: address.to_string(),
+            key: key.to_string(),
+        })
+    })
+}
+
+/// Records `record`, an effect of the current transaction
+pub fn record(record: impl Record) {
+    with_recorder(|recorder| recorder.journal.records.push(Box::new(record)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct Effect(&'static str);
+
+    #[test]
+    fn recording_is_noop_outside_journal_context() {
+        record_call("a");
+        begin();
+        record_kv_write("a", "k");
+
+        let ((), journal) = enter_journal_context(|| ());
+
+        assert!(journal.calls.is_empty());
+        assert!(journal.kv_writes.is_empty());
+    }
+
+    #[test]
+    fn rollback_discards_effects_but_keeps_calls() {
+        let ((), journal) = enter_journal_context(|| {
+            begin();
+            record_call("a");
+            record_kv_write("a", "kept");
+
+            begin();
+            record_call("b");
+            record_kv_write("b", "discarded");
+            record(Effect("discarded"));
+            rollback();
+
+            record(Effect("kept"));
+            record(1u64);
+            commit();
+        });
+
+        assert_eq!(journal.calls, vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(
+            journal.kv_writes,
+            vec![KvWrite {
+                address: "a".to_string(),
+                key: "kept".to_string()
+            }]
+        );
+        assert_eq!(
+            journal.records::<Effect>().collect::<Vec<_>>(),
+            vec![&Effect("kept")]
+        );
+        assert_eq!(journal.records::<u64>().collect::<Vec<_>>(), vec![&1]);
+    }
+
+    #[test]
+    fn committed_nested_effects_are_discarded_by_outer_rollback() {
+        let ((), journal) = enter_journal_context(|| {
+            begin();
+            begin();
+            record_kv_write("a", "k");
+            record(Effect("k"));
+            commit();
+            rollback();
+        });
+
+        assert!(journal.kv_writes.is_empty());
+        assert!(journal.records.is_empty());
+    }
+}
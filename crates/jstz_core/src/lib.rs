@@ -4,8 +4,10 @@ use boa_engine::Context;
 
 pub use error::{Error, Result};
 pub mod future;
+pub mod gas;
 pub mod host;
 pub mod iterators;
+pub mod journal;
 pub mod js_fn;
 pub mod kv;
 pub mod native;
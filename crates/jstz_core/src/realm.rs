@@ -1,6 +1,6 @@
 use std::{
     any::{Any, TypeId},
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     io::Read,
     ops::{Deref, DerefMut},
 };
@@ -8,14 +8,17 @@ use std::{
 pub use boa_engine::realm;
 use boa_engine::{
     js_string,
+    module::{ModuleLoader, Referrer},
     object::{builtins::JsPromise, NativeObject, ObjectInitializer},
     property::Attribute,
-    Context, JsObject, JsResult, JsValue, Source,
+    Context, JsNativeError, JsObject, JsResult, JsString, JsValue, Source,
 };
 use boa_gc::{empty_trace, Finalize, GcRef, GcRefCell, GcRefMut, Trace};
 use derive_more::{Deref, DerefMut, From};
+use jstz_crypto::hash::Blake2b;
 
 use crate::{
+    gas::{self, schedule},
     native::{register_global_class, NativeClass},
     Api,
 };
@@ -322,3 +325,308 @@ impl Realm {
         Ok(realm)
     }
 }
+
+/// Resolves `specifier` relative to the module at path `referrer` in a bundle
+/// (`None` for the bundle's entrypoint). Returns `None` if the resolved path
+/// escapes the bundle.
+pub fn resolve_specifier(referrer: Option<&str>, specifier: &str) -> Option<String> {
+    let mut segments: Vec<&str> = referrer
+        .map(|referrer| referrer.split('/').collect())
+        .unwrap_or_default();
+    // The directory of the referrer
+    segments.pop();
+
+    for segment in specifier.split('/') {
+        match segment {
+            "" | "." => (),
+            ".." => {
+                segments.pop()?;
+            }
+            segment => segments.push(segment),
+        }
+    }
+
+    if segments.is_empty() {
+        return None;
+    }
+
+    Some(segments.join("/"))
+}
+
+/// Scheme of the specifiers of modules of other accounts (libraries), e.g.
+/// `tezos://KT1.../router.js`
+const LIBRARY_SCHEME: &str = "tezos://";
+
+/// A resolved module specifier: a path in the bundle, or a path in the bundle
+/// of a library (the empty path being the library's code)
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ModulePath {
+    Bundle(String),
+    Library { address: String, path: String },
+}
+
+impl ModulePath {
+    /// Parses the key of a loaded module
+    fn from_key(key: &str) -> Self {
+        match key.strip_prefix(LIBRARY_SCHEME) {
+            Some(rest) => {
+                let (address, path) = rest.split_once('/').unwrap_or((rest, ""));
+                Self::Library {
+                    address: address.to_string(),
+                    path: path.to_string(),
+                }
+            }
+            None => Self::Bundle(key.to_string()),
+        }
+    }
+
+    fn key(&self) -> String {
+        match self {
+            Self::Bundle(path) => path.clone(),
+            Self::Library { address, path } => {
+                format!("{}{}/{}", LIBRARY_SCHEME, address, path)
+            }
+        }
+    }
+
+    /// Resolves `specifier` imported by the module at `referrer` (`None` for the
+    /// entrypoint). Returns the path and the code hash the module is pinned to,
+    /// if any.
+    fn resolve(
+        referrer: Option<&Self>,
+        specifier: &str,
+    ) -> Option<(Self, Option<String>)> {
+        if let Some(rest) = specifier.strip_prefix(LIBRARY_SCHEME) {
+            let (rest, hash) = match rest.split_once("?hash=") {
+                Some((rest, hash)) => (rest, Some(hash.to_string())),
+                None => (rest, None),
+            };
+            let (address, path) = rest.split_once('/').unwrap_or((rest, ""));
+            let path = match path {
+                "" => String::new(),
+                path => resolve_specifier(None, path)?,
+            };
+            let library = Self::Library {
+                address: address.to_string(),
+                path,
+            };
+            return Some((library, hash));
+        }
+
+        if !(specifier.starts_with("./") || specifier.starts_with("../")) {
+            return None;
+        }
+
+        // Relative imports of a library resolve within the library's bundle
+        let path = match referrer {
+            Some(Self::Library { address, path }) => Self::Library {
+                address: address.clone(),
+                path: resolve_specifier(Some(path), specifier)?,
+            },
+            Some(Self::Bundle(path)) => {
+                Self::Bundle(resolve_specifier(Some(path), specifier)?)
+            }
+            None => Self::Bundle(resolve_specifier(None, specifier)?),
+        };
+        Some((path, None))
+    }
+}
+
+/// Loads the source of the module at `path` in the bundle of the account at
+/// `address` (its code if `path` is empty)
+pub type LibraryLoader = fn(address: &str, path: &str) -> JsResult<String>;
+
+#[derive(Trace, Finalize)]
+struct LoadedModule {
+    key: String,
+    /// Hash of the module's source
+    hash: String,
+    module: boa_engine::Module,
+}
+
+/// The modules importable by the modules of a realm, by path. Stored in the
+/// realm's [`HostDefined`] and used by [`BundleModuleLoader`].
+#[derive(Trace, Finalize)]
+pub struct ModuleBundle {
+    sources: BTreeMap<String, String>,
+    #[unsafe_ignore_trace]
+    library_loader: Option<LibraryLoader>,
+    // Modules are only parsed once per realm, such that a module imported by
+    // several modules is evaluated once
+    loaded: Vec<LoadedModule>,
+}
+
+impl ModuleBundle {
+    pub fn new(sources: BTreeMap<String, String>) -> Self {
+        Self {
+            sources,
+            library_loader: None,
+            loaded: Vec::new(),
+        }
+    }
+
+    /// Allows importing the modules of other accounts with `tezos://` specifiers
+    pub fn with_library_loader(mut self, library_loader: LibraryLoader) -> Self {
+        self.library_loader = Some(library_loader);
+        self
+    }
+
+    fn key(&self, module: &boa_engine::Module) -> Option<&str> {
+        self.loaded
+            .iter()
+            .find(|loaded| &loaded.module == module)
+            .map(|loaded| loaded.key.as_str())
+    }
+
+    fn loaded(&self, key: &str) -> Option<&LoadedModule> {
+        self.loaded.iter().find(|loaded| loaded.key == key)
+    }
+
+    fn source(&self, path: &ModulePath) -> JsResult<Option<String>> {
+        match path {
+            ModulePath::Bundle(path) => Ok(self.sources.get(path).cloned()),
+            ModulePath::Library { address, path } => match self.library_loader {
+                Some(library_loader) => library_loader(address, path).map(Some),
+                None => Ok(None),
+            },
+        }
+    }
+}
+
+/// A module loader resolving the imports of the modules of a realm:
+///  - relative imports (`./` and `../`) to the modules of the realm's
+///    [`ModuleBundle`]
+///  - `tezos://<address>/<path>` imports to the modules of other accounts
+///    (libraries), optionally pinned to the hash of their code with a
+///    `?hash=<hex>` suffix. Libraries are evaluated in the importing realm,
+///    so they cannot access the state of their own account.
+#[derive(Debug, Default)]
+pub struct BundleModuleLoader;
+
+impl BundleModuleLoader {
+    fn check_pin(specifier: &str, pin: Option<&str>, hash: &str) -> JsResult<()> {
+        match pin {
+            Some(pin) if pin != hash => Err(JsNativeError::error()
+                .with_message(format!(
+                    "Module '{}' does not match its pinned hash",
+                    specifier
+                ))
+                .into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn load(
+        referrer: Referrer,
+        specifier: JsString,
+        context: &mut Context<'_>,
+    ) -> JsResult<boa_engine::Module> {
+        let specifier = specifier.to_std_string_escaped();
+        let not_found = || {
+            JsNativeError::error()
+                .with_message(format!("Cannot find module '{}'", specifier))
+        };
+
+        let (path, pin, source) = {
+            host_defined!(context, host_defined);
+            let bundle = host_defined.get::<ModuleBundle>().ok_or_else(not_found)?;
+
+            let referrer = match &referrer {
+                Referrer::Module(module) => bundle.key(module).map(ModulePath::from_key),
+                _ => None,
+            };
+            let (path, pin) = ModulePath::resolve(referrer.as_ref(), &specifier)
+                .ok_or_else(not_found)?;
+
+            if let Some(loaded) = bundle.loaded(&path.key()) {
+                Self::check_pin(&specifier, pin.as_deref(), &loaded.hash)?;
+                return Ok(loaded.module.clone());
+            }
+
+            let source = bundle.source(&path)?.ok_or_else(not_found)?;
+            (path, pin, source)
+        };
+
+        if matches!(path, ModulePath::Library { .. }) {
+            gas::charge(context, "import", schedule::MODULE_IMPORT, source.len())?;
+        }
+
+        let hash = Blake2b::from(source.as_bytes()).to_string();
+        Self::check_pin(&specifier, pin.as_deref(), &hash)?;
+
+        let realm = context.realm().clone();
+        let module =
+            boa_engine::Module::parse(Source::from_bytes(&source), Some(realm), context)?;
+
+        host_defined!(context, host_defined);
+        if let Some(mut bundle) = host_defined.get_mut::<ModuleBundle>() {
+            bundle.loaded.push(LoadedModule {
+                key: path.key(),
+                hash,
+                module: module.clone(),
+            });
+        }
+
+        Ok(module)
+    }
+}
+
+impl ModuleLoader for BundleModuleLoader {
+    fn load_imported_module(
+        &self,
+        referrer: Referrer,
+        specifier: JsString,
+        finish_load: Box<dyn FnOnce(JsResult<boa_engine::Module>, &mut Context<'_>)>,
+        context: &mut Context<'_>,
+    ) {
+        let result = Self::load(referrer, specifier, context);
+        finish_load(result, context);
+    }
+}
+
+pub const MODULE_LOADER: &'static dyn ModuleLoader = &BundleModuleLoader;
+
+#[cfg(test)]
+mod test {
+    use super::{resolve_specifier, ModulePath};
+
+    #[test]
+    fn resolve_specifier_relative_to_referrer() {
+        assert_eq!(
+            resolve_specifier(None, "./lib/math.js"),
+            Some("lib/math.js".to_string())
+        );
+        assert_eq!(
+            resolve_specifier(Some("lib/math.js"), "./round.js"),
+            Some("lib/round.js".to_string())
+        );
+        assert_eq!(
+            resolve_specifier(Some("lib/math.js"), "../util.js"),
+            Some("util.js".to_string())
+        );
+        assert_eq!(resolve_specifier(None, "../util.js"), None);
+    }
+
+    #[test]
+    fn resolve_library_imports() {
+        let library = |path: &str| ModulePath::Library {
+            address: "KT1".to_string(),
+            path: path.to_string(),
+        };
+
+        assert_eq!(
+            ModulePath::resolve(None, "tezos://KT1/lib/router.js?hash=ab"),
+            Some((library("lib/router.js"), Some("ab".to_string())))
+        );
+        assert_eq!(
+            ModulePath::resolve(None, "tezos://KT1"),
+            Some((library(""), None))
+        );
+        // Relative imports of a library resolve within the library
+        assert_eq!(
+            ModulePath::resolve(Some(&library("lib/router.js")), "./path.js"),
+            Some((library("lib/path.js"), None))
+        );
+        assert_eq!(ModulePath::resolve(None, "router"), None);
+    }
+}
@@ -1,5 +1,5 @@
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
     collections::VecDeque,
     future::poll_fn,
     io::Read,
@@ -21,11 +21,29 @@ use crate::{
     future,
     host::{HostRuntime, JsHostRuntime},
     kv::{JsTransaction, Transaction},
-    realm::{Module, Realm},
+    realm::{Module, Realm, MODULE_LOADER},
 };
 
 // This is the unix timestamp for date 31-07-2023 10:50:26 -- the date of the first commit
-const UTC_NOW: i64 = 1690797026;
+const DEFAULT_UTC_NOW: i64 = 1690797026;
+
+thread_local! {
+    /// Unix timestamp (in seconds) returned by `Date.now()`. This is the
+    /// timestamp of the current block, such that script execution remains
+    /// deterministic.
+    static UTC_NOW: Cell<i64> = Cell::new(DEFAULT_UTC_NOW);
+}
+
+/// Sets the unix timestamp (in seconds) observed by scripts as the current time
+pub fn set_utc_now(timestamp: i64) {
+    UTC_NOW.with(|utc_now| utc_now.set(timestamp))
+}
+
+/// Resets the time observed by scripts to the default, for executions outside
+/// of a block
+pub fn reset_utc_now() {
+    set_utc_now(DEFAULT_UTC_NOW)
+}
 
 struct Hooks;
 
@@ -49,7 +67,8 @@ impl HostHooks for Hooks {
     // }
 
     fn utc_now(&self) -> NaiveDateTime {
-        NaiveDateTime::from_timestamp_opt(UTC_NOW, 0)
+        let utc_now = UTC_NOW.with(Cell::get);
+        NaiveDateTime::from_timestamp_opt(utc_now, 0)
             .expect("Failed to create `NaiveDateTime` from `UTC_NOW`")
     }
 
@@ -221,6 +240,7 @@ impl<'host> Runtime<'host> {
         // NB: At this point, the context contains a 'default' realm
         let mut context = Context::builder()
             .host_hooks(HOOKS)
+            .module_loader(MODULE_LOADER)
             .job_queue(job_queue.clone() as Rc<dyn boa_engine::job::JobQueue>)
             .instructions_remaining(gas_limit)
             .build()?;
@@ -7,7 +7,12 @@ pub enum Error {
     TezosFromBase58Error { source: FromBase58CheckError },
     TezosFromBytesError { source: FromBytesError },
     TezosCryptoError { source: CryptoError },
+    Secp256k1Error { source: libsecp256k1::Error },
+    P256Error { source: p256::ecdsa::Error },
     InvalidSignature,
+    InvalidSecretKey,
+    InvalidPublicKey,
+    InvalidPublicKeyHash,
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -7,28 +7,141 @@ pub mod public_key_hash;
 pub mod secret_key;
 pub mod signature;
 
-use tezos_crypto_rs::hash::SeedEd25519;
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use tezos_crypto_rs::hash::{HashTrait, PublicKeyP256, PublicKeySecp256k1, SeedEd25519};
 
 use crate::{hash::Blake2b, public_key::PublicKey, secret_key::SecretKey};
 
+/// Signature schemes supported by jstz accounts
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum KeyType {
+    #[default]
+    Ed25519,
+    Secp256k1,
+    P256,
+}
+
 pub fn keypair_from_passphrase(passphrase: &str) -> Result<(SecretKey, PublicKey)> {
+    keypair_from_passphrase_with_key_type(KeyType::Ed25519, passphrase)
+}
+
+pub fn keypair_from_passphrase_with_key_type(
+    key_type: KeyType,
+    passphrase: &str,
+) -> Result<(SecretKey, PublicKey)> {
     let ikm = Blake2b::from(passphrase.as_bytes()).as_array().to_vec();
-    let seed = SeedEd25519::try_from(ikm)?;
-    let (pk, sk) = seed.keypair()?;
-    Ok((SecretKey::Ed25519(sk), PublicKey::Ed25519(pk)))
+    match key_type {
+        KeyType::Ed25519 => {
+            let seed = SeedEd25519::try_from(ikm)?;
+            let (pk, sk) = seed.keypair()?;
+            Ok((SecretKey::Ed25519(sk), PublicKey::Ed25519(pk)))
+        }
+        KeyType::Secp256k1 => {
+            let sk = libsecp256k1::SecretKey::parse_slice(&ikm)?;
+            let pk = libsecp256k1::PublicKey::from_secret_key(&sk);
+            let pk = PublicKeySecp256k1::try_from_bytes(&pk.serialize_compressed())?;
+            Ok((SecretKey::Secp256k1(sk), PublicKey::Secp256k1(pk)))
+        }
+        KeyType::P256 => {
+            let sk = p256::ecdsa::SigningKey::from_slice(&ikm)?;
+            let pk = sk.verifying_key().as_affine().to_encoded_point(true);
+            let pk = PublicKeyP256::try_from_bytes(pk.as_bytes())?;
+            Ok((SecretKey::P256(sk), PublicKey::P256(pk)))
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::keypair_from_passphrase;
+    use super::{keypair_from_passphrase_with_key_type, KeyType};
+    use crate::{
+        public_key::PublicKey, public_key_hash::PublicKeyHash, secret_key::SecretKey,
+        signature::Signature,
+    };
     use proptest::prelude::*;
 
+    fn key_type() -> impl Strategy<Value = KeyType> {
+        prop_oneof![
+            Just(KeyType::Ed25519),
+            Just(KeyType::Secp256k1),
+            Just(KeyType::P256)
+        ]
+    }
+
     proptest! {
         #[test]
-        fn test_keygen_verify(passphrase in any::<String>(), message in any::<Vec<u8>>()) {
-            let (sk, pk) = keypair_from_passphrase(&passphrase).unwrap();
+        fn test_keygen_verify(key_type in key_type(), passphrase in any::<String>(), message in any::<Vec<u8>>()) {
+            let (sk, pk) = keypair_from_passphrase_with_key_type(key_type, &passphrase).unwrap();
             let sig = sk.sign(&message).unwrap();
             assert!(sig.verify(&pk, &message).is_ok());
         }
+
+        #[test]
+        fn test_base58_roundtrip(key_type in key_type(), passphrase in any::<String>(), message in any::<Vec<u8>>()) {
+            let (sk, pk) = keypair_from_passphrase_with_key_type(key_type, &passphrase).unwrap();
+            let sig = sk.sign(&message).unwrap();
+            let pkh = PublicKeyHash::try_from(&pk).unwrap();
+
+            assert_eq!(SecretKey::from_base58(&sk.to_base58()).unwrap(), sk);
+            assert_eq!(PublicKey::from_base58(&pk.to_base58()).unwrap(), pk);
+            assert_eq!(Signature::from_base58(&sig.to_base58()).unwrap(), sig);
+            assert_eq!(PublicKeyHash::from_base58(&pkh.to_base58()).unwrap(), pkh);
+        }
+
+        #[test]
+        fn test_serde_roundtrip(key_type in key_type(), passphrase in any::<String>()) {
+            let (sk, _) = keypair_from_passphrase_with_key_type(key_type, &passphrase).unwrap();
+            let json = serde_json::to_value(&sk).unwrap();
+
+            assert_eq!(json.as_object().unwrap().values().next().unwrap(), &sk.to_base58());
+            assert_eq!(serde_json::from_value::<SecretKey>(json).unwrap(), sk);
+        }
+
+        #[test]
+        fn test_bytes_roundtrip(key_type in key_type(), passphrase in any::<String>()) {
+            let (_, pk) = keypair_from_passphrase_with_key_type(key_type, &passphrase).unwrap();
+            let pkh = PublicKeyHash::try_from(&pk).unwrap();
+            let address = PublicKeyHash::digest(passphrase.as_bytes()).unwrap();
+
+            assert_eq!(PublicKeyHash::from_slice(&pkh.to_bytes()).unwrap(), pkh);
+            assert_eq!(PublicKeyHash::from_slice(&address.to_bytes()).unwrap(), address);
+        }
+    }
+
+    #[test]
+    fn test_untagged_bytes_are_tz1() {
+        let (_, pk) =
+            keypair_from_passphrase_with_key_type(KeyType::Ed25519, "jstz").unwrap();
+        let pkh = PublicKeyHash::try_from(&pk).unwrap();
+
+        assert_eq!(PublicKeyHash::from_slice(pkh.as_bytes()).unwrap(), pkh);
+        assert!(PublicKeyHash::from_slice(&[4; 21]).is_err());
+    }
+
+    #[test]
+    fn test_public_key_hash_prefix() {
+        for (key_type, prefix) in [
+            (KeyType::Ed25519, "tz1"),
+            (KeyType::Secp256k1, "tz2"),
+            (KeyType::P256, "tz3"),
+        ] {
+            let (_, pk) =
+                keypair_from_passphrase_with_key_type(key_type, "jstz").unwrap();
+            let pkh = PublicKeyHash::try_from(&pk).unwrap();
+            assert!(pkh.to_base58().starts_with(prefix));
+            assert!(!pkh.is_smart_function());
+        }
+    }
+
+    #[test]
+    fn test_smart_function_address() {
+        let address = PublicKeyHash::digest(b"jstz").unwrap();
+
+        assert!(address.to_base58().starts_with("KT1"));
+        assert!(address.is_smart_function());
+        assert_eq!(
+            PublicKeyHash::from_base58(&address.to_base58()).unwrap(),
+            address
+        );
     }
 }
@@ -1,23 +1,37 @@
 use serde::{Deserialize, Serialize};
-use tezos_crypto_rs::hash::PublicKeyEd25519;
+use tezos_crypto_rs::hash::{PublicKeyEd25519, PublicKeyP256, PublicKeySecp256k1};
 
-use crate::error::Result;
+use crate::error::{Error, Result};
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
 pub enum PublicKey {
     Ed25519(PublicKeyEd25519),
+    Secp256k1(PublicKeySecp256k1),
+    P256(PublicKeyP256),
 }
 
 impl PublicKey {
     pub fn to_base58(&self) -> String {
-        let PublicKey::Ed25519(pk) = self;
-        pk.to_base58_check()
+        match self {
+            PublicKey::Ed25519(pk) => pk.to_base58_check(),
+            PublicKey::Secp256k1(pk) => pk.to_base58_check(),
+            PublicKey::P256(pk) => pk.to_base58_check(),
+        }
     }
 
     pub fn from_base58(data: &str) -> Result<Self> {
-        let pk = PublicKeyEd25519::from_base58_check(data)?;
+        let pk = match data.get(..4) {
+            Some("edpk") => {
+                PublicKey::Ed25519(PublicKeyEd25519::from_base58_check(data)?)
+            }
+            Some("sppk") => {
+                PublicKey::Secp256k1(PublicKeySecp256k1::from_base58_check(data)?)
+            }
+            Some("p2pk") => PublicKey::P256(PublicKeyP256::from_base58_check(data)?),
+            _ => return Err(Error::InvalidPublicKey),
+        };
 
-        Ok(PublicKey::Ed25519(pk))
+        Ok(pk)
     }
 }
 
@@ -3,8 +3,10 @@ use std::{fmt, str::FromStr};
 use boa_gc::{empty_trace, Finalize, Trace};
 use serde::{Deserialize, Serialize};
 use tezos_crypto_rs::{
-    blake2b::digest,
-    hash::{ContractTz1Hash, HashTrait},
+    blake2b::digest_160,
+    hash::{
+        ContractKt1Hash, ContractTz1Hash, ContractTz2Hash, ContractTz3Hash, HashTrait,
+    },
     PublicKeyWithHash,
 };
 
@@ -18,6 +20,12 @@ use crate::{
 )]
 pub enum PublicKeyHash {
     Tz1(ContractTz1Hash),
+    Tz2(ContractTz2Hash),
+    Tz3(ContractTz3Hash),
+    /// Address of a smart function. It is derived from the deployment of the
+    /// smart function rather than from a public key, so no key can sign on its
+    /// behalf.
+    Kt1(ContractKt1Hash),
 }
 
 unsafe impl Trace for PublicKeyHash {
@@ -34,28 +42,78 @@ impl FromStr for PublicKeyHash {
 
 impl PublicKeyHash {
     pub fn to_base58(&self) -> String {
-        let PublicKeyHash::Tz1(tz1) = self;
-        tz1.to_base58_check()
+        match self {
+            PublicKeyHash::Tz1(tz1) => tz1.to_base58_check(),
+            PublicKeyHash::Tz2(tz2) => tz2.to_base58_check(),
+            PublicKeyHash::Tz3(tz3) => tz3.to_base58_check(),
+            PublicKeyHash::Kt1(kt1) => kt1.to_base58_check(),
+        }
     }
 
     pub fn from_base58(data: &str) -> Result<Self> {
-        let tz1 = ContractTz1Hash::from_base58_check(data)?;
-        Ok(PublicKeyHash::Tz1(tz1))
+        let pkh = match data.get(..3) {
+            Some("tz1") => PublicKeyHash::Tz1(ContractTz1Hash::from_base58_check(data)?),
+            Some("tz2") => PublicKeyHash::Tz2(ContractTz2Hash::from_base58_check(data)?),
+            Some("tz3") => PublicKeyHash::Tz3(ContractTz3Hash::from_base58_check(data)?),
+            Some("KT1") => PublicKeyHash::Kt1(ContractKt1Hash::from_base58_check(data)?),
+            _ => return Err(Error::InvalidPublicKeyHash),
+        };
+
+        Ok(pkh)
     }
 
+    /// Decodes an address from its binary encoding (see [`Self::to_bytes`]).
+    /// An untagged hash is decoded as a tz1 address.
     pub fn from_slice(bytes: &[u8]) -> Result<Self> {
-        let tz1 = ContractTz1Hash::try_from_bytes(bytes)?;
-        Ok(PublicKeyHash::Tz1(tz1))
+        if bytes.len() == ContractTz1Hash::hash_size() {
+            return Ok(PublicKeyHash::Tz1(ContractTz1Hash::try_from_bytes(bytes)?));
+        }
+
+        let pkh = match bytes.split_first() {
+            Some((0, hash)) => PublicKeyHash::Tz1(ContractTz1Hash::try_from_bytes(hash)?),
+            Some((1, hash)) => PublicKeyHash::Tz2(ContractTz2Hash::try_from_bytes(hash)?),
+            Some((2, hash)) => PublicKeyHash::Tz3(ContractTz3Hash::try_from_bytes(hash)?),
+            Some((3, hash)) => PublicKeyHash::Kt1(ContractKt1Hash::try_from_bytes(hash)?),
+            _ => return Err(Error::InvalidPublicKeyHash),
+        };
+
+        Ok(pkh)
+    }
+
+    /// Binary encoding of the address: a tag byte (`0` for tz1, `1` for tz2,
+    /// `2` for tz3 and `3` for KT1) followed by the hash
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let tag = match self {
+            PublicKeyHash::Tz1(_) => 0,
+            PublicKeyHash::Tz2(_) => 1,
+            PublicKeyHash::Tz3(_) => 2,
+            PublicKeyHash::Kt1(_) => 3,
+        };
+
+        let mut bytes = vec![tag];
+        bytes.extend_from_slice(self.as_bytes());
+        bytes
     }
 
     pub fn as_bytes(&self) -> &[u8] {
-        let PublicKeyHash::Tz1(tz1) = self;
-        tz1.as_ref()
+        match self {
+            PublicKeyHash::Tz1(tz1) => tz1.as_ref(),
+            PublicKeyHash::Tz2(tz2) => tz2.as_ref(),
+            PublicKeyHash::Tz3(tz3) => tz3.as_ref(),
+            PublicKeyHash::Kt1(kt1) => kt1.as_ref(),
+        }
     }
+
+    /// Smart function address derived from `data`
     pub fn digest(data: &[u8]) -> Result<Self> {
-        let out_len = ContractTz1Hash::hash_size();
-        let bytes = digest(data, out_len).expect("failed to create hash");
-        Self::from_slice(&bytes)
+        let bytes = digest_160(data);
+        Ok(PublicKeyHash::Kt1(ContractKt1Hash::try_from_bytes(&bytes)?))
+    }
+
+    /// Returns `true` if the address is the address of a smart function
+    /// (rather than of an account holding a key)
+    pub fn is_smart_function(&self) -> bool {
+        matches!(self, PublicKeyHash::Kt1(_))
     }
 }
 
@@ -69,8 +127,18 @@ impl TryFrom<&PublicKey> for PublicKeyHash {
     type Error = Error;
 
     fn try_from(pk: &PublicKey) -> Result<Self> {
-        let PublicKey::Ed25519(key) = pk;
-        let tz1 = key.pk_hash();
-        Ok(PublicKeyHash::Tz1(tz1))
+        let pkh = match pk {
+            PublicKey::Ed25519(key) => PublicKeyHash::Tz1(key.pk_hash()),
+            PublicKey::Secp256k1(key) => {
+                let bytes = digest_160(key.as_ref());
+                PublicKeyHash::Tz2(ContractTz2Hash::try_from_bytes(&bytes)?)
+            }
+            PublicKey::P256(key) => {
+                let bytes = digest_160(key.as_ref());
+                PublicKeyHash::Tz3(ContractTz3Hash::try_from_bytes(&bytes)?)
+            }
+        };
+
+        Ok(pkh)
     }
 }
@@ -1,18 +1,29 @@
 use std::fmt::{self, Debug};
 
+use p256::ecdsa::signature::hazmat::PrehashSigner;
 use serde::{Deserialize, Serialize};
-use tezos_crypto_rs::hash::SecretKeyEd25519;
-use tezos_crypto_rs::hash::SeedEd25519;
+use tezos_crypto_rs::{
+    base58::{FromBase58Check, ToBase58Check},
+    hash::{HashTrait, P256Signature, Secp256k1Signature, SecretKeyEd25519},
+};
 
-use crate::{error::Result, signature::Signature};
+use crate::{
+    error::{Error, Result},
+    hash::Blake2b,
+    signature::Signature,
+};
 
-// FIXME: workaround via `SeedEd25519` will be unnecessary in the next tezos_crypto_rs release
-//        (will be included in next SDK release)
+// Base58 prefixes of `spsk` and `p2sk` encoded secret keys
+const SECP256K1_SECRET_KEY_PREFIX: [u8; 4] = [17, 162, 224, 201];
+const P256_SECRET_KEY_PREFIX: [u8; 4] = [16, 81, 238, 189];
+const SECRET_KEY_SIZE: usize = 32;
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Clone)]
-#[serde(from = "SecretKeySerde", into = "SecretKeySerde")]
+#[serde(try_from = "SecretKeySerde", into = "SecretKeySerde")]
 pub enum SecretKey {
     Ed25519(SecretKeyEd25519),
+    Secp256k1(libsecp256k1::SecretKey),
+    P256(p256::ecdsa::SigningKey),
 }
 
 impl Debug for SecretKey {
@@ -21,21 +32,70 @@ impl Debug for SecretKey {
     }
 }
 
+fn to_prefixed_base58(prefix: &[u8; 4], bytes: &[u8]) -> String {
+    let mut data = prefix.to_vec();
+    data.extend_from_slice(bytes);
+    data.to_base58check()
+}
+
+fn from_prefixed_base58(prefix: &[u8; 4], data: &str) -> Result<Vec<u8>> {
+    let bytes = data.from_base58check()?;
+    match bytes.strip_prefix(prefix) {
+        Some(sk) if sk.len() == SECRET_KEY_SIZE => Ok(sk.to_vec()),
+        _ => Err(Error::InvalidSecretKey),
+    }
+}
+
 impl SecretKey {
     pub fn to_base58(&self) -> String {
-        let Self::Ed25519(sk) = self;
-        sk.to_base58_check()
+        match self {
+            Self::Ed25519(sk) => sk.to_base58_check(),
+            Self::Secp256k1(sk) => {
+                to_prefixed_base58(&SECP256K1_SECRET_KEY_PREFIX, &sk.serialize())
+            }
+            Self::P256(sk) => to_prefixed_base58(&P256_SECRET_KEY_PREFIX, &sk.to_bytes()),
+        }
     }
 
     pub fn from_base58(data: &str) -> Result<Self> {
-        let sk = SecretKeyEd25519::from_base58_check(data)?;
+        let sk = match data.get(..4) {
+            Some("edsk") => {
+                SecretKey::Ed25519(SecretKeyEd25519::from_base58_check(data)?)
+            }
+            Some("spsk") => {
+                let bytes = from_prefixed_base58(&SECP256K1_SECRET_KEY_PREFIX, data)?;
+                SecretKey::Secp256k1(libsecp256k1::SecretKey::parse_slice(&bytes)?)
+            }
+            Some("p2sk") => {
+                let bytes = from_prefixed_base58(&P256_SECRET_KEY_PREFIX, data)?;
+                SecretKey::P256(p256::ecdsa::SigningKey::from_slice(&bytes)?)
+            }
+            _ => return Err(Error::InvalidSecretKey),
+        };
 
-        Ok(SecretKey::Ed25519(sk))
+        Ok(sk)
     }
 
+    /// Signs `message`. Secp256k1 and P256 signatures are computed over the
+    /// Blake2b digest of the message, as in Tezos.
     pub fn sign(&self, message: impl AsRef<[u8]>) -> Result<Signature> {
-        let SecretKey::Ed25519(sk) = self;
-        Ok(Signature::Ed25519(sk.sign(message)?))
+        match self {
+            SecretKey::Ed25519(sk) => Ok(Signature::Ed25519(sk.sign(message)?)),
+            SecretKey::Secp256k1(sk) => {
+                let digest = Blake2b::from(message.as_ref());
+                let message = libsecp256k1::Message::parse(digest.as_array());
+                let (sig, _) = libsecp256k1::sign(&message, sk);
+                let sig = Secp256k1Signature::try_from_bytes(&sig.serialize())?;
+                Ok(Signature::Secp256k1(sig))
+            }
+            SecretKey::P256(sk) => {
+                let digest = Blake2b::from(message.as_ref());
+                let sig: p256::ecdsa::Signature = sk.sign_prehash(digest.as_ref())?;
+                let sig = sig.normalize_s().unwrap_or(sig);
+                let sig = P256Signature::try_from_bytes(&sig.to_bytes())?;
+                Ok(Signature::P256(sig))
+            }
+        }
     }
 }
 
@@ -45,23 +105,32 @@ impl ToString for SecretKey {
     }
 }
 
+/// Secret keys of every type are serialized as their base58 encoding
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
 enum SecretKeySerde {
-    Ed25519(SeedEd25519),
+    Ed25519(String),
+    Secp256k1(String),
+    P256(String),
 }
 
 impl From<SecretKey> for SecretKeySerde {
     fn from(s: SecretKey) -> Self {
-        let SecretKey::Ed25519(sk) = s;
-        let sk: Vec<u8> = sk.into();
-        Self::Ed25519(SeedEd25519::try_from(sk).unwrap())
+        match s {
+            SecretKey::Ed25519(_) => Self::Ed25519(s.to_base58()),
+            SecretKey::Secp256k1(_) => Self::Secp256k1(s.to_base58()),
+            SecretKey::P256(_) => Self::P256(s.to_base58()),
+        }
     }
 }
 
-impl From<SecretKeySerde> for SecretKey {
-    fn from(s: SecretKeySerde) -> Self {
-        let SecretKeySerde::Ed25519(sk) = s;
-        let sk: Vec<u8> = sk.into();
-        Self::Ed25519(SecretKeyEd25519::try_from(sk).unwrap())
+impl TryFrom<SecretKeySerde> for SecretKey {
+    type Error = Error;
+
+    fn try_from(s: SecretKeySerde) -> Result<Self> {
+        match s {
+            SecretKeySerde::Ed25519(sk)
+            | SecretKeySerde::Secp256k1(sk)
+            | SecretKeySerde::P256(sk) => Self::from_base58(&sk),
+        }
     }
 }
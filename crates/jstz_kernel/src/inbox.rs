@@ -1,13 +1,28 @@
-use jstz_crypto::public_key_hash::PublicKeyHash;
-use jstz_proto::operation::{external::Deposit, ExternalOperation, SignedOperation};
+use jstz_crypto::{hash::Blake2b, public_key_hash::PublicKeyHash};
+use jstz_proto::{
+    context::{
+        account::Address,
+        asset::{Asset, TicketContent},
+        block::BlockInfo,
+    },
+    operation::{
+        external::{AssetDeposit, Deposit},
+        ChunkedOperationHeader, ExternalMessageContent, ExternalOperation,
+        OperationChunk, SignedOperation, MAX_CHUNKS,
+    },
+};
 use num_traits::ToPrimitive;
 use serde::{Deserialize, Serialize};
 use tezos_crypto_rs::hash::ContractKt1Hash;
 use tezos_smart_rollup::inbox::ExternalMessageFrame;
 use tezos_smart_rollup::{
     inbox::{InboxMessage, InternalInboxMessage, Transfer},
-    michelson::{ticket::UnitTicket, MichelsonBytes, MichelsonPair},
+    michelson::{
+        ticket::{Ticket, UnitTicket},
+        MichelsonBytes, MichelsonNat, MichelsonOption, MichelsonOr, MichelsonPair,
+    },
     prelude::{debug_msg, Runtime},
+    storage::path::{self, OwnedPath, RefPath},
     types::Contract,
 };
 
@@ -18,29 +33,45 @@ pub type InternalMessage = ExternalOperation;
 pub enum Message {
     External(ExternalMessage),
     Internal(InternalMessage),
+    /// Information on the current level, pushed by the Layer 1 at the
+    /// beginning of each level
+    BlockInfo(BlockInfo),
 }
 
+/// FA2.1 ticket content: token id and optional metadata
+type TokenTicketContent = MichelsonPair<MichelsonNat, MichelsonOption<MichelsonBytes>>;
+
 // reciever, ticket
-type RollupType = MichelsonPair<MichelsonBytes, UnitTicket>;
+type UnitDeposit = MichelsonPair<MichelsonBytes, UnitTicket>;
+type TokenDeposit = MichelsonPair<MichelsonBytes, Ticket<TokenTicketContent>>;
+
+/// The rollup's parameter type:
+/// `or (pair %default bytes (ticket unit)) (pair %deposit_token bytes (ticket (pair nat (option bytes))))`
+type RollupType = MichelsonOr<UnitDeposit, TokenDeposit>;
+
+/// The ticketers whose tickets can be deposited to jstz
+#[derive(Debug, Default)]
+pub struct Ticketers {
+    /// The ticketer of the native asset
+    pub native: Option<ContractKt1Hash>,
+    /// The ticketers of the other assets
+    pub allowlist: Vec<ContractKt1Hash>,
+}
+
+fn ticket_creator(contract: &Contract) -> Option<ContractKt1Hash> {
+    match contract {
+        Contract::Originated(kt1) => Some(kt1.clone()),
+        _ => None,
+    }
+}
 
 fn read_transfer(
     rt: &mut impl Runtime,
     transfer: Transfer<RollupType>,
-    ticketer: Option<&ContractKt1Hash>,
+    ticketers: &Ticketers,
 ) -> Option<Message> {
     debug_msg!(rt, "Internal message: transfer\n");
 
-    let ticketer = match ticketer {
-        Some(ticketer) => ticketer,
-        None => {
-            debug_msg!(
-                rt,
-                "Deposit ignored because of different smart rollup address"
-            );
-            return None;
-        }
-    };
-
     if transfer.destination.hash().as_ref() != &rt.reveal_metadata().raw_rollup_address {
         debug_msg!(
             rt,
@@ -49,26 +80,309 @@ fn read_transfer(
         return None;
     };
 
-    let ticket = transfer.payload.1;
+    let (pkh_bytes, ticketer, content, amount) = match transfer.payload {
+        MichelsonOr::Left(MichelsonPair(pkh_bytes, ticket)) => (
+            pkh_bytes.0,
+            ticket_creator(&ticket.creator().0),
+            TicketContent::Unit,
+            ticket.amount().to_u64()?,
+        ),
+        MichelsonOr::Right(MichelsonPair(pkh_bytes, ticket)) => {
+            let MichelsonPair(id, MichelsonOption(metadata)) = ticket.contents();
+            let content = TicketContent::Token {
+                id: id.0 .0.to_u64()?,
+                metadata: metadata.as_ref().map(|metadata| metadata.0.clone()),
+            };
+            (
+                pkh_bytes.0,
+                ticket_creator(&ticket.creator().0),
+                content,
+                ticket.amount().to_u64()?,
+            )
+        }
+    };
 
-    match &ticket.creator().0 {
-        Contract::Originated(kt1) if kt1 == ticketer => (),
-        _ => {
+    let asset = match ticketer {
+        Some(ticketer) => Asset { ticketer, content },
+        None => {
             debug_msg!(rt, "Deposit ignored because of different ticketer");
             return None;
         }
+    };
+
+    let reciever = PublicKeyHash::from_slice(&pkh_bytes).ok()?;
+
+    if ticketers.native.as_ref() == Some(&asset.ticketer)
+        && asset.content == TicketContent::Unit
+    {
+        let content = Deposit { amount, reciever };
+
+        debug_msg!(rt, "Deposit: {content:?}\n");
+
+        return Some(Message::Internal(InternalMessage::Deposit(content)));
     }
 
-    let amount = ticket.amount().to_u64()?;
+    if !ticketers.allowlist.contains(&asset.ticketer) {
+        debug_msg!(rt, "Deposit ignored because of different ticketer");
+        return None;
+    }
 
-    let pkh_bytes = transfer.payload.0 .0;
-    let reciever = PublicKeyHash::from_slice(&pkh_bytes).ok()?;
+    let content = AssetDeposit {
+        asset,
+        amount,
+        receiver: reciever,
+    };
+
+    debug_msg!(rt, "Asset deposit: {content:?}\n");
+
+    Some(Message::Internal(InternalMessage::AssetDeposit(content)))
+}
+
+/// Chunks of the operations being reassembled, at `<id>/<index>`
+const CHUNKS_PATH: RefPath = RefPath::assert_from(b"/jstz_kernel/chunks");
+
+/// The operations being reassembled, at `<id>`
+const CHUNKED_OPERATIONS_PATH: RefPath =
+    RefPath::assert_from(b"/jstz_kernel/chunked_operations");
+
+/// Ids and expiry levels of the operations being reassembled
+const CHUNKED_OPERATIONS_EXPIRY_PATH: RefPath =
+    RefPath::assert_from(b"/jstz_kernel/chunked_operations_expiry");
+
+/// Maximum number of operations being reassembled at once. Each signer
+/// reassembles at most one operation, its next one.
+const MAX_CHUNKED_OPERATIONS: usize = 32;
+
+/// Number of levels after its header after which an incomplete operation is
+/// discarded
+const CHUNKS_EXPIRY_LEVELS: u32 = 100;
+
+/// An operation whose chunks are being reassembled
+#[derive(Debug, Serialize, Deserialize)]
+struct ChunkedOperation {
+    header: ChunkedOperationHeader,
+    received: u32,
+}
+
+/// When the reassembly of an operation expires
+#[derive(Debug, Serialize, Deserialize)]
+struct Expiry {
+    id: Blake2b,
+    signer: Address,
+    expires_at: u32,
+}
+
+fn id_path(prefix: &RefPath, id: &Blake2b) -> Option<OwnedPath> {
+    let id_path = OwnedPath::try_from(format!("/{}", id.to_string())).ok()?;
+    path::concat(prefix, &id_path).ok()
+}
+
+fn chunk_path(id: &Blake2b, index: u32) -> Option<OwnedPath> {
+    let index_path = OwnedPath::try_from(format!("/{}", index)).ok()?;
+    path::concat(&id_path(&CHUNKS_PATH, id)?, &index_path).ok()
+}
+
+fn read_value<T: for<'de> Deserialize<'de>>(
+    rt: &impl Runtime,
+    path: &impl path::Path,
+) -> Option<T> {
+    rt.store_read_all(path)
+        .ok()
+        .and_then(|bytes| bincode::deserialize(&bytes).ok())
+}
+
+fn write_value<T: Serialize>(rt: &mut impl Runtime, path: &impl path::Path, value: &T) {
+    match bincode::serialize(value) {
+        Ok(bytes) => {
+            if let Err(err) = rt.store_write_all(path, &bytes) {
+                debug_msg!(rt, "[🔴] Failed to write chunked operations: {err:?}\n");
+            }
+        }
+        Err(err) => debug_msg!(rt, "[🔴] Failed to write chunked operations: {err:?}\n"),
+    }
+}
+
+fn read_expiries(rt: &impl Runtime) -> Vec<Expiry> {
+    read_value(rt, &CHUNKED_OPERATIONS_EXPIRY_PATH).unwrap_or_default()
+}
+
+fn read_chunked_operation(rt: &impl Runtime, id: &Blake2b) -> Option<ChunkedOperation> {
+    read_value(rt, &id_path(&CHUNKED_OPERATIONS_PATH, id)?)
+}
+
+fn delete_chunked_operation(rt: &mut impl Runtime, id: &Blake2b) {
+    if let Some(path) = id_path(&CHUNKED_OPERATIONS_PATH, id) {
+        let _ = rt.store_delete(&path);
+    }
+    if let Some(path) = id_path(&CHUNKS_PATH, id) {
+        let _ = rt.store_delete(&path);
+    }
+}
+
+/// Discards the operations that are still incomplete at `level`, i.e.
+/// [`CHUNKS_EXPIRY_LEVELS`] levels after their header. Called at the
+/// beginning of each level.
+fn expire_chunked_operations(rt: &mut impl Runtime, level: u32) {
+    let mut expiries = read_expiries(rt);
+    let count = expiries.len();
+    expiries.retain(|expiry| {
+        if expiry.expires_at >= level {
+            return true;
+        }
+        debug_msg!(rt, "Chunked operation {} expired\n", expiry.id.to_string());
+        delete_chunked_operation(rt, &expiry.id);
+        false
+    });
+    if expiries.len() != count {
+        write_value(rt, &CHUNKED_OPERATIONS_EXPIRY_PATH, &expiries);
+    }
+}
+
+/// Starts the reassembly of the operation announced by `header`, received at
+/// `level`, if it is the next operation of an existing account. The reassembly
+/// replaces any previous one of the same signer.
+fn read_chunked_operation_header(
+    rt: &mut impl Runtime,
+    level: u32,
+    header: ChunkedOperationHeader,
+) {
+    let id = header.id();
+    debug_msg!(
+        rt,
+        "External message: header {} of operation {} in {} chunks\n",
+        id.to_string(),
+        header.hash.to_string(),
+        header.chunk_hashes.len()
+    );
+
+    if header.chunk_hashes.is_empty() || header.chunk_hashes.len() > MAX_CHUNKS {
+        debug_msg!(rt, "Header ignored because of an invalid chunk count\n");
+        return;
+    }
+    let signer = match header.verify(rt) {
+        Ok(signer) => signer,
+        Err(err) => {
+            debug_msg!(rt, "Header ignored: {err:?}\n");
+            return;
+        }
+    };
+    if read_chunked_operation(rt, &id).is_some() {
+        debug_msg!(
+            rt,
+            "Header ignored because the operation is being reassembled\n"
+        );
+        return;
+    }
+    let mut expiries = read_expiries(rt);
+    if let Some(position) = expiries.iter().position(|expiry| expiry.signer == signer) {
+        let previous = expiries.remove(position);
+        debug_msg!(
+            rt,
+            "Chunked operation {} replaced by a new header of its signer\n",
+            previous.id.to_string()
+        );
+        delete_chunked_operation(rt, &previous.id);
+    }
+    if expiries.len() >= MAX_CHUNKED_OPERATIONS {
+        debug_msg!(
+            rt,
+            "Header ignored because too many operations are chunked\n"
+        );
+        return;
+    }
+
+    if let Some(path) = id_path(&CHUNKED_OPERATIONS_PATH, &id) {
+        write_value(
+            rt,
+            &path,
+            &ChunkedOperation {
+                header,
+                received: 0,
+            },
+        );
+        expiries.push(Expiry {
+            id,
+            signer,
+            expires_at: level.saturating_add(CHUNKS_EXPIRY_LEVELS),
+        });
+        write_value(rt, &CHUNKED_OPERATIONS_EXPIRY_PATH, &expiries);
+    }
+}
+
+/// Reads the chunks of an operation and deletes them
+fn take_chunks(rt: &mut impl Runtime, id: &Blake2b, count: u32) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    for index in 0..count {
+        bytes.extend(rt.store_read_all(&chunk_path(id, index)?).ok()?);
+    }
+    delete_chunked_operation(rt, id);
+
+    let mut expiries = read_expiries(rt);
+    expiries.retain(|expiry| expiry.id != *id);
+    write_value(rt, &CHUNKED_OPERATIONS_EXPIRY_PATH, &expiries);
+
+    Some(bytes)
+}
+
+/// Stores `chunk` if it matches the hash announced by its header. Returns the
+/// signed operation once all its chunks are received.
+fn read_chunk(rt: &mut impl Runtime, chunk: OperationChunk) -> Option<ExternalMessage> {
+    debug_msg!(
+        rt,
+        "External message: chunk {} of header {}\n",
+        chunk.index,
+        chunk.header.to_string()
+    );
+
+    let Some(mut operation) = read_chunked_operation(rt, &chunk.header) else {
+        debug_msg!(rt, "Chunk ignored because its header is unknown\n");
+        return None;
+    };
+    let count = operation.header.chunk_hashes.len() as u32;
+
+    match operation.header.chunk_hashes.get(chunk.index as usize) {
+        Some(hash) if *hash == Blake2b::from(&chunk.bytes) => (),
+        Some(_) => {
+            debug_msg!(rt, "Chunk ignored because of a different hash\n");
+            return None;
+        }
+        None => {
+            debug_msg!(rt, "Chunk ignored because of an invalid index\n");
+            return None;
+        }
+    }
 
-    let content = Deposit { amount, reciever };
+    // A chunk received again is identical to the stored one
+    let path = chunk_path(&chunk.header, chunk.index)?;
+    if let Ok(Some(_)) = rt.store_has(&path) {
+        return None;
+    }
+    rt.store_write_all(&path, &chunk.bytes).ok()?;
+    operation.received += 1;
 
-    debug_msg!(rt, "Deposit: {content:?}\n");
+    if operation.received < count {
+        write_value(
+            rt,
+            &id_path(&CHUNKED_OPERATIONS_PATH, &chunk.header)?,
+            &operation,
+        );
+        return None;
+    }
+
+    let bytes = take_chunks(rt, &chunk.header, count)?;
+    let msg = read_external_message(rt, &bytes)?;
+    if msg.hash() != operation.header.hash
+        || msg.public_key != operation.header.public_key
+        || msg.nonce() != operation.header.nonce
+    {
+        debug_msg!(
+            rt,
+            "Chunked operation ignored because of a different hash, signer or nonce\n"
+        );
+        return None;
+    }
 
-    Some(Message::Internal(InternalMessage::Deposit(content)))
+    Some(msg)
 }
 
 fn read_external_message(rt: &mut impl Runtime, bytes: &[u8]) -> Option<ExternalMessage> {
@@ -77,14 +391,37 @@ fn read_external_message(rt: &mut impl Runtime, bytes: &[u8]) -> Option<External
     Some(msg)
 }
 
-pub fn read_message(
+fn read_external_message_content(
     rt: &mut impl Runtime,
-    ticketer: Option<&ContractKt1Hash>,
-) -> Option<Message> {
-    let input = rt.read_input().ok()??;
-    let _ = rt.mark_for_reboot();
+    level: u32,
+    bytes: &[u8],
+) -> Option<ExternalMessage> {
+    match bincode::deserialize(bytes) {
+        Ok(ExternalMessageContent::Operation(msg)) => {
+            debug_msg!(rt, "External message: {msg:?}\n");
+            Some(msg)
+        }
+        Ok(ExternalMessageContent::ChunkedOperation(header)) => {
+            read_chunked_operation_header(rt, level, header);
+            None
+        }
+        Ok(ExternalMessageContent::Chunk(chunk)) => read_chunk(rt, chunk),
+        Err(_) => {
+            debug_msg!(rt, "Failed to parse the external message\n");
+            None
+        }
+    }
+}
 
-    let (_, message) = InboxMessage::<RollupType>::parse(input.as_ref()).ok()?;
+/// Parses an inbox message of the given `level`. Returns `None` for messages
+/// that are not addressed to jstz or that cannot be parsed.
+pub fn parse_message(
+    rt: &mut impl Runtime,
+    level: u32,
+    input: &[u8],
+    ticketers: &Ticketers,
+) -> Option<Message> {
+    let (_, message) = InboxMessage::<RollupType>::parse(input).ok()?;
 
     match message {
         InboxMessage::Internal(InternalInboxMessage::StartOfLevel) => {
@@ -103,7 +440,11 @@ pub fn read_message(
                 info.predecessor,
                 info.predecessor_timestamp
             );
-            None
+            expire_chunked_operations(rt, level);
+            Some(Message::BlockInfo(BlockInfo {
+                level,
+                timestamp: info.predecessor_timestamp.i64(),
+            }))
         }
         InboxMessage::Internal(InternalInboxMessage::EndOfLevel) => {
             // The "End of level" message is pushed by the Layer 1
@@ -112,7 +453,7 @@ pub fn read_message(
             None
         }
         InboxMessage::Internal(InternalInboxMessage::Transfer(transfer)) => {
-            read_transfer(rt, transfer, ticketer)
+            read_transfer(rt, transfer, ticketers)
         }
         InboxMessage::External(bytes) => match ExternalMessageFrame::parse(bytes) {
             Ok(frame) => match frame {
@@ -128,13 +469,8 @@ pub fn read_message(
                         );
                         None
                     } else {
-                        match read_external_message(rt, contents) {
-                            Some(msg) => Some(Message::External(msg)),
-                            None => {
-                                debug_msg!(rt, "Failed to parse the external message\n");
-                                None
-                            }
-                        }
+                        read_external_message_content(rt, level, contents)
+                            .map(Message::External)
                     }
                 }
             },
@@ -145,3 +481,299 @@ pub fn read_message(
         },
     }
 }
+
+#[cfg(test)]
+mod test {
+    use jstz_core::kv::Storage;
+    use jstz_crypto::keypair_from_passphrase;
+    use jstz_proto::{
+        context::account::{Account, Amount, Nonce},
+        operation::{Content, Operation, Transfer},
+    };
+    use tezos_smart_rollup_mock::MockHost;
+
+    use super::*;
+
+    const LEVEL: u32 = 10;
+
+    /// A transfer of `amount` to itself by the account of `passphrase`, signed
+    /// with `nonce`. Creates the account if it does not exist.
+    fn signed_transfer(
+        host: &mut MockHost,
+        passphrase: &str,
+        nonce: Nonce,
+        amount: Amount,
+    ) -> SignedOperation {
+        let (sk, pk) = keypair_from_passphrase(passphrase).unwrap();
+        let source = Address::try_from(&pk).unwrap();
+        let path = Account::path(&source).unwrap();
+        if !Storage::contains_key(host, &path).unwrap() {
+            Storage::insert(host, &path, &Account::default()).unwrap();
+        }
+        let op = Operation {
+            source: source.clone(),
+            nonce,
+            content: Content::Transfer(Transfer {
+                destination: source,
+                amount,
+            }),
+            rollup_address: host.reveal_metadata().address(),
+            valid_until_level: None,
+        };
+        let signature = sk.sign(op.hash()).unwrap();
+        SignedOperation::new(pk, signature, op)
+    }
+
+    fn signed_operation(host: &mut MockHost, passphrase: &str) -> SignedOperation {
+        signed_transfer(host, passphrase, Nonce::default(), 1)
+    }
+
+    /// The header and chunks of `operation`, split into 3 chunks
+    fn split(
+        operation: &SignedOperation,
+    ) -> (ChunkedOperationHeader, Vec<OperationChunk>) {
+        let bytes = bincode::serialize(operation).unwrap();
+        let chunks: Vec<&[u8]> = bytes.chunks(bytes.len().div_ceil(3)).collect();
+        assert_eq!(chunks.len(), 3);
+
+        let header = ChunkedOperationHeader::new(operation, &chunks);
+        let chunks = chunks
+            .into_iter()
+            .enumerate()
+            .map(|(index, bytes)| OperationChunk {
+                header: header.id(),
+                index: index as u32,
+                bytes: bytes.to_vec(),
+            })
+            .collect();
+        (header, chunks)
+    }
+
+    fn send(
+        host: &mut MockHost,
+        content: ExternalMessageContent,
+    ) -> Option<ExternalMessage> {
+        let bytes = bincode::serialize(&content).unwrap();
+        read_external_message_content(host, LEVEL, &bytes)
+    }
+
+    #[test]
+    fn chunked_operation_is_reassembled() {
+        let mut host = MockHost::default();
+        let operation = signed_operation(&mut host, "alice");
+        let (header, chunks) = split(&operation);
+
+        assert_eq!(
+            send(&mut host, ExternalMessageContent::ChunkedOperation(header)),
+            None
+        );
+        let mut chunks = chunks.into_iter();
+        assert_eq!(
+            send(
+                &mut host,
+                ExternalMessageContent::Chunk(chunks.next().unwrap())
+            ),
+            None
+        );
+        assert_eq!(
+            send(
+                &mut host,
+                ExternalMessageContent::Chunk(chunks.next().unwrap())
+            ),
+            None
+        );
+        assert_eq!(
+            send(
+                &mut host,
+                ExternalMessageContent::Chunk(chunks.next().unwrap())
+            ),
+            Some(operation)
+        );
+        assert!(read_expiries(&host).is_empty());
+    }
+
+    #[test]
+    fn chunks_are_reassembled_out_of_order() {
+        let mut host = MockHost::default();
+        let operation = signed_operation(&mut host, "alice");
+        let (header, chunks) = split(&operation);
+
+        send(&mut host, ExternalMessageContent::ChunkedOperation(header));
+        for index in [2, 0] {
+            let chunk = chunks[index].clone();
+            assert_eq!(
+                send(&mut host, ExternalMessageContent::Chunk(chunk.clone())),
+                None
+            );
+            // A chunk received twice is counted once
+            assert_eq!(send(&mut host, ExternalMessageContent::Chunk(chunk)), None);
+        }
+        assert_eq!(
+            send(&mut host, ExternalMessageContent::Chunk(chunks[1].clone())),
+            Some(operation)
+        );
+    }
+
+    #[test]
+    fn chunks_without_header_are_ignored() {
+        let mut host = MockHost::default();
+        let operation = signed_operation(&mut host, "alice");
+        let (header, chunks) = split(&operation);
+
+        for chunk in chunks {
+            assert_eq!(send(&mut host, ExternalMessageContent::Chunk(chunk)), None);
+        }
+        assert!(read_chunked_operation(&host, &header.id()).is_none());
+    }
+
+    #[test]
+    fn chunk_with_different_hash_is_ignored() {
+        let mut host = MockHost::default();
+        let operation = signed_operation(&mut host, "alice");
+        let (header, mut chunks) = split(&operation);
+
+        send(&mut host, ExternalMessageContent::ChunkedOperation(header));
+        send(&mut host, ExternalMessageContent::Chunk(chunks[0].clone()));
+        send(&mut host, ExternalMessageContent::Chunk(chunks[1].clone()));
+
+        let genuine = chunks.pop().unwrap();
+        let mut forged = genuine.clone();
+        forged.bytes[0] ^= 1;
+        assert_eq!(send(&mut host, ExternalMessageContent::Chunk(forged)), None);
+        assert_eq!(
+            send(&mut host, ExternalMessageContent::Chunk(genuine)),
+            Some(operation)
+        );
+    }
+
+    #[test]
+    fn header_with_invalid_signature_is_ignored() {
+        let mut host = MockHost::default();
+        let operation = signed_operation(&mut host, "alice");
+        let (mut header, chunks) = split(&operation);
+        header.public_key = signed_operation(&mut host, "bob").public_key;
+
+        send(
+            &mut host,
+            ExternalMessageContent::ChunkedOperation(header.clone()),
+        );
+        assert!(read_chunked_operation(&host, &header.id()).is_none());
+        for chunk in chunks {
+            assert_eq!(send(&mut host, ExternalMessageContent::Chunk(chunk)), None);
+        }
+    }
+
+    #[test]
+    fn header_of_unknown_account_is_ignored() {
+        let mut host = MockHost::default();
+        let operation = signed_operation(&mut host, "alice");
+        let (header, _) = split(&operation);
+        let source = Address::try_from(&operation.public_key).unwrap();
+        Storage::remove(&mut host, &Account::path(&source).unwrap()).unwrap();
+
+        send(
+            &mut host,
+            ExternalMessageContent::ChunkedOperation(header.clone()),
+        );
+        assert!(read_chunked_operation(&host, &header.id()).is_none());
+        assert!(read_expiries(&host).is_empty());
+    }
+
+    #[test]
+    fn header_of_other_than_next_nonce_is_ignored() {
+        let mut host = MockHost::default();
+        let operation = signed_transfer(&mut host, "alice", Nonce::default().next(), 1);
+        let (header, _) = split(&operation);
+
+        send(
+            &mut host,
+            ExternalMessageContent::ChunkedOperation(header.clone()),
+        );
+        assert!(read_chunked_operation(&host, &header.id()).is_none());
+        assert!(read_expiries(&host).is_empty());
+    }
+
+    #[test]
+    fn header_replaces_previous_reassembly_of_signer() {
+        let mut host = MockHost::default();
+        let previous = signed_operation(&mut host, "alice");
+        let (previous_header, previous_chunks) = split(&previous);
+        send(
+            &mut host,
+            ExternalMessageContent::ChunkedOperation(previous_header.clone()),
+        );
+        send(
+            &mut host,
+            ExternalMessageContent::Chunk(previous_chunks[0].clone()),
+        );
+
+        let operation = signed_transfer(&mut host, "alice", Nonce::default(), 2);
+        let (header, chunks) = split(&operation);
+        send(
+            &mut host,
+            ExternalMessageContent::ChunkedOperation(header.clone()),
+        );
+
+        assert!(read_chunked_operation(&host, &previous_header.id()).is_none());
+        assert!(read_chunked_operation(&host, &header.id()).is_some());
+        assert_eq!(read_expiries(&host).len(), 1);
+        assert!(matches!(
+            host.store_has(&chunk_path(&previous_header.id(), 0).unwrap()),
+            Ok(None)
+        ));
+
+        for chunk in previous_chunks.into_iter().skip(1) {
+            assert_eq!(send(&mut host, ExternalMessageContent::Chunk(chunk)), None);
+        }
+        let mut received = None;
+        for chunk in chunks {
+            received = send(&mut host, ExternalMessageContent::Chunk(chunk));
+        }
+        assert_eq!(received, Some(operation));
+    }
+
+    #[test]
+    fn concurrent_reassemblies_are_capped() {
+        let mut host = MockHost::default();
+        for index in 0..=MAX_CHUNKED_OPERATIONS {
+            let operation = signed_operation(&mut host, &format!("user{index}"));
+            let (header, _) = split(&operation);
+            send(
+                &mut host,
+                ExternalMessageContent::ChunkedOperation(header.clone()),
+            );
+            assert_eq!(
+                read_chunked_operation(&host, &header.id()).is_some(),
+                index < MAX_CHUNKED_OPERATIONS
+            );
+        }
+        assert_eq!(read_expiries(&host).len(), MAX_CHUNKED_OPERATIONS);
+    }
+
+    #[test]
+    fn incomplete_operations_expire() {
+        let mut host = MockHost::default();
+        let operation = signed_operation(&mut host, "alice");
+        let (header, chunks) = split(&operation);
+        let id = header.id();
+
+        send(&mut host, ExternalMessageContent::ChunkedOperation(header));
+        send(&mut host, ExternalMessageContent::Chunk(chunks[0].clone()));
+
+        expire_chunked_operations(&mut host, LEVEL + CHUNKS_EXPIRY_LEVELS);
+        assert!(read_chunked_operation(&host, &id).is_some());
+
+        expire_chunked_operations(&mut host, LEVEL + CHUNKS_EXPIRY_LEVELS + 1);
+        assert!(read_chunked_operation(&host, &id).is_none());
+        assert!(read_expiries(&host).is_empty());
+        assert!(matches!(
+            host.store_has(&chunk_path(&id, 0).unwrap()),
+            Ok(None)
+        ));
+
+        // The remaining chunks of an expired operation are ignored
+        for chunk in chunks.into_iter().skip(1) {
+            assert_eq!(send(&mut host, ExternalMessageContent::Chunk(chunk)), None);
+        }
+    }
+}
@@ -1,20 +1,32 @@
-use jstz_core::kv::{Storage, Transaction};
-use jstz_proto::{executor, Result};
-use tezos_crypto_rs::hash::ContractKt1Hash;
+use jstz_core::kv::Transaction;
+use jstz_proto::{
+    context::{outbox::Outbox, ticketer::Ticketer},
+    executor, Result,
+};
 use tezos_smart_rollup::{
     entrypoint,
     prelude::{debug_msg, Runtime},
     storage::path::RefPath,
 };
 
-use crate::inbox::{read_message, Message};
+use crate::{
+    inbox::{parse_message, Message, Ticketers},
+    ticks::TickBudget,
+};
 
 pub mod inbox;
+pub mod ticks;
 
-const TICKETER: RefPath = RefPath::assert_from(b"/ticketer");
+/// A message read from the inbox but deferred to the next kernel run because
+/// the tick budget of the current run was exhausted
+const PENDING_MESSAGE_PATH: RefPath =
+    RefPath::assert_from(b"/jstz_kernel/pending_message");
 
-fn read_ticketer(rt: &impl Runtime) -> Option<ContractKt1Hash> {
-    Storage::get(rt, &TICKETER).ok()?
+fn read_ticketers(rt: &impl Runtime) -> Ticketers {
+    Ticketers {
+        native: Ticketer::get(rt).ok().flatten(),
+        allowlist: Ticketer::allowlist(rt).unwrap_or_default(),
+    }
 }
 
 fn handle_message(hrt: &mut impl Runtime, message: Message) -> Result<()> {
@@ -31,19 +43,153 @@ fn handle_message(hrt: &mut impl Runtime, message: Message) -> Result<()> {
             debug_msg!(hrt, "Receipt: {receipt:?}\n");
             receipt.write(hrt, &mut tx)?
         }
+        Message::BlockInfo(block) => {
+            block.set(hrt)?;
+
+            // Calls scheduled for this level are run once its time is known
+            for receipt in executor::scheduler::execute_due(hrt, &mut tx, block.level)? {
+                debug_msg!(hrt, "Scheduled call receipt: {receipt:?}\n");
+                receipt.write(hrt, &mut tx)?
+            }
+        }
     }
 
     tx.commit(hrt)?;
-    Ok(())
+
+    // Withdrawals are only written to the outbox once committed
+    Outbox::flush(hrt)
+}
+
+fn take_pending_message(rt: &mut impl Runtime) -> Option<Message> {
+    rt.store_has(&PENDING_MESSAGE_PATH).ok()??;
+    let bytes = rt.store_read_all(&PENDING_MESSAGE_PATH).ok()?;
+    let _ = rt.store_delete(&PENDING_MESSAGE_PATH);
+    bincode::deserialize(&bytes).ok()
+}
+
+fn defer_message(rt: &mut impl Runtime, message: &Message) {
+    match bincode::serialize(message) {
+        Ok(bytes) => {
+            if let Err(err) = rt.store_write_all(&PENDING_MESSAGE_PATH, &bytes) {
+                debug_msg!(rt, "[🔴] Failed to defer message: {err:?}\n");
+            }
+        }
+        Err(err) => debug_msg!(rt, "[🔴] Failed to defer message: {err:?}\n"),
+    }
+}
+
+fn next_message(
+    rt: &mut impl Runtime,
+    budget: &mut TickBudget,
+    ticketers: &Ticketers,
+) -> Option<Message> {
+    while let Some(input) = rt.read_input().ok()? {
+        match parse_message(rt, input.level, input.as_ref(), ticketers) {
+            Some(message) => return Some(message),
+            // Skipped messages (and stored chunks) use ticks too. Once they
+            // exhaust the budget, the kernel reboots and reads the rest of the
+            // inbox in the next run.
+            None if !budget.try_consume(ticks::skipped(input.as_ref().len())) => {
+                let _ = rt.mark_for_reboot();
+                return None;
+            }
+            None => (),
+        }
+    }
+
+    None
 }
 
 // kernel entry
 #[entrypoint::main]
 pub fn entry(rt: &mut impl Runtime) {
-    let ticketer = read_ticketer(rt);
+    // The ticketers are read once per kernel run, not once per message
+    let ticketers = read_ticketers(rt);
+    let mut budget = TickBudget::default();
+
+    // A message deferred by the previous run is handled before reading the inbox
+    let mut pending = take_pending_message(rt);
+
+    while let Some(message) = pending
+        .take()
+        .or_else(|| next_message(rt, &mut budget, &ticketers))
+    {
+        // Close to the tick limit, the message is stored in durable storage and
+        // handled first after rebooting, so that it is not lost
+        if !budget.try_consume(ticks::estimate(rt, &message)) {
+            defer_message(rt, &message);
+            let _ = rt.mark_for_reboot();
+            return;
+        }
 
-    if let Some(message) = read_message(rt, ticketer.as_ref()) {
         handle_message(rt, message)
             .unwrap_or_else(|err| debug_msg!(rt, "[🔴] {err:?}\n"));
     }
 }
+
+#[cfg(test)]
+mod test {
+    use jstz_proto::context::block::BlockInfo;
+    use tezos_smart_rollup_mock::MockHost;
+
+    use super::*;
+
+    const REBOOT_PATH: RefPath = RefPath::assert_from(b"/kernel/env/reboot");
+
+    #[test]
+    fn deferred_message_is_taken_once() {
+        let mut host = MockHost::default();
+        let message = Message::BlockInfo(BlockInfo {
+            level: 7,
+            timestamp: 1_700_000_000,
+        });
+
+        defer_message(&mut host, &message);
+        assert_eq!(take_pending_message(&mut host), Some(message));
+        assert_eq!(take_pending_message(&mut host), None);
+    }
+
+    #[test]
+    fn deferred_message_is_handled_after_reboot() {
+        let mut host = MockHost::default();
+        let block = BlockInfo {
+            level: 7,
+            timestamp: 1_700_000_000,
+        };
+
+        defer_message(&mut host, &Message::BlockInfo(block));
+        entry(&mut host);
+
+        assert_eq!(BlockInfo::get(&host).unwrap(), Some(block));
+        assert!(host.store_has(&PENDING_MESSAGE_PATH).unwrap().is_none());
+    }
+
+    #[test]
+    fn messages_are_read_within_the_budget() {
+        let mut host = MockHost::default();
+
+        host.run_level(|rt: &mut MockHost| {
+            let mut budget = TickBudget::default();
+            let message = next_message(rt, &mut budget, &Ticketers::default());
+
+            // The start of level message is skipped
+            assert!(matches!(message, Some(Message::BlockInfo(_))));
+            assert!(rt.store_has(&REBOOT_PATH).unwrap().is_none());
+        });
+    }
+
+    #[test]
+    fn skipped_messages_exhausting_the_budget_reboot_the_kernel() {
+        let mut host = MockHost::default();
+
+        host.run_level(|rt: &mut MockHost| {
+            let mut budget = TickBudget::default();
+            assert!(budget.try_consume(u64::MAX));
+            let message = next_message(rt, &mut budget, &Ticketers::default());
+
+            // The start of level message exhausts the budget
+            assert_eq!(message, None);
+            assert!(rt.store_has(&REBOOT_PATH).unwrap().is_some());
+        });
+    }
+}
@@ -0,0 +1,144 @@
+use jstz_proto::context::scheduler::{self, Scheduler};
+use tezos_smart_rollup::prelude::Runtime;
+
+use crate::inbox::Message;
+
+/// Maximum number of ticks of a single kernel run
+const MAX_TICKS: u64 = 11_000_000_000;
+
+/// Ticks reserved for the kernel's own bookkeeping (reading the inbox,
+/// deferring a message and rebooting)
+const SAFETY_MARGIN: u64 = 1_000_000_000;
+
+/// Estimated ticks to read, parse and dispatch a message
+const TICKS_PER_MESSAGE: u64 = 20_000_000;
+
+/// Estimated ticks per byte of a message (decoding, signature checks and
+/// durable storage writes)
+const TICKS_PER_BYTE: u64 = 50_000;
+
+/// Estimated ticks per unit of gas of a smart function call
+const TICKS_PER_GAS: u64 = 5_000;
+
+/// Estimated ticks per byte revealed from preimages (revealing the pages and
+/// parsing the revealed code)
+const TICKS_PER_REVEALED_BYTE: u64 = 50_000;
+
+/// Estimated ticks to skip an inbox message of `size` bytes (or to store it,
+/// such as operation chunks)
+pub fn skipped(size: usize) -> u64 {
+    TICKS_PER_MESSAGE.saturating_add((size as u64).saturating_mul(TICKS_PER_BYTE))
+}
+
+/// Conservative estimate of the number of ticks needed to handle `message`
+pub fn estimate(rt: &impl Runtime, message: &Message) -> u64 {
+    let size = bincode::serialized_size(message).unwrap_or(0);
+
+    let (gas_limit, reveal_limit) = match message {
+        Message::External(signed_operation) => (
+            signed_operation.gas_limit() as u64,
+            signed_operation.reveal_limit() as u64,
+        ),
+        Message::Internal(_) => (0, 0),
+        // The calls scheduled for the level are run
+        Message::BlockInfo(block) => (
+            Scheduler::due_gas(rt, block.level).unwrap_or(scheduler::MAX_GAS_PER_LEVEL)
+                as u64,
+            0,
+        ),
+    };
+
+    skipped(size as usize)
+        .saturating_add(gas_limit.saturating_mul(TICKS_PER_GAS))
+        .saturating_add(reveal_limit.saturating_mul(TICKS_PER_REVEALED_BYTE))
+}
+
+/// Estimated ticks used by the current kernel run
+#[derive(Debug, Default)]
+pub struct TickBudget {
+    used: u64,
+}
+
+impl TickBudget {
+    /// Consumes `ticks` if they fit within the budget. The first message of a
+    /// run always fits, such that messages exceeding the budget on their own
+    /// are still handled (instead of being deferred forever).
+    pub fn try_consume(&mut self, ticks: u64) -> bool {
+        let used = self.used.saturating_add(ticks);
+        if self.used > 0 && used > MAX_TICKS - SAFETY_MARGIN {
+            return false;
+        }
+        self.used = used;
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use jstz_core::kv::Transaction;
+    use jstz_proto::{
+        context::{account::Address, block::BlockInfo, scheduler::ScheduledCall},
+        operation::RunFunction,
+    };
+    use tezos_smart_rollup_mock::MockHost;
+
+    use super::*;
+
+    #[test]
+    fn first_message_always_fits() {
+        let mut budget = TickBudget::default();
+
+        assert!(budget.try_consume(MAX_TICKS));
+        assert!(!budget.try_consume(1));
+    }
+
+    #[test]
+    fn budget_is_exhausted_by_skipped_messages() {
+        let mut budget = TickBudget::default();
+
+        let mut count = 0;
+        while budget.try_consume(skipped(4096)) {
+            count += 1;
+        }
+        assert_eq!(count, (MAX_TICKS - SAFETY_MARGIN) / skipped(4096));
+    }
+
+    #[test]
+    fn block_info_estimate_depends_on_scheduled_calls() {
+        let hrt = &mut MockHost::default();
+        let message = Message::BlockInfo(BlockInfo {
+            level: 5,
+            timestamp: 0,
+        });
+        let empty = estimate(hrt, &message);
+        assert_eq!(
+            empty,
+            skipped(bincode::serialized_size(&message).unwrap() as usize)
+        );
+
+        let address = Address::from_base58("tz1XQjK1b3P72kMcHsoPhnAg3dvX1n8Ainty")
+            .expect("Could not parse pkh");
+        let call = ScheduledCall {
+            id: 0,
+            scheduler: address,
+            run: RunFunction {
+                uri: http::Uri::from_static(
+                    "tezos://tz1XQjK1b3P72kMcHsoPhnAg3dvX1n8Ainty/",
+                ),
+                method: http::Method::GET,
+                headers: http::HeaderMap::new(),
+                body: None,
+                gas_limit: 100,
+            },
+            level: 5,
+            every: 1,
+            remaining: 1,
+        };
+        let mut tx = Transaction::default();
+        tx.begin();
+        Scheduler::schedule(hrt, &mut tx, call).unwrap();
+        tx.commit(hrt).unwrap();
+
+        assert_eq!(estimate(hrt, &message), empty + 100 * TICKS_PER_GAS);
+    }
+}
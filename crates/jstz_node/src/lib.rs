@@ -11,7 +11,9 @@ mod services;
 mod tailed_file;
 
 pub use error::{Error, Result};
-pub use services::{AccountsService, LogsService, OperationsService, Service};
+pub use services::{
+    AccountsService, EventsService, LogsService, OperationsService, Service,
+};
 
 pub async fn run(
     addr: &str,
@@ -23,7 +25,7 @@ pub async fn run(
 
     let cancellation_token = CancellationToken::new();
 
-    let (broadcaster, db, tail_file_handle) =
+    let (broadcaster, event_broadcaster, db, tail_file_handle) =
         LogsService::init(kernel_log_path, &cancellation_token)
             .await
             .map_err(|e| io::Error::new(Other, e.to_string()))?;
@@ -34,10 +36,12 @@ pub async fn run(
         App::new()
             .app_data(rollup_client.clone())
             .app_data(Data::from(broadcaster.clone()))
+            .app_data(Data::from(event_broadcaster.clone()))
             .app_data(Data::new(db.clone()))
             .configure(OperationsService::configure)
             .configure(AccountsService::configure)
             .configure(LogsService::configure)
+            .configure(EventsService::configure)
             .wrap(Logger::default())
             .wrap(cors)
     })
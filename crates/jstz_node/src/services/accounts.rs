@@ -7,7 +7,7 @@ use actix_web::{
 };
 use anyhow::anyhow;
 use jstz_api::KvValue;
-use jstz_proto::context::account::Account;
+use jstz_proto::context::account::{Account, ParsedCode};
 use octez::OctezRollupClient;
 
 use crate::Result;
@@ -42,12 +42,74 @@ async fn nonce(
     Ok(HttpResponse::Ok().json(nonce))
 }
 
+async fn get_code_version(
+    rollup_client: &OctezRollupClient,
+    address: &str,
+    version: usize,
+) -> Result<Option<ParsedCode>> {
+    let key = format!("/jstz_code_history/{}/{}", address, version);
+
+    let value = rollup_client.get_value(&key).await?;
+
+    let code = match value {
+        Some(value) => Some(
+            bincode::deserialize::<ParsedCode>(&value)
+                .map_err(|_| anyhow!("Failed to deserialize code"))?,
+        ),
+        None => None,
+    };
+
+    Ok(code)
+}
+
+async fn get_code_history(
+    rollup_client: &OctezRollupClient,
+    address: &str,
+) -> Result<Option<Vec<ParsedCode>>> {
+    let key = format!("/jstz_code_versions/{}", address);
+
+    let Some(value) = rollup_client.get_value(&key).await? else {
+        return Ok(None);
+    };
+    let len = bincode::deserialize::<usize>(&value)
+        .map_err(|_| anyhow!("Failed to deserialize code versions"))?;
+
+    let mut history = Vec::with_capacity(len);
+    for version in 0..len {
+        let code = get_code_version(rollup_client, address, version)
+            .await?
+            .ok_or_else(|| anyhow!("Missing code version {}", version))?;
+        history.push(code);
+    }
+
+    Ok(Some(history))
+}
+
 #[get("/{address}/code")]
 async fn code(
     rollup_client: Data<OctezRollupClient>,
     path: Path<String>,
+    query: Query<HashMap<String, String>>,
 ) -> Result<impl Responder> {
-    let key = format!("/jstz_account/{}", path.into_inner());
+    let address = path.into_inner();
+
+    // Previous versions of the code are read from the code history
+    if let Some(version) = query.get("version") {
+        let Ok(version) = version.parse::<usize>() else {
+            return Ok(
+                HttpResponse::BadRequest().body(format!("Invalid version: {}", version))
+            );
+        };
+
+        let code = get_code_version(&rollup_client, &address, version).await?;
+
+        return match code {
+            Some(code) => Ok(HttpResponse::Ok().json(code)),
+            None => Ok(HttpResponse::NotFound().finish()),
+        };
+    }
+
+    let key = format!("/jstz_account/{}", address);
 
     let value = rollup_client.get_value(&key).await?;
 
@@ -63,6 +125,19 @@ async fn code(
     Ok(HttpResponse::Ok().json(code))
 }
 
+#[get("/{address}/code/history")]
+async fn code_history(
+    rollup_client: Data<OctezRollupClient>,
+    path: Path<String>,
+) -> Result<impl Responder> {
+    let history = match get_code_history(&rollup_client, &path.into_inner()).await? {
+        Some(history) => history,
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+
+    Ok(HttpResponse::Ok().json(history))
+}
+
 #[get("/{address}/balance")]
 async fn balance(
     rollup_client: Data<OctezRollupClient>,
@@ -135,6 +210,7 @@ impl Service for AccountsService {
         let scope = Scope::new("/accounts")
             .service(nonce)
             .service(code)
+            .service(code_history)
             .service(balance)
             .service(kv)
             .service(kv_subkeys);
@@ -0,0 +1,167 @@
+use actix_web::{
+    get,
+    web::{Data, Path, Query, ServiceConfig},
+    Responder, Scope,
+};
+use jstz_proto::{context::account::Address, event_logger::EventRecord};
+use serde::Deserialize;
+
+use super::{logs::broadcaster::Broadcaster, Service};
+use crate::Result;
+
+/// A subscription to the events of a smart function, optionally filtered by
+/// topic
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EventSubscription {
+    pub address: Address,
+    pub topic: Option<String>,
+}
+
+impl EventSubscription {
+    /// The subscriptions an event is broadcast to
+    pub fn matching(event: &EventRecord) -> [Self; 2] {
+        [
+            EventSubscription {
+                address: event.address.clone(),
+                topic: None,
+            },
+            EventSubscription {
+                address: event.address.clone(),
+                topic: Some(event.topic.clone()),
+            },
+        ]
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct TopicFilter {
+    topic: Option<String>,
+}
+
+#[get("{address}/stream")]
+async fn stream_events(
+    broadcaster: Data<Broadcaster<EventSubscription>>,
+    path: Path<String>,
+    filter: Query<TopicFilter>,
+) -> Result<impl Responder> {
+    let address = Address::from_base58(&path.into_inner())?;
+    let TopicFilter { topic } = filter.into_inner();
+
+    Ok(broadcaster
+        .new_client(EventSubscription { address, topic })
+        .await)
+}
+
+#[cfg(feature = "persistent-logging")]
+mod persistent_events {
+    use actix_web::HttpResponse;
+
+    use super::*;
+    use crate::{services::logs::Db, Error};
+
+    const DEFAULT_PAGINATION_LIMIT: usize = 100;
+    const DEFAULT_PAGINATION_OFFSET: usize = 0;
+
+    #[derive(Deserialize, Debug)]
+    pub struct EventsQuery {
+        topic: Option<String>,
+        limit: Option<usize>,
+        offset: Option<usize>,
+    }
+
+    #[get("{address}")]
+    pub async fn events(
+        query: Query<EventsQuery>,
+        db: Data<Db>,
+        path: Path<String>,
+    ) -> Result<HttpResponse> {
+        let address = Address::from_base58(&path.into_inner())?;
+
+        let EventsQuery {
+            topic,
+            limit,
+            offset,
+        } = query.into_inner();
+        let result = db
+            .events_by_address(
+                address,
+                topic,
+                limit.unwrap_or(DEFAULT_PAGINATION_LIMIT),
+                offset.unwrap_or(DEFAULT_PAGINATION_OFFSET),
+            )
+            .await
+            .map_err(Error::InternalError)?;
+
+        Ok(HttpResponse::Ok().json(result))
+    }
+}
+
+pub struct EventsService;
+
+impl Service for EventsService {
+    fn configure(cfg: &mut ServiceConfig) {
+        let scope = Scope::new("/events").service(stream_events);
+
+        #[cfg(feature = "persistent-logging")]
+        let scope = scope.service(persistent_events::events);
+
+        cfg.service(scope);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use jstz_proto::{context::account::Address, event_logger::EventRecord};
+
+    use super::EventSubscription;
+    use crate::services::logs::broadcaster::Broadcaster;
+
+    fn address() -> Address {
+        Address::from_base58("tz1XQjK1b3P72kMcHsoPhnAg3dvX1n8Ainty")
+            .expect("Could not parse address")
+    }
+
+    fn subscription(address: Address, topic: Option<&str>) -> EventSubscription {
+        EventSubscription {
+            address,
+            topic: topic.map(str::to_string),
+        }
+    }
+
+    #[actix_web::test]
+    async fn events_are_streamed_to_matching_subscriptions() {
+        let broadcaster = Broadcaster::create();
+        let other = Address::from_base58("tz1KqTpEZ7Yob7QbPE4Hy4Wo8fHG8LhKxZSx")
+            .expect("Could not parse address");
+
+        let mut all = broadcaster.subscribe(subscription(address(), None)).await;
+        let mut transfers = broadcaster
+            .subscribe(subscription(address(), Some("transfer")))
+            .await;
+        let mut mints = broadcaster
+            .subscribe(subscription(address(), Some("mint")))
+            .await;
+        let mut others = broadcaster.subscribe(subscription(other, None)).await;
+
+        let event = EventRecord {
+            address: address(),
+            request_id: "request".to_string(),
+            topic: "transfer".to_string(),
+            payload: serde_json::json!({ "amount": 42 }),
+        };
+        for subscription in EventSubscription::matching(&event) {
+            broadcaster
+                .broadcast(&subscription, &event.to_string())
+                .await;
+        }
+
+        // Each client first receives the "connected" message
+        for rx in [&mut all, &mut transfers, &mut mints, &mut others] {
+            assert!(rx.try_recv().is_ok());
+        }
+        assert!(all.try_recv().is_ok());
+        assert!(transfers.try_recv().is_ok());
+        assert!(mints.try_recv().is_err());
+        assert!(others.try_recv().is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use std::{collections::HashMap, sync::Arc, time::Duration};
+use std::{collections::HashMap, hash::Hash, sync::Arc, time::Duration};
 
 use actix_web::rt::time::interval;
 use actix_web_lab::{
@@ -8,17 +8,22 @@ use actix_web_lab::{
 use futures_util::future;
 use jstz_proto::context::account::Address;
 use parking_lot::Mutex;
-use tokio::sync::mpsc::{self, Sender};
+use tokio::sync::mpsc::{self, Receiver, Sender};
 use tokio_stream::wrappers::ReceiverStream;
 
-pub struct Broadcaster {
-    clients: Mutex<HashMap<Address, Vec<Sender<Event>>>>, // TODO: Use a read-write lock instead?
+/// Broadcasts messages to the SSE clients subscribed to a key `K` (by default,
+/// the address of a smart function)
+pub struct Broadcaster<K = Address> {
+    clients: Mutex<HashMap<K, Vec<Sender<Event>>>>, // TODO: Use a read-write lock instead?
 }
 
 // Pings clients every 10 seconds
 const PING_INTERVAL: u64 = 10;
 
-impl Broadcaster {
+impl<K> Broadcaster<K>
+where
+    K: Eq + Hash + Clone + Send + 'static,
+{
     /// Constructs new broadcaster and spawns ping loop responsible for removing stale clients.
     pub fn create() -> Arc<Self> {
         let this = Arc::new(Broadcaster::new());
@@ -51,7 +56,7 @@ impl Broadcaster {
     async fn remove_stale_clients(&self) {
         let clients = self.clients.lock().clone();
 
-        let mut responsive_clients: HashMap<Address, Vec<Sender<Event>>> = HashMap::new();
+        let mut responsive_clients: HashMap<K, Vec<Sender<Event>>> = HashMap::new();
 
         for (contract_address, senders) in clients {
             let mut responsive_senders = Vec::new();
@@ -75,8 +80,13 @@ impl Broadcaster {
     /// Registers client with broadcaster, returning an SSE response body.
     pub async fn new_client(
         &self,
-        contract_address: Address,
+        contract_address: K,
     ) -> Sse<InfallibleStream<ReceiverStream<sse::Event>>> {
+        Sse::from_infallible_receiver(self.subscribe(contract_address).await)
+    }
+
+    /// Registers client with broadcaster, returning the receiver of its messages.
+    pub(crate) async fn subscribe(&self, contract_address: K) -> Receiver<Event> {
         let (tx, rx) = mpsc::channel(10);
 
         tx.send(sse::Data::new("connected").into()).await.unwrap();
@@ -87,11 +97,11 @@ impl Broadcaster {
             .or_default()
             .push(tx);
 
-        Sse::from_infallible_receiver(rx)
+        rx
     }
 
     /// Broadcasts `msg` to all clients.
-    pub async fn broadcast(&self, contract_address: &Address, msg: &str) {
+    pub async fn broadcast(&self, contract_address: &K, msg: &str) {
         let clients = self.clients.lock().clone();
 
         if let Some(clients) = clients.get(contract_address) {
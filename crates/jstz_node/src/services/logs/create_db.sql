@@ -10,4 +10,15 @@ CREATE TABLE IF NOT EXISTS log (
     function_address TEXT NOT NULL,
     request_id TEXT NOT NULL,
         FOREIGN KEY (request_id) REFERENCES request (id)
-);
\ No newline at end of file
+);
+
+CREATE TABLE IF NOT EXISTS event (
+    id INTEGER PRIMARY KEY,
+    topic TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    function_address TEXT NOT NULL,
+    request_id TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS event_function_address_topic
+    ON event (function_address, topic);
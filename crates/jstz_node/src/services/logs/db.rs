@@ -5,7 +5,8 @@ use super::{Line, QueryResponse};
 use actix_web::web::block;
 use anyhow::{anyhow, Result};
 use jstz_proto::{
-    context::account::Address, js_logger::LogRecord, request_logger::RequestEvent,
+    context::account::Address, event_logger::EventRecord, js_logger::LogRecord,
+    request_logger::RequestEvent,
 };
 use r2d2::{Pool, PooledConnection};
 use r2d2_sqlite::SqliteConnectionManager;
@@ -39,6 +40,10 @@ impl Db {
         let manager = SqliteConnectionManager::file(db_path);
         let pool = SqliteConnectionPool::new(manager)?;
 
+        Self::from_pool(pool).await
+    }
+
+    async fn from_pool(pool: SqliteConnectionPool) -> Result<Self> {
         Self::create_table(pool.clone()).await?;
 
         Ok(Db { pool })
@@ -92,6 +97,15 @@ impl Db {
                     request_id
                 ),
             )?,
+            Line::Event(EventRecord {
+                address,
+                request_id,
+                topic,
+                payload,
+            }) => connection.execute(
+                "INSERT INTO event (topic, payload, function_address, request_id) VALUES (?1, ?2, ?3, ?4)",
+                (topic, payload.to_string(), address.to_string(), request_id),
+            )?,
             // TODO: Update the request row with more fields.
             Line::Request(_) => 0,
         };
@@ -126,6 +140,58 @@ impl Db {
         Self::collect_logs(stmt, [function_address.to_string(), request_id])
     }
 
+    pub async fn events_by_address(
+        &self,
+        function_address: Address,
+        topic: Option<String>,
+        limit: usize,
+        offset: usize,
+    ) -> QueryResponseResult {
+        let conn = self.connection().await?;
+
+        match topic {
+            Some(topic) => {
+                let stmt = conn.prepare(
+                    "SELECT * FROM event WHERE function_address = ? AND topic = ? ORDER BY id LIMIT ? OFFSET ?",
+                )?;
+                Self::collect_events(
+                    stmt,
+                    params![function_address.to_string(), topic, limit, offset],
+                )
+            }
+            None => {
+                let stmt = conn.prepare(
+                    "SELECT * FROM event WHERE function_address = ? ORDER BY id LIMIT ? OFFSET ?",
+                )?;
+                Self::collect_events(
+                    stmt,
+                    params![function_address.to_string(), limit, offset],
+                )
+            }
+        }
+    }
+
+    fn collect_events<P: Params>(
+        mut stmt: Statement<'_>,
+        params: P,
+    ) -> QueryResponseResult {
+        let events = stmt
+            .query_map(params, |row| {
+                let payload: String = row.get(2)?;
+                Ok(QueryResponse::Event {
+                    topic: row.get(1)?,
+                    payload: serde_json::from_str(&payload)
+                        .unwrap_or(serde_json::Value::String(payload)),
+                    function_address: row.get(3)?,
+                    request_id: row.get(4)?,
+                })
+            })?
+            .filter_map(Result::ok)
+            .collect();
+
+        Ok(events)
+    }
+
     fn collect_logs<P: Params>(
         mut stmt: Statement<'_>,
         params: P,
@@ -145,3 +211,106 @@ impl Db {
         Ok(logs)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    const ADDRESS: &str = "tz1XQjK1b3P72kMcHsoPhnAg3dvX1n8Ainty";
+    const OTHER_ADDRESS: &str = "tz1KqTpEZ7Yob7QbPE4Hy4Wo8fHG8LhKxZSx";
+
+    // Each connection to an in-memory database opens a new database, hence the
+    // pool of a single connection
+    async fn in_memory_db() -> Db {
+        let pool = SqliteConnectionPool::builder()
+            .max_size(1)
+            .build(SqliteConnectionManager::memory())
+            .expect("Could not create pool");
+        Db::from_pool(pool).await.expect("Could not create tables")
+    }
+
+    fn event(address: &str, topic: &str, payload: serde_json::Value) -> Line {
+        Line::Event(EventRecord {
+            address: Address::from_base58(address).expect("Could not parse address"),
+            request_id: "request".to_string(),
+            topic: topic.to_string(),
+            payload,
+        })
+    }
+
+    async fn events(
+        db: &Db,
+        topic: Option<&str>,
+        limit: usize,
+        offset: usize,
+    ) -> serde_json::Value {
+        let address = Address::from_base58(ADDRESS).expect("Could not parse address");
+        let events = db
+            .events_by_address(address, topic.map(str::to_string), limit, offset)
+            .await
+            .expect("Could not query events");
+        serde_json::to_value(events).unwrap()
+    }
+
+    fn response(topic: &str, payload: serde_json::Value) -> serde_json::Value {
+        json!({
+            "Event": {
+                "topic": topic,
+                "payload": payload,
+                "function_address": ADDRESS,
+                "request_id": "request",
+            }
+        })
+    }
+
+    #[actix_web::test]
+    async fn events_are_queried_by_address_and_topic() {
+        let db = in_memory_db().await;
+        db.flush(&event(ADDRESS, "mint", json!({ "amount": 1 })))
+            .await
+            .unwrap();
+        db.flush(&event(ADDRESS, "transfer", json!("text")))
+            .await
+            .unwrap();
+        db.flush(&event(OTHER_ADDRESS, "mint", json!(null)))
+            .await
+            .unwrap();
+        db.flush(&event(ADDRESS, "mint", json!([2]))).await.unwrap();
+
+        assert_eq!(
+            events(&db, None, 100, 0).await,
+            json!([
+                response("mint", json!({ "amount": 1 })),
+                response("transfer", json!("text")),
+                response("mint", json!([2])),
+            ])
+        );
+        assert_eq!(
+            events(&db, Some("mint"), 100, 0).await,
+            json!([
+                response("mint", json!({ "amount": 1 })),
+                response("mint", json!([2])),
+            ])
+        );
+        assert_eq!(events(&db, Some("burn"), 100, 0).await, json!([]));
+    }
+
+    #[actix_web::test]
+    async fn events_are_paginated() {
+        let db = in_memory_db().await;
+        for n in 0..5 {
+            db.flush(&event(ADDRESS, "mint", json!(n))).await.unwrap();
+        }
+
+        assert_eq!(
+            events(&db, Some("mint"), 2, 1).await,
+            json!([response("mint", json!(1)), response("mint", json!(2))])
+        );
+        assert_eq!(
+            events(&db, None, 100, 4).await,
+            json!([response("mint", json!(4))])
+        );
+    }
+}
@@ -9,6 +9,7 @@ use super::Service;
 use anyhow;
 use jstz_proto::context::account::Address;
 use jstz_proto::{
+    event_logger::{EventRecord, EVENT_PREFIX},
     js_logger::{LogRecord, LOG_PREFIX},
     request_logger::{RequestEvent, REQUEST_END_PREFIX, REQUEST_START_PREFIX},
 };
@@ -33,7 +34,9 @@ mod db {
     }
 }
 
-use self::{broadcaster::Broadcaster, db::Db};
+use self::broadcaster::Broadcaster;
+pub(crate) use self::db::Db;
+use super::events::EventSubscription;
 
 #[cfg(feature = "persistent-logging")]
 mod persistent_logging {
@@ -99,6 +102,12 @@ mod persistent_logging {
             function_address: String,
             request_id: String,
         },
+        Event {
+            topic: String,
+            payload: serde_json::Value,
+            function_address: String,
+            request_id: String,
+        },
     }
 
     /// Queries the log database.
@@ -163,6 +172,8 @@ pub enum Line {
     Request(RequestEvent),
     // Indicates the js log message from the smart function (e.g. log).
     Js(LogRecord),
+    // Indicates an event emitted by the smart function (with `Jstz.emit`).
+    Event(EventRecord),
 }
 
 impl LogsService {
@@ -170,9 +181,15 @@ impl LogsService {
     pub async fn init(
         path: &std::path::Path,
         cancellation_token: &CancellationToken,
-    ) -> anyhow::Result<(Arc<Broadcaster>, Db, JoinHandle<std::io::Result<()>>)> {
-        // Create a broadcaster for streaming logs.
+    ) -> anyhow::Result<(
+        Arc<Broadcaster>,
+        Arc<Broadcaster<EventSubscription>>,
+        Db,
+        JoinHandle<std::io::Result<()>>,
+    )> {
+        // Create broadcasters for streaming logs and events.
         let broadcaster = Broadcaster::create();
+        let event_broadcaster = Broadcaster::create();
 
         // Create a connection with the sqlite database.
         let db = Db::init().await?;
@@ -183,12 +200,13 @@ impl LogsService {
         let tail_file_handle = Self::tail_file(
             file,
             broadcaster.clone(),
+            event_broadcaster.clone(),
             db.clone(),
             cancellation_token.clone(),
         )
         .await;
 
-        Ok((broadcaster, db, tail_file_handle))
+        Ok((broadcaster, event_broadcaster, db, tail_file_handle))
     }
 
     /// Spawn a future that tails log file.
@@ -196,6 +214,7 @@ impl LogsService {
     async fn tail_file(
         file: TailedFile,
         broadcaster: Arc<Broadcaster>,
+        event_broadcaster: Arc<Broadcaster<EventSubscription>>,
         #[allow(unused_variables)] db: Db,
         cancellation_token: CancellationToken,
     ) -> JoinHandle<std::io::Result<()>> {
@@ -216,11 +235,19 @@ impl LogsService {
                                     );
                                 }
 
-                                // Stream the log
-                                if let Line::Js(log) = line {
-                                    broadcaster
-                                        .broadcast(&log.address, &line_str[LOG_PREFIX.len()..])
-                                        .await;
+                                // Stream the log or event
+                                match line {
+                                    Line::Js(log) => {
+                                        let msg = &line_str[LOG_PREFIX.len()..];
+                                        broadcaster.broadcast(&log.address, msg).await;
+                                    }
+                                    Line::Event(event) => {
+                                        let msg = &line_str[EVENT_PREFIX.len()..];
+                                        for subscription in EventSubscription::matching(&event) {
+                                            event_broadcaster.broadcast(&subscription, msg).await;
+                                        }
+                                    }
+                                    Line::Request(_) => (),
                                 }
                             }
                         }
@@ -241,6 +268,11 @@ impl LogsService {
             return LogRecord::try_from_string(&line[LOG_PREFIX.len()..]).map(Line::Js);
         }
 
+        if line.starts_with(EVENT_PREFIX) {
+            return EventRecord::try_from_string(&line[EVENT_PREFIX.len()..])
+                .map(Line::Event);
+        }
+
         if line.starts_with(REQUEST_START_PREFIX) {
             return RequestEvent::try_from_string(&line[REQUEST_START_PREFIX.len()..])
                 .map(Line::Request);
@@ -254,3 +286,41 @@ impl LogsService {
         None
     }
 }
+
+#[cfg(test)]
+mod test {
+    use jstz_proto::{context::account::Address, event_logger::EventRecord};
+
+    use super::*;
+
+    fn event() -> EventRecord {
+        EventRecord {
+            address: Address::from_base58("tz1XQjK1b3P72kMcHsoPhnAg3dvX1n8Ainty")
+                .expect("Could not parse address"),
+            request_id: "request".to_string(),
+            topic: "transfer".to_string(),
+            payload: serde_json::json!({ "to": "tz1", "amount": 42 }),
+        }
+    }
+
+    #[test]
+    fn event_lines_are_parsed() {
+        let line = format!("{}{}", EVENT_PREFIX, event());
+
+        let Some(Line::Event(parsed)) = LogsService::parse_line(&line) else {
+            panic!("Expected an event line")
+        };
+        assert_eq!(parsed.address, event().address);
+        assert_eq!(parsed.request_id, "request");
+        assert_eq!(parsed.topic, "transfer");
+        assert_eq!(parsed.payload, event().payload);
+    }
+
+    #[test]
+    fn invalid_event_lines_are_ignored() {
+        assert!(LogsService::parse_line(&format!("{}{{", EVENT_PREFIX)).is_none());
+        assert!(LogsService::parse_line(&event().to_string()).is_none());
+        // Events are not log records
+        assert!(LogsService::parse_line(&format!("{}{}", LOG_PREFIX, event())).is_none());
+    }
+}
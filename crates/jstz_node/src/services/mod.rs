@@ -1,9 +1,11 @@
 mod accounts;
+pub mod events;
 pub mod logs;
 mod operations;
 
 pub use accounts::AccountsService;
 use actix_web::web::ServiceConfig;
+pub use events::EventsService;
 pub use logs::LogsService;
 pub use operations::OperationsService;
 
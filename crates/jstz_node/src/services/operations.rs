@@ -4,7 +4,13 @@ use actix_web::{
     HttpResponse, Responder, Scope,
 };
 use anyhow::anyhow;
-use jstz_proto::{operation::SignedOperation, receipt::Receipt};
+use jstz_proto::{
+    operation::{
+        ChunkedOperationHeader, ExternalMessageContent, OperationChunk, SignedOperation,
+        MAX_CHUNKS,
+    },
+    receipt::Receipt,
+};
 use octez::OctezRollupClient;
 use tezos_data_encoding::enc::BinWriter;
 use tezos_smart_rollup::inbox::ExternalMessageFrame;
@@ -13,27 +19,84 @@ use crate::Result;
 
 use super::Service;
 
+/// Maximum size of the contents of an external message: the inbox message
+/// limit (4096 bytes) minus the external message tag and the targetted frame
+const MAX_MESSAGE_CONTENTS_SIZE: usize = 4096 - 22;
+
+/// Number of bytes of an operation carried by each chunk, leaving room for the
+/// encoding of the chunk itself (variant tag, header id, index and length)
+const CHUNK_SIZE: usize = MAX_MESSAGE_CONTENTS_SIZE - 64;
+
+/// Encodes `operation` as the contents of external messages. Operations too
+/// large for a single message are sent as a header followed by chunks,
+/// reassembled by the kernel.
+fn encode_operation(operation: SignedOperation) -> Result<Vec<Vec<u8>>> {
+    let bytes = bincode::serialize(&operation)
+        .map_err(|_| anyhow!("Failed to serialize operation"))?;
+    let chunks: Vec<&[u8]> = bytes.chunks(CHUNK_SIZE).collect();
+    let header = ChunkedOperationHeader::new(&operation, &chunks);
+
+    let message = bincode::serialize(&ExternalMessageContent::Operation(operation))
+        .map_err(|_| anyhow!("Failed to serialize operation"))?;
+    if message.len() <= MAX_MESSAGE_CONTENTS_SIZE {
+        return Ok(vec![message]);
+    }
+
+    if chunks.len() > MAX_CHUNKS {
+        return Err(anyhow!(
+            "Operation too large: {} bytes, the limit is {} bytes",
+            bytes.len(),
+            MAX_CHUNKS * CHUNK_SIZE
+        )
+        .into());
+    }
+
+    let id = header.id();
+    let header = bincode::serialize(&ExternalMessageContent::ChunkedOperation(header))
+        .map_err(|_| anyhow!("Failed to serialize operation header"))?;
+    if header.len() > MAX_MESSAGE_CONTENTS_SIZE {
+        return Err(anyhow!("Operation header too large: {} bytes", header.len()).into());
+    }
+
+    let mut messages = vec![header];
+    for (index, chunk) in chunks.into_iter().enumerate() {
+        let chunk = ExternalMessageContent::Chunk(OperationChunk {
+            header: id.clone(),
+            index: index as u32,
+            bytes: chunk.to_vec(),
+        });
+        messages.push(
+            bincode::serialize(&chunk)
+                .map_err(|_| anyhow!("Failed to serialize operation chunk"))?,
+        );
+    }
+    Ok(messages)
+}
+
 #[post("")]
 async fn inject(
     rollup_client: Data<OctezRollupClient>,
     operation: web::Json<SignedOperation>,
 ) -> Result<impl Responder> {
-    let encoded_operation = bincode::serialize(&operation)
-        .map_err(|_| anyhow!("Failed to serialize operation"))?;
+    let messages = encode_operation(operation.into_inner())?;
 
     let address = rollup_client.get_rollup_address().await?;
 
-    let message_frame = ExternalMessageFrame::Targetted {
-        address,
-        contents: encoded_operation,
-    };
+    let mut binary_messages = Vec::with_capacity(messages.len());
+    for contents in messages {
+        let message_frame = ExternalMessageFrame::Targetted {
+            address: address.clone(),
+            contents,
+        };
 
-    let mut binary_contents = Vec::new();
-    message_frame
-        .bin_write(&mut binary_contents)
-        .map_err(|_| anyhow!("Failed to write binary frame"))?;
+        let mut binary_contents = Vec::new();
+        message_frame
+            .bin_write(&mut binary_contents)
+            .map_err(|_| anyhow!("Failed to write binary frame"))?;
+        binary_messages.push(binary_contents);
+    }
 
-    rollup_client.batcher_injection([binary_contents]).await?;
+    rollup_client.batcher_injection(binary_messages).await?;
 
     Ok(HttpResponse::Ok())
 }
@@ -56,12 +119,135 @@ async fn receipt(
     Ok(HttpResponse::Ok().json(receipt))
 }
 
+/// The address of the rollup, which signed operations must be bound to
+#[get("/rollup_address")]
+async fn rollup_address(
+    rollup_client: Data<OctezRollupClient>,
+) -> Result<impl Responder> {
+    let address = rollup_client.get_rollup_address().await?;
+
+    Ok(HttpResponse::Ok().json(address.hash().to_base58_check()))
+}
+
 pub struct OperationsService;
 
 impl Service for OperationsService {
     fn configure(cfg: &mut ServiceConfig) {
-        let scope = Scope::new("/operations").service(inject).service(receipt);
+        let scope = Scope::new("/operations")
+            .service(inject)
+            .service(rollup_address)
+            .service(receipt);
 
         cfg.service(scope);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use jstz_crypto::{hash::Blake2b, keypair_from_passphrase};
+    use jstz_proto::{
+        context::account::{Address, Modules, Nonce, ParsedCode},
+        operation::{Content, DeployFunction, Operation},
+    };
+    use tezos_crypto_rs::hash::SmartRollupHash;
+
+    use super::*;
+
+    /// A signed deployment whose code is a comment of `len` bytes
+    fn deploy(len: usize) -> SignedOperation {
+        let (sk, pk) = keypair_from_passphrase("alice").unwrap();
+        let code = format!("//{}", "x".repeat(len));
+        let op = Operation {
+            source: Address::try_from(&pk).unwrap(),
+            nonce: Nonce::default(),
+            content: Content::DeployFunction(DeployFunction {
+                function_code: ParsedCode::try_from(code).unwrap(),
+                account_credit: 0,
+                admin: None,
+                salt: None,
+                modules: Modules::default(),
+            }),
+            rollup_address: SmartRollupHash::try_from(vec![0; 20]).unwrap(),
+            valid_until_level: None,
+        };
+        let signature = sk.sign(op.hash()).unwrap();
+        SignedOperation::new(pk, signature, op)
+    }
+
+    /// A signed operation of exactly `size` serialized bytes
+    fn operation_of_size(size: usize) -> SignedOperation {
+        let overhead = bincode::serialized_size(&deploy(0)).unwrap() as usize;
+        let operation = deploy(size - overhead);
+        assert_eq!(bincode::serialized_size(&operation).unwrap() as usize, size);
+        operation
+    }
+
+    fn decode(message: &[u8]) -> ExternalMessageContent {
+        assert!(message.len() <= MAX_MESSAGE_CONTENTS_SIZE);
+        bincode::deserialize(message).unwrap()
+    }
+
+    #[test]
+    fn small_operation_is_sent_in_one_message() {
+        let operation = deploy(1000);
+        let hash = operation.hash();
+
+        let messages = encode_operation(operation).unwrap();
+        assert_eq!(messages.len(), 1);
+        match decode(&messages[0]) {
+            ExternalMessageContent::Operation(operation) => {
+                assert_eq!(operation.hash(), hash)
+            }
+            content => panic!("Unexpected message: {content:?}"),
+        }
+    }
+
+    #[test]
+    fn large_operation_is_sent_in_chunks() {
+        let operation = operation_of_size(3 * CHUNK_SIZE + 1);
+        let bytes = bincode::serialize(&operation).unwrap();
+        let hash = operation.hash();
+
+        let messages = encode_operation(operation).unwrap();
+        assert_eq!(messages.len(), 5);
+
+        let header = match decode(&messages[0]) {
+            ExternalMessageContent::ChunkedOperation(header) => header,
+            content => panic!("Unexpected message: {content:?}"),
+        };
+        assert_eq!(header.hash, hash);
+        assert!(header
+            .signature
+            .verify(&header.public_key, header.hash.as_ref())
+            .is_ok());
+        assert_eq!(header.chunk_hashes.len(), 4);
+
+        let mut reassembled = Vec::new();
+        for (index, message) in messages[1..].iter().enumerate() {
+            match decode(message) {
+                ExternalMessageContent::Chunk(chunk) => {
+                    assert_eq!(chunk.header, header.id());
+                    assert_eq!(chunk.index, index as u32);
+                    assert_eq!(Blake2b::from(&chunk.bytes), header.chunk_hashes[index]);
+                    reassembled.extend(chunk.bytes);
+                }
+                content => panic!("Unexpected message: {content:?}"),
+            }
+        }
+        assert_eq!(reassembled, bytes);
+    }
+
+    #[test]
+    fn largest_operation_fits_in_chunks() {
+        let messages =
+            encode_operation(operation_of_size(MAX_CHUNKS * CHUNK_SIZE)).unwrap();
+        assert_eq!(messages.len(), MAX_CHUNKS + 1);
+        for message in messages {
+            decode(&message);
+        }
+
+        assert!(
+            encode_operation(operation_of_size(MAX_CHUNKS * CHUNK_SIZE + 1)).is_err()
+        );
+    }
+}
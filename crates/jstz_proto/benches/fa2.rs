@@ -0,0 +1,118 @@
+//! Benchmarks `examples/benchmark.js`, which mints FA2 tokens and transfers them
+//! `n` times, calling the FA2 smart function once per transfer.
+//!
+//! The FA2 example must be built beforehand:
+//!
+//! ```sh
+//! (cd examples/fa2 && npm install && npm run build)
+//! cargo bench --package jstz_proto --bench fa2
+//! ```
+
+use std::{fs, path::PathBuf};
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use jstz_core::kv::Transaction;
+use jstz_crypto::{
+    keypair_from_passphrase_with_key_type, public_key::PublicKey, secret_key::SecretKey,
+    KeyType,
+};
+use jstz_proto::{
+    context::account::{Address, Modules, Nonce, ParsedCode},
+    executor::{execute_operation, smart_function::Script},
+    operation::{Content, Operation, RunFunction, SignedOperation},
+    receipt,
+};
+use tezos_smart_rollup::prelude::Runtime;
+use tezos_smart_rollup_mock::MockHost;
+
+const BENCHMARK_CODE: &str = include_str!("../../../examples/benchmark.js");
+
+const GAS_LIMIT: usize = 10_000_000;
+
+fn fa2_code() -> String {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("../../examples/fa2/dist/index.js");
+    fs::read_to_string(&path).unwrap_or_else(|err| {
+        panic!(
+            "Could not read {}: {err}. Build the FA2 example with `npm run build`.",
+            path.display()
+        )
+    })
+}
+
+fn deploy(
+    hrt: &mut MockHost,
+    tx: &mut Transaction,
+    source: &Address,
+    code: &str,
+) -> Address {
+    let code = ParsedCode::try_from(code.to_string()).expect("Could not parse code");
+    Script::deploy(hrt, tx, source, code, Modules::default(), 0, None, None)
+        .expect("Could not deploy code")
+}
+
+/// A host where the FA2 and benchmark smart functions are deployed, and the
+/// signed operation running the benchmark with `n` transfers
+fn setup(
+    (sk, pk, source): &(SecretKey, PublicKey, Address),
+    fa2_code: &str,
+    n: usize,
+) -> (MockHost, Transaction, SignedOperation) {
+    let mut hrt = MockHost::default();
+    let mut tx = Transaction::default();
+    tx.begin();
+
+    let fa2 = deploy(&mut hrt, &mut tx, source, fa2_code);
+    let benchmark = deploy(&mut hrt, &mut tx, source, BENCHMARK_CODE);
+
+    let op = Operation {
+        source: source.clone(),
+        nonce: Nonce::default(),
+        content: Content::RunFunction(RunFunction {
+            uri: format!("tezos://{benchmark}/?n={n}&fa2={fa2}")
+                .parse()
+                .unwrap(),
+            method: http::Method::GET,
+            headers: http::HeaderMap::new(),
+            body: None,
+            gas_limit: GAS_LIMIT,
+        }),
+        rollup_address: hrt.reveal_metadata().address(),
+        valid_until_level: None,
+    };
+    let signature = sk.sign(op.hash()).expect("Could not sign operation");
+    let op = SignedOperation::new(pk.clone(), signature, op);
+
+    (hrt, tx, op)
+}
+
+fn run(
+    (mut hrt, mut tx, op): (MockHost, Transaction, SignedOperation),
+) -> receipt::RunFunction {
+    match execute_operation(&mut hrt, &mut tx, op).inner {
+        Ok(receipt::Content::RunFunction(run)) => run,
+        other => panic!("Unexpected receipt: {other:?}"),
+    }
+}
+
+fn fa2_transfers(c: &mut Criterion) {
+    let fa2_code = fa2_code();
+    let (sk, pk) = keypair_from_passphrase_with_key_type(KeyType::Ed25519, "benchmark")
+        .expect("Could not generate keypair");
+    let source = Address::try_from(&pk).expect("Could not hash public key");
+    let keypair = (sk, pk, source);
+
+    let mut group = c.benchmark_group("fa2_transfers");
+    for n in [1, 10, 100] {
+        let gas_used = run(setup(&keypair, &fa2_code, n)).gas_used;
+        println!("fa2_transfers/{n}: {gas_used} gas");
+
+        group.bench_with_input(BenchmarkId::from_parameter(n), &n, |b, &n| {
+            b.iter_batched(|| setup(&keypair, &fa2_code, n), run, BatchSize::SmallInput)
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, fa2_transfers);
+criterion_main!(benches);
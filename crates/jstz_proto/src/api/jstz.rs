@@ -0,0 +1,351 @@
+use std::ops::Deref;
+
+use boa_engine::{
+    js_string,
+    object::{builtins::JsArray, JsObject, Object, ObjectInitializer},
+    property::Attribute,
+    Context, JsArgs, JsNativeError, JsResult, JsValue, NativeFunction,
+};
+use boa_gc::{empty_trace, Finalize, GcRefMut, Trace};
+use jstz_api::http::request::Request;
+use jstz_core::{
+    gas::{self, schedule, GasCost},
+    host::HostRuntime,
+    journal,
+    kv::Transaction,
+    native::{Accessor, JsNativeObject},
+    runtime,
+};
+
+use crate::{
+    context::{
+        account::Address,
+        block::BlockInfo,
+        scheduler::{self, ScheduledCall, Scheduler},
+    },
+    operation::{OperationHash, RunFunction},
+    receipt, Result,
+};
+
+// Jstz.block.level
+// Jstz.block.timestamp
+// Jstz.schedule(request, { atLevel, every, times, gasLimit })
+// Jstz.emit(topic, payload)
+
+struct Jstz {
+    address: Address,
+}
+
+impl Finalize for Jstz {}
+
+unsafe impl Trace for Jstz {
+    empty_trace!();
+}
+
+/// Options of `Jstz.schedule`
+struct ScheduleOptions {
+    at_level: u32,
+    every: u32,
+    times: u32,
+    gas_limit: usize,
+}
+
+impl ScheduleOptions {
+    fn get_u32(
+        options: &JsObject,
+        name: &str,
+        context: &mut Context<'_>,
+    ) -> JsResult<Option<u32>> {
+        let value = options.get(js_string!(name), context)?;
+        if value.is_undefined() {
+            return Ok(None);
+        }
+
+        match value.as_number() {
+            Some(n) if n.fract() == 0.0 && (0.0..=u32::MAX as f64).contains(&n) => {
+                Ok(Some(n as u32))
+            }
+            _ => Err(JsNativeError::typ()
+                .with_message(format!("`{}` must be a non-negative integer", name))
+                .into()),
+        }
+    }
+
+    fn required_u32(
+        options: &JsObject,
+        name: &str,
+        context: &mut Context<'_>,
+    ) -> JsResult<u32> {
+        Self::get_u32(options, name, context)?.ok_or_else(|| {
+            JsNativeError::typ()
+                .with_message(format!("Missing option `{}`", name))
+                .into()
+        })
+    }
+
+    fn from_js(value: &JsValue, context: &mut Context<'_>) -> JsResult<Self> {
+        let options = value.as_object().ok_or_else(|| {
+            JsNativeError::typ().with_message("Expected schedule options object")
+        })?;
+
+        let at_level = Self::required_u32(options, "atLevel", context)?;
+        let gas_limit = Self::required_u32(options, "gasLimit", context)? as usize;
+        let times = Self::get_u32(options, "times", context)?.unwrap_or(1);
+        let every = Self::get_u32(options, "every", context)?;
+
+        if gas_limit == 0 {
+            return Err(JsNativeError::range()
+                .with_message("`gasLimit` must be at least 1")
+                .into());
+        }
+
+        if times == 0 || times > scheduler::MAX_RUNS {
+            return Err(JsNativeError::range()
+                .with_message(format!(
+                    "`times` must be between 1 and {}",
+                    scheduler::MAX_RUNS
+                ))
+                .into());
+        }
+
+        let every = match every {
+            Some(every) if every > 0 => every,
+            None if times == 1 => 0,
+            _ => {
+                return Err(JsNativeError::range()
+                    .with_message("`every` must be at least 1 for recurring calls")
+                    .into())
+            }
+        };
+
+        Ok(Self {
+            at_level,
+            every,
+            times,
+            gas_limit,
+        })
+    }
+
+    /// Gas prepaid by the scheduler for all runs of the call, including
+    /// storing their receipts
+    fn prepaid_gas(&self) -> usize {
+        self.gas_limit
+            .saturating_add(schedule::JSTZ_SCHEDULE_RUN.cost(0))
+            .saturating_mul(self.times as usize)
+    }
+}
+
+impl Jstz {
+    fn try_from_js(value: &JsValue) -> JsResult<GcRefMut<'_, Object, Self>> {
+        value
+            .as_object()
+            .and_then(|obj| obj.downcast_mut::<Self>())
+            .ok_or_else(|| {
+                JsNativeError::typ()
+                    .with_message("Failed to convert js value into rust type `Jstz`")
+                    .into()
+            })
+    }
+
+    /// Schedules `run`, returning the hashes of the receipts of all its runs
+    fn schedule(
+        &self,
+        hrt: &impl HostRuntime,
+        tx: &mut Transaction,
+        run: RunFunction,
+        options: &ScheduleOptions,
+    ) -> Result<Vec<OperationHash>> {
+        let id = Scheduler::next_id(hrt, tx)?;
+
+        Scheduler::schedule(
+            hrt,
+            tx,
+            ScheduledCall {
+                id,
+                scheduler: self.address.clone(),
+                run,
+                level: options.at_level,
+                every: options.every,
+                remaining: options.times,
+            },
+        )?;
+
+        Ok((0..options.times)
+            .map(|n| {
+                let level = options
+                    .at_level
+                    .saturating_add(n.saturating_mul(options.every));
+                ScheduledCall::receipt_hash_at(id, level)
+            })
+            .collect())
+    }
+}
+
+pub struct JstzApi {
+    pub address: Address,
+}
+
+impl JstzApi {
+    const NAME: &'static str = "Jstz";
+
+    fn block(context: &mut Context<'_>) -> Accessor {
+        Accessor::new("block").get(NativeFunction::from_fn_ptr(Self::get_block), context)
+    }
+
+    fn get_block(
+        _this: &JsValue,
+        _args: &[JsValue],
+        context: &mut Context<'_>,
+    ) -> JsResult<JsValue> {
+        let level = runtime::with_js_hrt(|hrt| BlockInfo::get(hrt.deref()))?
+            .map_or(0, |block| block.level);
+
+        // The timestamp is the one observed by `Date.now()`, in milliseconds
+        let timestamp = context.host_hooks().utc_now().timestamp_millis();
+
+        let block = ObjectInitializer::new(context)
+            .property(js_string!("level"), level, Attribute::ENUMERABLE)
+            .property(
+                js_string!("timestamp"),
+                timestamp as f64,
+                Attribute::ENUMERABLE,
+            )
+            .build();
+
+        Ok(block.into())
+    }
+
+    fn emit(
+        this: &JsValue,
+        args: &[JsValue],
+        context: &mut Context<'_>,
+    ) -> JsResult<JsValue> {
+        let jstz = Jstz::try_from_js(this)?;
+        let topic = args
+            .get_or_undefined(0)
+            .as_string()
+            .ok_or_else(|| {
+                JsNativeError::typ().with_message("Event topic must be a string")
+            })?
+            .to_std_string_escaped();
+
+        let payload = match args.get_or_undefined(1) {
+            value if value.is_undefined() => serde_json::Value::Null,
+            value => value.to_json(context)?,
+        }
+        .to_string();
+
+        gas::charge(
+            context,
+            "Jstz.emit",
+            schedule::JSTZ_EMIT,
+            topic.len() + payload.len(),
+        )?;
+
+        // The event is discarded if the call emitting it is rolled back
+        journal::record(receipt::Event {
+            address: jstz.address.clone(),
+            topic,
+            payload,
+        });
+
+        Ok(JsValue::undefined())
+    }
+
+    fn schedule(
+        this: &JsValue,
+        args: &[JsValue],
+        context: &mut Context<'_>,
+    ) -> JsResult<JsValue> {
+        let jstz = Jstz::try_from_js(this)?;
+        let request: JsNativeObject<Request> =
+            args.get_or_undefined(0).clone().try_into()?;
+        let options = ScheduleOptions::from_js(args.get_or_undefined(1), context)?;
+
+        gas::charge(
+            context,
+            "Jstz.schedule",
+            schedule::JSTZ_SCHEDULE,
+            request.deref().body().size(),
+        )?;
+        // The scheduler prepays the gas of all runs of the call
+        gas::charge(
+            context,
+            "Jstz.schedule",
+            GasCost::new(options.prepaid_gas(), 0),
+            0,
+        )?;
+
+        let run = {
+            let request = request.deref();
+            let uri =
+                request.url().href().parse().map_err(|_| {
+                    JsNativeError::typ().with_message("Invalid request URL")
+                })?;
+
+            RunFunction {
+                uri,
+                method: request.method().clone(),
+                headers: request.headers().deref().to_http_headers(),
+                body: request.body().to_http_body(),
+                gas_limit: options.gas_limit,
+            }
+        };
+
+        let hashes = runtime::with_js_hrt_and_tx(|hrt, tx| -> JsResult<_> {
+            let level = BlockInfo::get(hrt.deref())?.map_or(0, |block| block.level);
+            if options.at_level <= level {
+                return Err(JsNativeError::range()
+                    .with_message(format!(
+                        "Calls must be scheduled after the current level ({})",
+                        level
+                    ))
+                    .into());
+            }
+
+            Ok(jstz.schedule(hrt.deref(), tx, run, &options)?)
+        })?;
+
+        Ok(JsArray::from_iter(
+            hashes
+                .iter()
+                .map(|hash| js_string!(hash.to_string()).into()),
+            context,
+        )
+        .into())
+    }
+}
+
+impl jstz_core::Api for JstzApi {
+    fn init(self, context: &mut Context<'_>) {
+        let block = Self::block(context);
+
+        let jstz = ObjectInitializer::with_native(
+            Jstz {
+                address: self.address,
+            },
+            context,
+        )
+        .accessor(
+            js_string!(block.name),
+            block.get,
+            block.set,
+            Attribute::ENUMERABLE,
+        )
+        .function(
+            NativeFunction::from_fn_ptr(Self::schedule),
+            js_string!("schedule"),
+            2,
+        )
+        .function(
+            NativeFunction::from_fn_ptr(Self::emit),
+            js_string!("emit"),
+            2,
+        )
+        .build();
+
+        context
+            .register_global_property(js_string!(Self::NAME), jstz, Attribute::all())
+            .expect("The jstz object shouldn't exist yet");
+    }
+}
@@ -4,23 +4,39 @@ use boa_engine::{
     js_string,
     object::{Object, ObjectInitializer},
     property::Attribute,
-    Context, JsArgs, JsNativeError, JsResult, JsString, JsValue, NativeFunction,
+    Context, JsArgs, JsBigInt, JsNativeError, JsResult, JsString, JsValue,
+    NativeFunction,
 };
 use boa_gc::{empty_trace, Finalize, GcRefMut, Trace};
 
 use jstz_core::{
-    accessor, host::HostRuntime, kv::Transaction, native::Accessor, runtime,
+    accessor,
+    gas::{self, schedule},
+    host::HostRuntime,
+    kv::Transaction,
+    native::Accessor,
+    runtime,
     value::IntoJs,
 };
 
 use crate::{
-    context::account::{Account, Address, Amount},
+    context::{
+        account::{Account, Address, Amount},
+        allowance::Allowance,
+        asset::Asset,
+    },
     error::Result,
+    executor::withdraw,
+    operation::Withdraw,
 };
 
 // Ledger.selfAddress
-// Ledger.balance(pkh)
-// Ledger.transfer(dst, amount)
+// Ledger.balance(pkh, asset?)
+// Ledger.transfer(dst, amount, asset?)
+// Ledger.withdraw(l1Address, amount)
+// Ledger.approve(spender, amount, asset?)
+// Ledger.allowance(owner, spender, asset?)
+// Ledger.transferFrom(owner, dst, amount, asset?)
 
 struct Ledger {
     address: Address,
@@ -41,8 +57,12 @@ impl Ledger {
         rt: &impl HostRuntime,
         tx: &mut Transaction,
         addr: &Address,
+        asset: Option<&Asset>,
     ) -> Result<u64> {
-        let balance = Account::balance(rt, tx, addr)?;
+        let balance = match asset {
+            Some(asset) => Account::asset_balance(rt, tx, addr, asset)?,
+            None => Account::balance(rt, tx, addr)?,
+        };
 
         Ok(balance)
     }
@@ -53,11 +73,50 @@ impl Ledger {
         tx: &mut Transaction,
         dst: &Address,
         amount: Amount,
+        asset: Option<&Asset>,
     ) -> Result<()> {
-        Account::transfer(rt, tx, &self.address, dst, amount)?;
+        match asset {
+            Some(asset) => {
+                Account::transfer_asset(rt, tx, &self.address, dst, asset, amount)?
+            }
+            None => Account::transfer(rt, tx, &self.address, dst, amount)?,
+        }
 
         Ok(())
     }
+
+    fn approve(
+        &self,
+        rt: &impl HostRuntime,
+        tx: &mut Transaction,
+        spender: &Address,
+        amount: Amount,
+        asset: Option<&Asset>,
+    ) -> Result<()> {
+        Allowance::approve(rt, tx, &self.address, spender, asset, amount)
+    }
+
+    fn transfer_from(
+        &self,
+        rt: &impl HostRuntime,
+        tx: &mut Transaction,
+        owner: &Address,
+        dst: &Address,
+        amount: Amount,
+        asset: Option<&Asset>,
+    ) -> Result<()> {
+        Allowance::transfer_from(rt, tx, &self.address, owner, dst, asset, amount)
+    }
+
+    fn withdraw(
+        &self,
+        rt: &impl HostRuntime,
+        tx: &mut Transaction,
+        receiver: Address,
+        amount: Amount,
+    ) -> Result<()> {
+        withdraw::execute(rt, tx, &self.address, Withdraw { receiver, amount })
+    }
 }
 
 pub struct LedgerApi {
@@ -76,6 +135,58 @@ pub(crate) fn js_value_to_pkh(value: &JsValue) -> Result<Address> {
     Ok(Address::from_base58(&pkh_string)?)
 }
 
+/// Largest integer exactly representable by a JS number (`Number.MAX_SAFE_INTEGER`)
+const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_991.0;
+
+/// Converts an amount given either as a `BigInt` or as a safe integer `number`.
+/// Amounts that cannot be represented exactly as an `Amount` are rejected
+/// rather than truncated.
+pub(crate) fn js_value_to_amount(value: &JsValue) -> JsResult<Amount> {
+    if let Some(amount) = value.as_bigint() {
+        return amount.to_string().parse().map_err(|_| {
+            JsNativeError::range()
+                .with_message(format!(
+                    "Amount must be between 0 and {}, got {}",
+                    Amount::MAX,
+                    amount
+                ))
+                .into()
+        });
+    }
+
+    match value.as_number() {
+        Some(amount)
+            if amount.fract() == 0.0 && (0.0..=MAX_SAFE_INTEGER).contains(&amount) =>
+        {
+            Ok(amount as Amount)
+        }
+        Some(amount) => Err(JsNativeError::range()
+            .with_message(format!(
+                "Amount must be a non-negative safe integer, got {}",
+                amount
+            ))
+            .into()),
+        None => Err(JsNativeError::typ()
+            .with_message("Amount must be a bigint or a number")
+            .into()),
+    }
+}
+
+/// Converts an optional asset argument, `undefined` being the native asset
+fn js_value_to_asset(value: &JsValue) -> Result<Option<Asset>> {
+    if value.is_undefined() {
+        return Ok(None);
+    }
+
+    let asset = value
+        .as_string()
+        .ok_or_else(|| JsNativeError::typ().with_message("Asset must be a string"))?
+        .to_std_string_escaped()
+        .parse()?;
+
+    Ok(Some(asset))
+}
+
 impl Ledger {
     fn try_from_js(value: &JsValue) -> JsResult<GcRefMut<'_, Object, Self>> {
         value
@@ -104,31 +215,114 @@ impl LedgerApi {
     fn balance(
         _this: &JsValue,
         args: &[JsValue],
-        _context: &mut Context<'_>,
+        context: &mut Context<'_>,
     ) -> JsResult<JsValue> {
+        gas::charge(context, "Ledger.balance", schedule::LEDGER_BALANCE, 0)?;
+
         let pkh = js_value_to_pkh(args.get_or_undefined(0))?;
+        let asset = js_value_to_asset(args.get_or_undefined(1))?;
 
         let balance = runtime::with_js_hrt_and_tx(|hrt, tx| {
-            Ledger::balance(hrt.deref(), tx, &pkh)
+            Ledger::balance(hrt.deref(), tx, &pkh, asset.as_ref())
         })?;
 
-        Ok(balance.into())
+        Ok(JsBigInt::new(balance).into())
     }
 
     fn transfer(
         this: &JsValue,
         args: &[JsValue],
-        _context: &mut Context<'_>,
+        context: &mut Context<'_>,
     ) -> JsResult<JsValue> {
+        gas::charge(context, "Ledger.transfer", schedule::LEDGER_TRANSFER, 0)?;
+
         let ledger = Ledger::try_from_js(this)?;
         let dst = js_value_to_pkh(args.get_or_undefined(0))?;
-        let amount = args
-            .get_or_undefined(1)
-            .as_number()
-            .ok_or_else(JsNativeError::typ)?;
+        let amount = js_value_to_amount(args.get_or_undefined(1))?;
+        let asset = js_value_to_asset(args.get_or_undefined(2))?;
+
+        runtime::with_js_hrt_and_tx(|hrt, tx| {
+            ledger.transfer(hrt.deref(), tx, &dst, amount, asset.as_ref())
+        })?;
+
+        Ok(JsValue::undefined())
+    }
+
+    fn approve(
+        this: &JsValue,
+        args: &[JsValue],
+        context: &mut Context<'_>,
+    ) -> JsResult<JsValue> {
+        gas::charge(context, "Ledger.approve", schedule::LEDGER_APPROVE, 0)?;
+
+        let ledger = Ledger::try_from_js(this)?;
+        let spender = js_value_to_pkh(args.get_or_undefined(0))?;
+        let amount = js_value_to_amount(args.get_or_undefined(1))?;
+        let asset = js_value_to_asset(args.get_or_undefined(2))?;
+
+        runtime::with_js_hrt_and_tx(|hrt, tx| {
+            ledger.approve(hrt.deref(), tx, &spender, amount, asset.as_ref())
+        })?;
+
+        Ok(JsValue::undefined())
+    }
+
+    fn allowance(
+        _this: &JsValue,
+        args: &[JsValue],
+        context: &mut Context<'_>,
+    ) -> JsResult<JsValue> {
+        gas::charge(context, "Ledger.allowance", schedule::LEDGER_ALLOWANCE, 0)?;
+
+        let owner = js_value_to_pkh(args.get_or_undefined(0))?;
+        let spender = js_value_to_pkh(args.get_or_undefined(1))?;
+        let asset = js_value_to_asset(args.get_or_undefined(2))?;
+
+        let allowance = runtime::with_js_hrt_and_tx(|hrt, tx| {
+            Allowance::get(hrt.deref(), tx, &owner, &spender, asset.as_ref())
+        })?;
+
+        Ok(JsBigInt::new(allowance).into())
+    }
+
+    fn transfer_from(
+        this: &JsValue,
+        args: &[JsValue],
+        context: &mut Context<'_>,
+    ) -> JsResult<JsValue> {
+        gas::charge(
+            context,
+            "Ledger.transferFrom",
+            schedule::LEDGER_TRANSFER_FROM,
+            0,
+        )?;
+
+        let ledger = Ledger::try_from_js(this)?;
+        let owner = js_value_to_pkh(args.get_or_undefined(0))?;
+        let dst = js_value_to_pkh(args.get_or_undefined(1))?;
+        let amount = js_value_to_amount(args.get_or_undefined(2))?;
+        let asset = js_value_to_asset(args.get_or_undefined(3))?;
 
         runtime::with_js_hrt_and_tx(|hrt, tx| {
-            ledger.transfer(hrt.deref(), tx, &dst, amount as Amount)
+            ledger.transfer_from(hrt.deref(), tx, &owner, &dst, amount, asset.as_ref())
+        })?;
+
+        Ok(JsValue::undefined())
+    }
+
+    fn withdraw(
+        this: &JsValue,
+        args: &[JsValue],
+        context: &mut Context<'_>,
+    ) -> JsResult<JsValue> {
+        gas::charge(context, "Ledger.withdraw", schedule::LEDGER_WITHDRAW, 0)?;
+
+        let ledger = Ledger::try_from_js(this)?;
+        let receiver = js_value_to_pkh(args.get_or_undefined(0))?;
+        let amount = js_value_to_amount(args.get_or_undefined(1))?;
+
+        runtime::with_js_hrt_and_tx(|hrt, tx| {
+            ledger.withdraw(hrt.deref(), tx, receiver, amount)
         })?;
 
         Ok(JsValue::undefined())
@@ -154,13 +348,33 @@ impl jstz_core::Api for LedgerApi {
         .function(
             NativeFunction::from_fn_ptr(Self::balance),
             js_string!("balance"),
-            1,
+            2,
         )
         .function(
             NativeFunction::from_fn_ptr(Self::transfer),
             js_string!("transfer"),
             3,
         )
+        .function(
+            NativeFunction::from_fn_ptr(Self::withdraw),
+            js_string!("withdraw"),
+            2,
+        )
+        .function(
+            NativeFunction::from_fn_ptr(Self::approve),
+            js_string!("approve"),
+            3,
+        )
+        .function(
+            NativeFunction::from_fn_ptr(Self::allowance),
+            js_string!("allowance"),
+            3,
+        )
+        .function(
+            NativeFunction::from_fn_ptr(Self::transfer_from),
+            js_string!("transferFrom"),
+            4,
+        )
         .build();
 
         context
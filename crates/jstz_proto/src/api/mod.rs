@@ -1,5 +1,7 @@
+mod jstz;
 mod ledger;
 mod smart_function;
 
+pub use jstz::JstzApi;
 pub use ledger::LedgerApi;
 pub use smart_function::{SmartFunctionApi, TraceData};
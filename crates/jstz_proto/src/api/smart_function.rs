@@ -8,12 +8,18 @@ use boa_engine::{
 };
 use jstz_api::http::request::Request;
 use jstz_core::{
-    host::HostRuntime, host_defined, kv::Transaction, native::JsNativeObject, runtime,
+    gas::{self, schedule},
+    host::HostRuntime,
+    host_defined, journal,
+    kv::Transaction,
+    native::JsNativeObject,
+    runtime,
     value::IntoJs,
 };
 
 use crate::{
-    context::account::{Account, Address, Amount, ParsedCode},
+    api::ledger::js_value_to_amount,
+    context::account::{Account, Address, Amount, Modules, ParsedCode},
     executor::smart_function::{headers, Script},
     operation::OperationHash,
     Error, Result,
@@ -61,6 +67,8 @@ impl SmartFunction {
         tx: &mut Transaction,
         function_code: ParsedCode,
         initial_balance: Amount,
+        admin: Option<Address>,
+        salt: Option<String>,
     ) -> Result<String> {
         // 1. Check if the associated account has sufficient balance
         {
@@ -72,8 +80,16 @@ impl SmartFunction {
         } // The mutable borrow of `tx` in `balance` is released here
 
         // 2. Deploy the smart function
-        let address =
-            Script::deploy(hrt, tx, &self.address, function_code, initial_balance)?; // The mutable borrow of `tx` in `Script::deploy` is released here
+        let address = Script::deploy(
+            hrt,
+            tx,
+            &self.address,
+            function_code,
+            Modules::default(),
+            initial_balance,
+            admin,
+            salt.as_deref(),
+        )?; // The mutable borrow of `tx` in `Script::deploy` is released here
 
         // 3. Increment nonce of current account
         {
@@ -87,6 +103,16 @@ impl SmartFunction {
         Ok(address.to_string())
     }
 
+    fn upgrade(
+        &self,
+        hrt: &impl HostRuntime,
+        tx: &mut Transaction,
+        address: &Address,
+        function_code: ParsedCode,
+    ) -> Result<usize> {
+        Account::upgrade_function_code(hrt, tx, address, &self.address, function_code)
+    }
+
     fn call(
         self_address: &Address,
         request: &JsNativeObject<Request>,
@@ -107,6 +133,7 @@ impl SmartFunction {
         headers::test_and_set_referrer(&request.deref(), self_address)?;
 
         // 3. Load, init and run!
+        journal::record_call(&address);
         Script::load_init_run(address, operation_hash, request.inner(), context)
     }
 }
@@ -123,14 +150,22 @@ impl SmartFunctionApi {
         args: &[JsValue],
         context: &mut Context<'_>,
     ) -> JsResult<JsValue> {
+        let request: JsNativeObject<Request> =
+            args.get_or_undefined(0).clone().try_into()?;
+
+        let body_len = request.deref().body().size();
+        gas::charge(
+            context,
+            "SmartFunction.call",
+            schedule::SMART_FUNCTION_CALL,
+            body_len,
+        )?;
+
         host_defined!(context, host_defined);
         let trace_data = host_defined
             .get::<TraceData>()
             .expect("trace data undefined");
 
-        let request: JsNativeObject<Request> =
-            args.get_or_undefined(0).clone().try_into()?;
-
         SmartFunction::call(
             address,
             &request,
@@ -162,15 +197,36 @@ impl SmartFunctionApi {
                     .with_message("Expected at least 1 argument but 0 provided")
             })?
             .try_js_into(context)?;
+        gas::charge(
+            context,
+            "SmartFunction.create",
+            schedule::SMART_FUNCTION_CREATE,
+            function_code.len(),
+        )?;
         let parsed_code: ParsedCode = function_code.try_into()?;
 
         let initial_balance = match args.get(1) {
             None => 0,
-            Some(balance) => balance
-                .to_big_uint64(context)?
-                .iter_u64_digits()
-                .next()
-                .unwrap_or_default(),
+            Some(balance) if balance.is_undefined() => 0,
+            Some(balance) => js_value_to_amount(balance)?,
+        };
+
+        let admin = match args.get(2) {
+            None => None,
+            Some(admin) if admin.is_null_or_undefined() => None,
+            Some(admin) => Some(parse_address(admin, context)?),
+        };
+
+        let salt = match args.get(3) {
+            None => None,
+            Some(salt) if salt.is_null_or_undefined() => None,
+            Some(salt) => Some(
+                salt.as_string()
+                    .ok_or_else(|| {
+                        JsNativeError::typ().with_message("Salt must be a string")
+                    })?
+                    .to_std_string_escaped(),
+            ),
         };
 
         let promise = JsPromise::new(
@@ -180,7 +236,9 @@ impl SmartFunctionApi {
                         hrt.deref(),
                         tx,
                         parsed_code,
-                        initial_balance as Amount,
+                        initial_balance,
+                        admin,
+                        salt,
                     )
                 })?;
 
@@ -196,6 +254,63 @@ impl SmartFunctionApi {
 
         Ok(promise.into())
     }
+
+    fn upgrade(
+        this: &JsValue,
+        args: &[JsValue],
+        context: &mut Context<'_>,
+    ) -> JsResult<JsValue> {
+        let smart_function = SmartFunction::from_js_value(this)?;
+
+        let function_code: String = args
+            .get(0)
+            .ok_or_else(|| {
+                JsNativeError::typ()
+                    .with_message("Expected at least 1 argument but 0 provided")
+            })?
+            .try_js_into(context)?;
+        gas::charge(
+            context,
+            "SmartFunction.upgrade",
+            schedule::SMART_FUNCTION_UPGRADE,
+            function_code.len(),
+        )?;
+        let parsed_code: ParsedCode = function_code.try_into()?;
+
+        // Smart functions upgrade themselves unless another address is given
+        let address = match args.get(1) {
+            None => smart_function.address.clone(),
+            Some(address) if address.is_null_or_undefined() => {
+                smart_function.address.clone()
+            }
+            Some(address) => parse_address(address, context)?,
+        };
+
+        let version = runtime::with_js_hrt_and_tx(|hrt, tx| {
+            smart_function.upgrade(hrt.deref(), tx, &address, parsed_code)
+        })?;
+
+        Ok(JsValue::from(version as f64))
+    }
+
+    fn is_smart_function(
+        _this: &JsValue,
+        args: &[JsValue],
+        context: &mut Context<'_>,
+    ) -> JsResult<JsValue> {
+        let address = parse_address(args.get_or_undefined(0), context)?;
+
+        Ok(address.is_smart_function().into())
+    }
+}
+
+fn parse_address(value: &JsValue, context: &mut Context<'_>) -> JsResult<Address> {
+    let address: String = value.try_js_into(context)?;
+    Address::from_base58(&address).map_err(|_| {
+        JsNativeError::typ()
+            .with_message(format!("Invalid address: {address}"))
+            .into()
+    })
 }
 
 impl jstz_core::Api for SmartFunctionApi {
@@ -216,6 +331,16 @@ impl jstz_core::Api for SmartFunctionApi {
             js_string!("create"),
             1,
         )
+        .function(
+            NativeFunction::from_fn_ptr(Self::upgrade),
+            js_string!("upgrade"),
+            1,
+        )
+        .function(
+            NativeFunction::from_fn_ptr(Self::is_smart_function),
+            js_string!("isSmartFunction"),
+            1,
+        )
         .build();
 
         context
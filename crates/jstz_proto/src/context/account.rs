@@ -1,12 +1,21 @@
 use std::{
+    collections::BTreeMap,
     fmt::{self, Display, Formatter},
     result,
 };
 
-use crate::error::{Error, Result};
+use crate::{
+    context::{
+        asset::{Asset, TicketContent},
+        ticketer::Ticketer,
+    },
+    error::{Error, Result},
+    receipt,
+};
 use boa_engine::{Context, JsError, JsResult, Module, Source};
 use jstz_core::{
     host::HostRuntime,
+    journal,
     kv::{Entry, Transaction},
 };
 use jstz_crypto::public_key_hash::PublicKeyHash;
@@ -29,6 +38,10 @@ impl Nonce {
     pub fn increment(&mut self) {
         self.0 += 1
     }
+
+    pub fn value(&self) -> u64 {
+        self.0
+    }
 }
 
 impl Display for Nonce {
@@ -67,7 +80,125 @@ pub struct Account {
     pub function_code: Option<ParsedCode>,
 }
 
+/// Address allowed to upgrade the code of a smart function. Smart functions
+/// without an admin have immutable code.
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
+pub struct Admin(pub Address);
+
+/// Every version of a smart function's code. Versions are numbered from 0 and
+/// each is stored under its own path.
+pub struct CodeHistory;
+
+/// The modules the code of a smart function can import, by path (e.g.
+/// `lib/math.js`, imported from the code as `./lib/math.js`)
+#[derive(Default, PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
+pub struct Modules(pub BTreeMap<String, ParsedCode>);
+
 const ACCOUNTS_PATH: RefPath = RefPath::assert_from(b"/jstz_account");
+const ADMINS_PATH: RefPath = RefPath::assert_from(b"/jstz_admin");
+const CODE_HISTORY_PATH: RefPath = RefPath::assert_from(b"/jstz_code_history");
+const CODE_VERSIONS_PATH: RefPath = RefPath::assert_from(b"/jstz_code_versions");
+const MODULES_PATH: RefPath = RefPath::assert_from(b"/jstz_modules");
+const ASSET_BALANCES_PATH: RefPath = RefPath::assert_from(b"/jstz_asset_balance");
+
+impl Admin {
+    pub fn path(pkh: &Address) -> Result<OwnedPath> {
+        let admin_path = OwnedPath::try_from(format!("/{}", pkh))?;
+
+        Ok(path::concat(&ADMINS_PATH, &admin_path)?)
+    }
+
+    /// Returns the admin of the smart function at `addr`, if any
+    pub fn get(
+        hrt: &impl HostRuntime,
+        tx: &mut Transaction,
+        addr: &Address,
+    ) -> Result<Option<Self>> {
+        let admin = tx.get::<Self>(hrt, Self::path(addr)?)?;
+
+        Ok(admin.cloned())
+    }
+
+    pub fn set(self, tx: &mut Transaction, addr: &Address) -> Result<()> {
+        Ok(tx.insert(Self::path(addr)?, self)?)
+    }
+}
+
+impl CodeHistory {
+    /// Path of version `version` of the code
+    pub fn path(pkh: &Address, version: usize) -> Result<OwnedPath> {
+        let version_path = OwnedPath::try_from(format!("/{}/{}", pkh, version))?;
+
+        Ok(path::concat(&CODE_HISTORY_PATH, &version_path)?)
+    }
+
+    /// Path of the number of versions of the code
+    pub fn len_path(pkh: &Address) -> Result<OwnedPath> {
+        let len_path = OwnedPath::try_from(format!("/{}", pkh))?;
+
+        Ok(path::concat(&CODE_VERSIONS_PATH, &len_path)?)
+    }
+
+    /// Returns the number of versions of the code of the smart function at `addr`
+    pub fn len(
+        hrt: &impl HostRuntime,
+        tx: &mut Transaction,
+        addr: &Address,
+    ) -> Result<usize> {
+        let len = tx.get::<usize>(hrt, Self::len_path(addr)?)?;
+
+        Ok(len.copied().unwrap_or_default())
+    }
+
+    /// Returns version `version` of the code of the smart function at `addr`
+    pub fn get(
+        hrt: &impl HostRuntime,
+        tx: &mut Transaction,
+        addr: &Address,
+        version: usize,
+    ) -> Result<Option<ParsedCode>> {
+        let code = tx.get::<ParsedCode>(hrt, Self::path(addr, version)?)?;
+
+        Ok(code.cloned())
+    }
+
+    /// Records a new version of the code, returning its version number
+    fn push(
+        hrt: &impl HostRuntime,
+        tx: &mut Transaction,
+        addr: &Address,
+        function_code: ParsedCode,
+    ) -> Result<usize> {
+        let version = Self::len(hrt, tx, addr)?;
+        tx.insert(Self::path(addr, version)?, function_code)?;
+        tx.insert(Self::len_path(addr)?, version + 1)?;
+
+        Ok(version)
+    }
+}
+
+impl Modules {
+    pub fn path(pkh: &Address) -> Result<OwnedPath> {
+        let modules_path = OwnedPath::try_from(format!("/{}", pkh))?;
+
+        Ok(path::concat(&MODULES_PATH, &modules_path)?)
+    }
+
+    /// Returns the modules of the smart function at `addr`
+    pub fn get(
+        hrt: &impl HostRuntime,
+        tx: &mut Transaction,
+        addr: &Address,
+    ) -> Result<Self> {
+        let modules = tx.get::<Self>(hrt, Self::path(addr)?)?;
+
+        Ok(modules.cloned().unwrap_or_default())
+    }
+
+    pub fn set(self, tx: &mut Transaction, addr: &Address) -> Result<()> {
+        Ok(tx.insert(Self::path(addr)?, self)?)
+    }
+}
 
 impl Account {
     pub fn path(pkh: &Address) -> Result<OwnedPath> {
@@ -95,6 +226,20 @@ impl Account {
         addr: &Address,
     ) -> Result<()> {
         match tx.entry::<Self>(hrt, Self::path(addr)?)? {
+            // Smart function addresses can be known (and funded) before the
+            // deployment, in which case the code is installed in the account
+            Entry::Occupied(mut entry)
+                if entry.get().function_code.is_none()
+                    && self.function_code.is_some() =>
+            {
+                let account = entry.get_mut();
+                account.amount = account
+                    .amount
+                    .checked_add(self.amount)
+                    .ok_or(Error::BalanceOverflow)?;
+                account.function_code = self.function_code;
+                Ok(())
+            }
             Entry::Occupied(ntry) => {
                 let acc: &Self = ntry.get();
                 hrt.write_debug(&format!("📜 already exists: {:?}\n", acc.function_code));
@@ -138,6 +283,29 @@ impl Account {
         Ok(())
     }
 
+    /// Replaces the code of the smart function at `addr`, keeping its balance
+    /// and KV storage. Only the account's admin may upgrade the code.
+    /// Returns the version number of the new code.
+    pub fn upgrade_function_code(
+        hrt: &impl HostRuntime,
+        tx: &mut Transaction,
+        addr: &Address,
+        caller: &Address,
+        function_code: ParsedCode,
+    ) -> Result<usize> {
+        if Self::get_mut(hrt, tx, addr)?.function_code.is_none() {
+            return Err(Error::InvalidAddress);
+        }
+        match Admin::get(hrt, tx, addr)? {
+            Some(Admin(admin)) if &admin == caller => (),
+            _ => return Err(Error::UnauthorizedUpgrade),
+        }
+
+        Self::get_mut(hrt, tx, addr)?.function_code = Some(function_code.clone());
+
+        CodeHistory::push(hrt, tx, addr, function_code)
+    }
+
     pub fn balance(
         hrt: &impl HostRuntime,
         tx: &mut Transaction,
@@ -160,6 +328,22 @@ impl Account {
         Ok(())
     }
 
+    /// Burns `amount` from the account's balance
+    pub fn withdraw(
+        hrt: &impl HostRuntime,
+        tx: &mut Transaction,
+        addr: &Address,
+        amount: Amount,
+    ) -> Result<()> {
+        let account = Self::get_mut(hrt, tx, addr)?;
+
+        match account.amount.checked_sub(amount) {
+            Some(amount) => account.amount = amount,
+            None => return Err(Error::InsufficientFunds),
+        }
+        Ok(())
+    }
+
     pub fn set_balance(
         hrt: &impl HostRuntime,
         tx: &mut Transaction,
@@ -178,13 +362,23 @@ impl Account {
         addr: &Address,
         amount: Amount,
         function_code: Option<ParsedCode>,
+        admin: Option<Address>,
     ) -> Result<()> {
         Self {
             nonce: Nonce::default(),
             amount,
-            function_code,
+            function_code: function_code.clone(),
         }
-        .try_insert(hrt, tx, addr)
+        .try_insert(hrt, tx, addr)?;
+
+        if let Some(admin) = admin {
+            Admin(admin).set(tx, addr)?;
+        }
+
+        if let Some(function_code) = function_code {
+            CodeHistory::push(hrt, tx, addr, function_code)?;
+        }
+        Ok(())
     }
 
     pub fn transfer(
@@ -212,6 +406,96 @@ impl Account {
             }
         }
 
+        journal::record(receipt::Transfer {
+            from: src.clone(),
+            to: dst.clone(),
+            amount: amt,
+            asset: None,
+        });
+
+        Ok(())
+    }
+
+    fn asset_balance_path(addr: &Address, asset: &Asset) -> Result<OwnedPath> {
+        let balance_path =
+            OwnedPath::try_from(format!("/{}/{}", addr, asset.id().to_string()))?;
+
+        Ok(path::concat(&ASSET_BALANCES_PATH, &balance_path)?)
+    }
+
+    fn asset_balance_mut<'a>(
+        hrt: &impl HostRuntime,
+        tx: &'a mut Transaction,
+        addr: &Address,
+        asset: &Asset,
+    ) -> Result<&'a mut Amount> {
+        if Self::is_native(hrt, asset)? {
+            return Ok(&mut Self::get_mut(hrt, tx, addr)?.amount);
+        }
+
+        Ok(tx
+            .entry::<Amount>(hrt, Self::asset_balance_path(addr, asset)?)?
+            .or_insert_default())
+    }
+
+    /// The native asset (the unit tickets of the [`Ticketer`]) is held in the
+    /// account's `amount`
+    pub(crate) fn is_native(hrt: &impl HostRuntime, asset: &Asset) -> Result<bool> {
+        Ok(asset.content == TicketContent::Unit
+            && Ticketer::get(hrt)?.as_ref() == Some(&asset.ticketer))
+    }
+
+    pub fn asset_balance(
+        hrt: &impl HostRuntime,
+        tx: &mut Transaction,
+        addr: &Address,
+        asset: &Asset,
+    ) -> Result<Amount> {
+        Ok(*Self::asset_balance_mut(hrt, tx, addr, asset)?)
+    }
+
+    pub fn deposit_asset(
+        hrt: &impl HostRuntime,
+        tx: &mut Transaction,
+        addr: &Address,
+        asset: &Asset,
+        amount: Amount,
+    ) -> Result<()> {
+        let balance = Self::asset_balance_mut(hrt, tx, addr, asset)?;
+
+        *balance = balance.checked_add(amount).ok_or(Error::BalanceOverflow)?;
+        Ok(())
+    }
+
+    pub fn transfer_asset(
+        hrt: &impl HostRuntime,
+        tx: &mut Transaction,
+        src: &Address,
+        dst: &Address,
+        asset: &Asset,
+        amt: Amount,
+    ) -> Result<()> {
+        if Self::is_native(hrt, asset)? {
+            return Self::transfer(hrt, tx, src, dst, amt);
+        }
+
+        {
+            let src = Self::asset_balance_mut(hrt, tx, src, asset)?;
+            *src = src.checked_sub(amt).ok_or(Error::InsufficientFunds)?;
+        }
+
+        {
+            let dst = Self::asset_balance_mut(hrt, tx, dst, asset)?;
+            *dst = dst.checked_add(amt).ok_or(Error::BalanceOverflow)?;
+        }
+
+        journal::record(receipt::Transfer {
+            from: src.clone(),
+            to: dst.clone(),
+            amount: amt,
+            asset: Some(asset.clone()),
+        });
+
         Ok(())
     }
 }
@@ -246,4 +530,122 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn asset_balances_are_separate() -> Result<()> {
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+        tx.begin();
+
+        let src = PublicKeyHash::from_base58("tz1XQjK1b3P72kMcHsoPhnAg3dvX1n8Ainty")
+            .expect("Could not parse pkh");
+        let dst = PublicKeyHash::from_base58("tz1faswCTDciRzE4oJ9jn2Vm2dvjeyA9fUzU")
+            .expect("Could not parse pkh");
+        let asset: Asset = "KT1RJ6PbjHpwc3M5rw5s2Nbmefwbuwbdxton:1".parse()?;
+
+        Account::deposit_asset(hrt, tx, &src, &asset, 10)?;
+        Account::transfer_asset(hrt, tx, &src, &dst, &asset, 4)?;
+
+        assert_eq!(Account::asset_balance(hrt, tx, &src, &asset)?, 6);
+        assert_eq!(Account::asset_balance(hrt, tx, &dst, &asset)?, 4);
+        assert_eq!(Account::balance(hrt, tx, &src)?, 0);
+        assert!(matches!(
+            Account::transfer_asset(hrt, tx, &dst, &src, &asset, 5),
+            Err(Error::InsufficientFunds)
+        ));
+
+        Ok(())
+    }
+
+    fn code(code: &str) -> ParsedCode {
+        ParsedCode::try_from(code.to_string()).expect("Could not parse code")
+    }
+
+    #[test]
+    fn admin_upgrades_code_and_records_versions() -> Result<()> {
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+        tx.begin();
+
+        let addr = PublicKeyHash::digest(b"upgradable")?;
+        let admin = PublicKeyHash::from_base58("tz1XQjK1b3P72kMcHsoPhnAg3dvX1n8Ainty")
+            .expect("Could not parse pkh");
+
+        Account::create(hrt, tx, &addr, 0, Some(code("// v0")), Some(admin.clone()))?;
+        let version =
+            Account::upgrade_function_code(hrt, tx, &addr, &admin, code("// v1"))?;
+
+        assert_eq!(version, 1);
+        assert_eq!(Admin::get(hrt, tx, &addr)?, Some(Admin(admin)));
+        assert_eq!(
+            Account::function_code(hrt, tx, &addr)?.cloned(),
+            Some("// v1".to_string())
+        );
+        assert_eq!(CodeHistory::len(hrt, tx, &addr)?, 2);
+        assert_eq!(CodeHistory::get(hrt, tx, &addr, 0)?, Some(code("// v0")));
+        assert_eq!(CodeHistory::get(hrt, tx, &addr, 1)?, Some(code("// v1")));
+        assert_eq!(CodeHistory::get(hrt, tx, &addr, 2)?, None);
+
+        // The node reads versions from these paths
+        assert_eq!(
+            CodeHistory::path(&addr, 1)?,
+            OwnedPath::try_from(format!("/jstz_code_history/{addr}/1"))?
+        );
+        assert_eq!(
+            CodeHistory::len_path(&addr)?,
+            OwnedPath::try_from(format!("/jstz_code_versions/{addr}"))?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn non_admin_cannot_upgrade_code() -> Result<()> {
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+        tx.begin();
+
+        let addr = PublicKeyHash::digest(b"upgradable")?;
+        let admin = PublicKeyHash::from_base58("tz1XQjK1b3P72kMcHsoPhnAg3dvX1n8Ainty")
+            .expect("Could not parse pkh");
+        let other = PublicKeyHash::from_base58("tz1faswCTDciRzE4oJ9jn2Vm2dvjeyA9fUzU")
+            .expect("Could not parse pkh");
+
+        Account::create(hrt, tx, &addr, 0, Some(code("// v0")), Some(admin))?;
+
+        assert!(matches!(
+            Account::upgrade_function_code(hrt, tx, &addr, &other, code("// v1")),
+            Err(Error::UnauthorizedUpgrade)
+        ));
+        assert_eq!(
+            Account::function_code(hrt, tx, &addr)?.cloned(),
+            Some("// v0".to_string())
+        );
+        assert_eq!(CodeHistory::len(hrt, tx, &addr)?, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn code_without_admin_is_immutable() -> Result<()> {
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+        tx.begin();
+
+        let addr = PublicKeyHash::digest(b"immutable")?;
+        let deployer = PublicKeyHash::from_base58("tz1XQjK1b3P72kMcHsoPhnAg3dvX1n8Ainty")
+            .expect("Could not parse pkh");
+
+        Account::create(hrt, tx, &addr, 0, Some(code("// v0")), None)?;
+
+        assert_eq!(Admin::get(hrt, tx, &addr)?, None);
+        for caller in [&deployer, &addr] {
+            assert!(matches!(
+                Account::upgrade_function_code(hrt, tx, &addr, caller, code("// v1")),
+                Err(Error::UnauthorizedUpgrade)
+            ));
+        }
+
+        Ok(())
+    }
 }
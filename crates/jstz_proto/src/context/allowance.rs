@@ -0,0 +1,161 @@
+use jstz_core::{host::HostRuntime, journal, kv::Transaction};
+use tezos_smart_rollup::storage::path::{self, OwnedPath, RefPath};
+
+use crate::{
+    context::{
+        account::{Account, Address, Amount},
+        asset::Asset,
+    },
+    error::{Error, Result},
+    receipt,
+};
+
+const ALLOWANCES_PATH: RefPath = RefPath::assert_from(b"/jstz_allowance");
+
+/// Path segment of the allowances of the native asset
+const NATIVE: &str = "native";
+
+/// Amounts that a spender may transfer from the balance of an owner, set by
+/// the owner with `Ledger.approve`
+pub struct Allowance;
+
+impl Allowance {
+    /// The native asset is `None`, whether or not it is given explicitly
+    pub(crate) fn canonical_asset<'a>(
+        hrt: &impl HostRuntime,
+        asset: Option<&'a Asset>,
+    ) -> Result<Option<&'a Asset>> {
+        match asset {
+            Some(asset) if Account::is_native(hrt, asset)? => Ok(None),
+            asset => Ok(asset),
+        }
+    }
+
+    fn path(
+        owner: &Address,
+        spender: &Address,
+        asset: Option<&Asset>,
+    ) -> Result<OwnedPath> {
+        let asset = match asset {
+            Some(asset) => asset.id().to_string(),
+            None => NATIVE.to_string(),
+        };
+        let allowance_path =
+            OwnedPath::try_from(format!("/{}/{}/{}", owner, spender, asset))?;
+
+        Ok(path::concat(&ALLOWANCES_PATH, &allowance_path)?)
+    }
+
+    /// Returns the amount of `asset` that `spender` may transfer from the
+    /// balance of `owner`
+    pub fn get(
+        hrt: &impl HostRuntime,
+        tx: &mut Transaction,
+        owner: &Address,
+        spender: &Address,
+        asset: Option<&Asset>,
+    ) -> Result<Amount> {
+        let asset = Self::canonical_asset(hrt, asset)?;
+        let allowance = tx.get::<Amount>(hrt, Self::path(owner, spender, asset)?)?;
+
+        Ok(allowance.copied().unwrap_or_default())
+    }
+
+    fn set(
+        hrt: &impl HostRuntime,
+        tx: &mut Transaction,
+        owner: &Address,
+        spender: &Address,
+        asset: Option<&Asset>,
+        amount: Amount,
+    ) -> Result<()> {
+        *tx.entry::<Amount>(hrt, Self::path(owner, spender, asset)?)?
+            .or_insert_default() = amount;
+
+        journal::record(receipt::Approval {
+            owner: owner.clone(),
+            spender: spender.clone(),
+            amount,
+            asset: asset.cloned(),
+        });
+
+        Ok(())
+    }
+
+    /// Allows `spender` to transfer up to `amount` of `asset` from the balance
+    /// of `owner`, replacing any previous allowance
+    pub fn approve(
+        hrt: &impl HostRuntime,
+        tx: &mut Transaction,
+        owner: &Address,
+        spender: &Address,
+        asset: Option<&Asset>,
+        amount: Amount,
+    ) -> Result<()> {
+        let asset = Self::canonical_asset(hrt, asset)?;
+
+        Self::set(hrt, tx, owner, spender, asset, amount)
+    }
+
+    /// Transfers `amount` of `asset` from `owner` to `dst` on behalf of
+    /// `spender`, decreasing the allowance of `spender` accordingly
+    pub fn transfer_from(
+        hrt: &impl HostRuntime,
+        tx: &mut Transaction,
+        spender: &Address,
+        owner: &Address,
+        dst: &Address,
+        asset: Option<&Asset>,
+        amount: Amount,
+    ) -> Result<()> {
+        let asset = Self::canonical_asset(hrt, asset)?;
+
+        let allowance = Self::get(hrt, tx, owner, spender, asset)?
+            .checked_sub(amount)
+            .ok_or(Error::InsufficientAllowance)?;
+
+        match asset {
+            Some(asset) => Account::transfer_asset(hrt, tx, owner, dst, asset, amount)?,
+            None => Account::transfer(hrt, tx, owner, dst, amount)?,
+        }
+
+        Self::set(hrt, tx, owner, spender, asset, allowance)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tezos_smart_rollup_mock::MockHost;
+
+    fn address(pkh: &str) -> Address {
+        Address::from_base58(pkh).expect("Could not parse pkh")
+    }
+
+    #[test]
+    fn transfer_from_spends_allowance() -> Result<()> {
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+        tx.begin();
+
+        let owner = address("tz1XQjK1b3P72kMcHsoPhnAg3dvX1n8Ainty");
+        let spender = address("tz1faswCTDciRzE4oJ9jn2Vm2dvjeyA9fUzU");
+
+        Account::deposit(hrt, tx, &owner, 100)?;
+        Allowance::approve(hrt, tx, &owner, &spender, None, 30)?;
+
+        Allowance::transfer_from(hrt, tx, &spender, &owner, &spender, None, 20)?;
+        assert_eq!(Allowance::get(hrt, tx, &owner, &spender, None)?, 10);
+        assert_eq!(Account::balance(hrt, tx, &owner)?, 80);
+        assert_eq!(Account::balance(hrt, tx, &spender)?, 20);
+
+        assert!(matches!(
+            Allowance::transfer_from(hrt, tx, &spender, &owner, &spender, None, 11),
+            Err(Error::InsufficientAllowance)
+        ));
+        // The allowance is directional
+        assert_eq!(Allowance::get(hrt, tx, &spender, &owner, None)?, 0);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,123 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+use jstz_crypto::hash::Blake2b;
+use serde::{Deserialize, Serialize};
+use tezos_crypto_rs::hash::ContractKt1Hash;
+
+use crate::{Error, Result};
+
+/// The content of the tickets backing an asset
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TicketContent {
+    /// `unit` tickets, e.g. the tickets of the native jstz asset or tez tickets
+    Unit,
+    /// FA2.1 `pair nat (option bytes)` tickets, e.g. the tickets of FA1.2 and
+    /// FA2 tokens
+    Token { id: u64, metadata: Option<Vec<u8>> },
+}
+
+/// An asset bridged from L1, identified by the ticketer minting its tickets
+/// and the content of these tickets.
+///
+/// Assets are represented as strings of the form `<ticketer>` (unit tickets),
+/// `<ticketer>:<token id>` or `<ticketer>:<token id>:<hex metadata>`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Asset {
+    pub ticketer: ContractKt1Hash,
+    pub content: TicketContent,
+}
+
+impl Asset {
+    /// The unit tickets minted by `ticketer`
+    pub fn unit(ticketer: ContractKt1Hash) -> Self {
+        Self {
+            ticketer,
+            content: TicketContent::Unit,
+        }
+    }
+
+    /// A hash uniquely identifying the asset
+    pub fn id(&self) -> Blake2b {
+        Blake2b::from(self.to_string().as_bytes())
+    }
+}
+
+impl Display for Asset {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.ticketer.to_base58_check())?;
+        match &self.content {
+            TicketContent::Unit => Ok(()),
+            TicketContent::Token { id, metadata: None } => write!(f, ":{}", id),
+            TicketContent::Token {
+                id,
+                metadata: Some(metadata),
+            } => write!(f, ":{}:{}", id, hex::encode(metadata)),
+        }
+    }
+}
+
+impl FromStr for Asset {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts = s.split(':');
+
+        let ticketer = parts
+            .next()
+            .and_then(|ticketer| ContractKt1Hash::from_base58_check(ticketer).ok())
+            .ok_or(Error::InvalidAsset)?;
+
+        let content = match parts.next() {
+            None => TicketContent::Unit,
+            Some(id) => TicketContent::Token {
+                id: id.parse().map_err(|_| Error::InvalidAsset)?,
+                metadata: parts
+                    .next()
+                    .map(|metadata| {
+                        hex::decode(metadata).map_err(|_| Error::InvalidAsset)
+                    })
+                    .transpose()?,
+            },
+        };
+
+        if parts.next().is_some() {
+            return Err(Error::InvalidAsset);
+        }
+
+        Ok(Self { ticketer, content })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TICKETER: &str = "KT1RJ6PbjHpwc3M5rw5s2Nbmefwbuwbdxton";
+
+    #[test]
+    fn asset_string_roundtrip() {
+        for asset in [
+            TICKETER.to_string(),
+            format!("{}:42", TICKETER),
+            format!("{}:0:cafe", TICKETER),
+        ] {
+            let parsed = Asset::from_str(&asset).expect("Could not parse asset");
+            assert_eq!(parsed.to_string(), asset);
+        }
+    }
+
+    #[test]
+    fn invalid_assets_are_rejected() {
+        for asset in [
+            "tz1XQjK1b3P72kMcHsoPhnAg3dvX1n8Ainty".to_string(),
+            format!("{}:-1", TICKETER),
+            format!("{}:0:xyz", TICKETER),
+            format!("{}:0:cafe:0", TICKETER),
+        ] {
+            assert!(Asset::from_str(&asset).is_err());
+        }
+    }
+}
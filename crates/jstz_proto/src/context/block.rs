@@ -0,0 +1,27 @@
+use jstz_core::{host::HostRuntime, kv::Storage};
+use serde::{Deserialize, Serialize};
+use tezos_smart_rollup::storage::path::RefPath;
+
+use crate::Result;
+
+const BLOCK_PATH: RefPath = RefPath::assert_from(b"/jstz_block");
+
+/// The L1 block being processed by the rollup. Updated by the kernel at the
+/// beginning of each level.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockInfo {
+    pub level: u32,
+    /// Unix timestamp (in seconds) of the predecessor block
+    pub timestamp: i64,
+}
+
+impl BlockInfo {
+    /// Returns the current block, if the kernel has seen one
+    pub fn get(rt: &impl HostRuntime) -> Result<Option<Self>> {
+        Ok(Storage::get(rt, &BLOCK_PATH)?)
+    }
+
+    pub fn set(&self, rt: &mut impl HostRuntime) -> Result<()> {
+        Ok(Storage::insert(rt, &BLOCK_PATH, self)?)
+    }
+}
@@ -1,2 +1,8 @@
 pub mod account;
+pub mod allowance;
+pub mod asset;
+pub mod block;
+pub mod outbox;
 pub mod receipt;
+pub mod scheduler;
+pub mod ticketer;
@@ -0,0 +1,224 @@
+use jstz_core::{
+    host::HostRuntime,
+    kv::{Storage, Transaction},
+};
+use serde::{Deserialize, Serialize};
+use tezos_data_encoding::enc::BinWriter;
+use tezos_smart_rollup::{
+    michelson::{ticket::UnitTicket, MichelsonContract, MichelsonPair, MichelsonUnit},
+    outbox::{OutboxMessage, OutboxMessageTransaction},
+    prelude::debug_msg,
+    storage::path::{OwnedPath, RefPath},
+    types::{Contract, Entrypoint},
+};
+
+use crate::{
+    context::{
+        account::{Address, Amount},
+        ticketer::Ticketer,
+    },
+    Error, Result,
+};
+
+const OUTBOX_PATH: RefPath = RefPath::assert_from(b"/jstz_outbox");
+
+const WITHDRAW_ENTRYPOINT: &str = "withdraw";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Withdrawal {
+    pub receiver: Address,
+    pub amount: Amount,
+}
+
+/// Pending outbox messages.
+///
+/// Withdrawals are queued within the current transaction and are only
+/// written to the rollup outbox (see [`Outbox::flush`]) once the
+/// transaction has been committed. This ensures a rolled back withdrawal
+/// never reaches L1.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Outbox(Vec<Withdrawal>);
+
+impl Outbox {
+    fn path() -> OwnedPath {
+        OwnedPath::from(&OUTBOX_PATH)
+    }
+
+    pub fn push_withdrawal(
+        hrt: &impl HostRuntime,
+        tx: &mut Transaction,
+        withdrawal: Withdrawal,
+    ) -> Result<()> {
+        let outbox = tx.entry::<Self>(hrt, Self::path())?.or_insert_default();
+        outbox.0.push(withdrawal);
+        Ok(())
+    }
+
+    /// Writes the pending (committed) withdrawals to the rollup outbox as
+    /// ticket transfers to the ticketer's `%withdraw` entrypoint. Withdrawals
+    /// that do not fit in the outbox of the current level stay queued, and are
+    /// written by a later flush.
+    pub fn flush(rt: &mut impl HostRuntime) -> Result<()> {
+        let mut outbox = match Storage::get::<Self>(rt, &OUTBOX_PATH)? {
+            Some(outbox) => outbox,
+            None => return Ok(()),
+        };
+
+        let ticketer = Ticketer::get(rt)?.ok_or(Error::TicketerNotSet)?;
+
+        let mut written = 0;
+        for withdrawal in &outbox.0 {
+            match Self::encode_withdrawal(&ticketer, withdrawal) {
+                Ok(bytes) => {
+                    if let Err(err) = rt.write_output(&bytes) {
+                        debug_msg!(
+                            rt,
+                            "Withdrawals postponed to a later level: {err:?}\n"
+                        );
+                        break;
+                    }
+                    debug_msg!(rt, "Withdrawal: {withdrawal:?}\n");
+                }
+                // Retrying would never succeed and would hold back the
+                // withdrawals queued after it
+                Err(err) => {
+                    debug_msg!(rt, "[🔴] Invalid withdrawal {withdrawal:?}: {err:?}\n")
+                }
+            }
+            written += 1;
+        }
+
+        outbox.0.drain(..written);
+        if outbox.0.is_empty() {
+            Storage::remove(rt, &OUTBOX_PATH)?;
+        } else {
+            Storage::insert(rt, &OUTBOX_PATH, &outbox)?;
+        }
+
+        Ok(())
+    }
+
+    fn encode_withdrawal(
+        ticketer: &tezos_crypto_rs::hash::ContractKt1Hash,
+        withdrawal: &Withdrawal,
+    ) -> Result<Vec<u8>> {
+        let Withdrawal { receiver, amount } = withdrawal;
+
+        let receiver = Contract::from_b58check(&receiver.to_base58())
+            .map_err(|_| Error::InvalidAddress)?;
+        let ticket = UnitTicket::new(
+            Contract::Originated(ticketer.clone()),
+            MichelsonUnit,
+            *amount,
+        )
+        .map_err(|_| Error::InvalidWithdrawal)?;
+
+        let transaction = OutboxMessageTransaction {
+            // receiver, ticket
+            parameters: MichelsonPair(MichelsonContract(receiver), ticket),
+            destination: Contract::Originated(ticketer.clone()),
+            entrypoint: Entrypoint::try_from(WITHDRAW_ENTRYPOINT.to_string())
+                .map_err(|_| Error::InvalidWithdrawal)?,
+        };
+        let message = OutboxMessage::AtomicTransactionBatch(vec![transaction].into());
+
+        let mut bytes = Vec::new();
+        message
+            .bin_write(&mut bytes)
+            .map_err(|_| Error::InvalidWithdrawal)?;
+
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tezos_crypto_rs::hash::ContractKt1Hash;
+    use tezos_smart_rollup_mock::MockHost;
+
+    use super::*;
+    use crate::context::ticketer::TICKETER_PATH;
+
+    fn ticketer() -> ContractKt1Hash {
+        ContractKt1Hash::from_base58_check("KT1RJ6PbjHpwc3M5rw5s2Nbmefwbuwbdxton")
+            .expect("Could not parse ticketer")
+    }
+
+    fn withdrawal() -> Withdrawal {
+        Withdrawal {
+            receiver: Address::from_base58("tz1XQjK1b3P72kMcHsoPhnAg3dvX1n8Ainty")
+                .expect("Could not parse pkh"),
+            amount: 10,
+        }
+    }
+
+    #[test]
+    fn committed_withdrawals_are_flushed_once() -> Result<()> {
+        let hrt = &mut MockHost::default();
+        Storage::insert(hrt, &TICKETER_PATH, &ticketer())?;
+
+        let tx = &mut Transaction::default();
+        tx.begin();
+        Outbox::push_withdrawal(hrt, tx, withdrawal())?;
+        tx.commit(hrt)?;
+
+        Outbox::flush(hrt)?;
+        Outbox::flush(hrt)?;
+
+        assert_eq!(
+            hrt.outbox_at(hrt.level()),
+            vec![Outbox::encode_withdrawal(&ticketer(), &withdrawal())?]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn rolled_back_withdrawals_are_not_flushed() -> Result<()> {
+        let hrt = &mut MockHost::default();
+        Storage::insert(hrt, &TICKETER_PATH, &ticketer())?;
+
+        let tx = &mut Transaction::default();
+        tx.begin();
+        tx.begin();
+        Outbox::push_withdrawal(hrt, tx, withdrawal())?;
+        tx.rollback()?;
+        tx.commit(hrt)?;
+
+        Outbox::flush(hrt)?;
+
+        assert!(hrt.outbox_at(hrt.level()).is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn withdrawals_exceeding_the_outbox_are_flushed_at_later_levels() -> Result<()> {
+        // Maximum number of outbox messages per level
+        const OUTBOX_LIMIT: usize = 100;
+
+        let hrt = &mut MockHost::default();
+        Storage::insert(hrt, &TICKETER_PATH, &ticketer())?;
+
+        let tx = &mut Transaction::default();
+        tx.begin();
+        for _ in 0..OUTBOX_LIMIT + 10 {
+            Outbox::push_withdrawal(hrt, tx, withdrawal())?;
+        }
+        tx.commit(hrt)?;
+
+        Outbox::flush(hrt)?;
+        assert_eq!(hrt.outbox_at(hrt.level()).len(), OUTBOX_LIMIT);
+
+        // The outbox of the level is full, nothing is written or lost
+        Outbox::flush(hrt)?;
+        assert_eq!(hrt.outbox_at(hrt.level()).len(), OUTBOX_LIMIT);
+
+        hrt.run_level(|_: &mut MockHost| {});
+        Outbox::flush(hrt)?;
+        assert_eq!(hrt.outbox_at(hrt.level()).len(), 10);
+        assert!(Storage::get::<Outbox>(hrt, &OUTBOX_PATH)?.is_none());
+
+        Ok(())
+    }
+}
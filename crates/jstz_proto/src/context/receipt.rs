@@ -1,12 +1,41 @@
 use jstz_core::{host::HostRuntime, kv::Transaction};
 use tezos_smart_rollup::storage::path::{self, OwnedPath, RefPath};
 
-use crate::{receipt::Receipt, Result};
+use crate::{
+    event_logger,
+    receipt::{Content, Event, Receipt},
+    Result,
+};
 
 const RECEIPTS_PATH: RefPath = RefPath::assert_from(b"/jstz_receipt");
 
+fn collect_events<'a>(content: &'a Content, events: &mut Vec<&'a Event>) {
+    match content {
+        Content::RunFunction(run) => events.extend(run.events.iter()),
+        Content::Batch(steps) => {
+            steps.iter().for_each(|step| collect_events(step, events))
+        }
+        _ => (),
+    }
+}
+
 impl Receipt {
-    pub fn write(self, _hrt: &impl HostRuntime, tx: &mut Transaction) -> Result<()> {
+    /// Events emitted by the operation, in emission order
+    pub fn events(&self) -> Vec<&Event> {
+        let mut events = vec![];
+        if let Ok(content) = &self.inner {
+            collect_events(content, &mut events);
+        }
+        events
+    }
+
+    /// Writes the receipt to durable storage. The events of the receipt are
+    /// also logged, such that they can be indexed by the node.
+    pub fn write(self, hrt: &impl HostRuntime, tx: &mut Transaction) -> Result<()> {
+        for event in self.events() {
+            event_logger::log_event(hrt, self.hash().to_string(), event);
+        }
+
         let receipt_path = OwnedPath::try_from(format!("/{}", self.hash().to_string()))?;
 
         Ok(tx.insert(path::concat(&RECEIPTS_PATH, &receipt_path)?, self)?)
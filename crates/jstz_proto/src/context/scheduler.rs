@@ -0,0 +1,386 @@
+use std::collections::BTreeMap;
+
+use jstz_core::{
+    host::HostRuntime,
+    kv::{Storage, Transaction},
+};
+use jstz_crypto::hash::Blake2b;
+use serde::{Deserialize, Serialize};
+use tezos_smart_rollup::storage::path::{self, OwnedPath, RefPath};
+
+use crate::{
+    context::account::Address,
+    operation::{OperationHash, RunFunction},
+    Error, Result,
+};
+
+const SCHEDULER_PATH: RefPath = RefPath::assert_from(b"/jstz_scheduler");
+
+const NEXT_ID_PATH: RefPath = RefPath::assert_from(b"/next_id");
+
+const QUEUE_PATH: RefPath = RefPath::assert_from(b"/queue");
+
+const RESERVED_PATH: RefPath = RefPath::assert_from(b"/reserved");
+
+/// Maximum total gas limit of the calls scheduled for the same level. This
+/// bounds the ticks needed to run the calls of a level.
+pub const MAX_GAS_PER_LEVEL: usize = 1_000_000;
+
+/// Maximum total gas limit of the calls scheduled by the same smart function
+/// for the same level, such that no smart function can take a whole level
+pub const MAX_GAS_PER_SCHEDULER: usize = MAX_GAS_PER_LEVEL / 10;
+
+/// Maximum number of runs of a scheduled call
+pub const MAX_RUNS: u32 = 100;
+
+/// A smart function call scheduled by a smart function for a later level
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduledCall {
+    pub id: u64,
+    /// Address of the smart function that scheduled the call. It is the
+    /// referer of the call and has prepaid its gas.
+    pub scheduler: Address,
+    pub run: RunFunction,
+    /// Level of the next run
+    pub level: u32,
+    /// Number of levels between two runs
+    pub every: u32,
+    /// Number of runs left, including the next one
+    pub remaining: u32,
+}
+
+impl ScheduledCall {
+    /// The hash under which the receipt of the next run is stored
+    pub fn receipt_hash(&self) -> OperationHash {
+        Self::receipt_hash_at(self.id, self.level)
+    }
+
+    /// The hash under which the receipt of the run of call `id` at `level`
+    /// is stored
+    pub fn receipt_hash_at(id: u64, level: u32) -> OperationHash {
+        let mut bytes = b"jstz_scheduler".to_vec();
+        bytes.extend_from_slice(&id.to_be_bytes());
+        bytes.extend_from_slice(&level.to_be_bytes());
+        Blake2b::from(&bytes)
+    }
+
+    /// The levels of the next run and of all following runs
+    pub fn levels(&self) -> impl Iterator<Item = u32> + '_ {
+        (0..self.remaining)
+            .map_while(|n| self.level.checked_add(n.checked_mul(self.every)?))
+    }
+
+    /// Returns the following run of the call, if any
+    pub fn next(self) -> Option<Self> {
+        if self.remaining <= 1 {
+            return None;
+        }
+
+        Some(Self {
+            level: self.level.checked_add(self.every)?,
+            remaining: self.remaining - 1,
+            ..self
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ScheduledCalls(Vec<ScheduledCall>);
+
+/// Gas reserved for the calls of a level, by scheduler
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Reservations(BTreeMap<Address, usize>);
+
+impl Reservations {
+    fn total(&self) -> usize {
+        self.0.values().sum()
+    }
+}
+
+/// Durable queue of scheduled calls, indexed by level
+pub struct Scheduler;
+
+impl Scheduler {
+    fn next_id_path() -> Result<OwnedPath> {
+        Ok(path::concat(&SCHEDULER_PATH, &NEXT_ID_PATH)?)
+    }
+
+    fn queue_path(level: u32) -> Result<OwnedPath> {
+        let level_path = OwnedPath::try_from(format!("/{}", level))?;
+
+        Ok(path::concat(
+            &path::concat(&SCHEDULER_PATH, &QUEUE_PATH)?,
+            &level_path,
+        )?)
+    }
+
+    /// Allocates a new id for a scheduled call
+    pub fn next_id(hrt: &impl HostRuntime, tx: &mut Transaction) -> Result<u64> {
+        let next_id = tx
+            .entry::<u64>(hrt, Self::next_id_path()?)?
+            .or_insert_default();
+        let id = *next_id;
+        *next_id += 1;
+        Ok(id)
+    }
+
+    fn reserved_path(level: u32) -> Result<OwnedPath> {
+        let level_path = OwnedPath::try_from(format!("/{}", level))?;
+
+        Ok(path::concat(
+            &path::concat(&SCHEDULER_PATH, &RESERVED_PATH)?,
+            &level_path,
+        )?)
+    }
+
+    /// Enqueues `call` for its next run and reserves the gas of all its runs.
+    /// Fails if the total gas limit of the calls scheduled for any of these
+    /// levels would exceed [`MAX_GAS_PER_SCHEDULER`] for the call's scheduler
+    /// or [`MAX_GAS_PER_LEVEL`], such that recurring calls are always
+    /// rescheduled.
+    pub fn schedule(
+        hrt: &impl HostRuntime,
+        tx: &mut Transaction,
+        call: ScheduledCall,
+    ) -> Result<()> {
+        let gas_limit = call.run.gas_limit;
+        let levels: Vec<u32> = call.levels().collect();
+
+        if gas_limit > MAX_GAS_PER_SCHEDULER {
+            return Err(Error::SchedulerQuotaExceeded);
+        }
+        for level in &levels {
+            let Some(reservations) =
+                tx.get::<Reservations>(hrt, Self::reserved_path(*level)?)?
+            else {
+                continue;
+            };
+            let reserved = reservations
+                .0
+                .get(&call.scheduler)
+                .copied()
+                .unwrap_or_default();
+            if reserved.saturating_add(gas_limit) > MAX_GAS_PER_SCHEDULER {
+                return Err(Error::SchedulerQuotaExceeded);
+            }
+            if reservations.total().saturating_add(gas_limit) > MAX_GAS_PER_LEVEL {
+                return Err(Error::SchedulerLevelFull);
+            }
+        }
+
+        for level in levels {
+            *tx.entry::<Reservations>(hrt, Self::reserved_path(level)?)?
+                .or_insert_default()
+                .0
+                .entry(call.scheduler.clone())
+                .or_default() += gas_limit;
+        }
+
+        Self::reschedule(hrt, tx, call)
+    }
+
+    /// Enqueues the next run of a recurring call. Its gas was reserved when
+    /// the call was scheduled.
+    pub fn reschedule(
+        hrt: &impl HostRuntime,
+        tx: &mut Transaction,
+        call: ScheduledCall,
+    ) -> Result<()> {
+        tx.entry::<ScheduledCalls>(hrt, Self::queue_path(call.level)?)?
+            .or_insert_default()
+            .0
+            .push(call);
+        Ok(())
+    }
+
+    /// Total gas limit of the calls due at `level`, as committed to durable
+    /// storage
+    pub fn due_gas(hrt: &impl HostRuntime, level: u32) -> Result<usize> {
+        let calls: Option<ScheduledCalls> = Storage::get(hrt, &Self::queue_path(level)?)?;
+
+        Ok(calls
+            .map(|calls| calls.0.iter().map(|call| call.run.gas_limit).sum())
+            .unwrap_or(0))
+    }
+
+    /// Removes and returns the calls due at `level`, in scheduling order
+    pub fn take_due(
+        hrt: &impl HostRuntime,
+        tx: &mut Transaction,
+        level: u32,
+    ) -> Result<Vec<ScheduledCall>> {
+        let path = Self::queue_path(level)?;
+
+        let calls = match tx.get::<ScheduledCalls>(hrt, path.clone())? {
+            Some(calls) => calls.0.clone(),
+            None => return Ok(vec![]),
+        };
+        tx.remove(path)?;
+        tx.remove(Self::reserved_path(level)?)?;
+
+        Ok(calls)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use http::{HeaderMap, Method, Uri};
+    use jstz_crypto::keypair_from_passphrase;
+    use tezos_smart_rollup_mock::MockHost;
+
+    use super::*;
+
+    /// The address of the `n`th scheduler
+    fn scheduler(n: usize) -> Address {
+        let (_, pk) = keypair_from_passphrase(&format!("scheduler {n}"))
+            .expect("Could not generate keypair");
+        Address::try_from(&pk).expect("Could not hash public key")
+    }
+
+    fn call(id: u64, level: u32, gas_limit: usize) -> ScheduledCall {
+        call_by(&scheduler(0), id, level, gas_limit)
+    }
+
+    fn call_by(
+        scheduler: &Address,
+        id: u64,
+        level: u32,
+        gas_limit: usize,
+    ) -> ScheduledCall {
+        ScheduledCall {
+            id,
+            scheduler: scheduler.clone(),
+            run: RunFunction {
+                uri: Uri::from_static("tezos://tz1XQjK1b3P72kMcHsoPhnAg3dvX1n8Ainty/"),
+                method: Method::GET,
+                headers: HeaderMap::new(),
+                body: None,
+                gas_limit,
+            },
+            level,
+            every: 10,
+            remaining: 2,
+        }
+    }
+
+    #[test]
+    fn due_calls_are_taken_once() -> Result<()> {
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+        tx.begin();
+
+        Scheduler::schedule(hrt, tx, call(0, 5, 100))?;
+        Scheduler::schedule(hrt, tx, call(1, 5, 100))?;
+        Scheduler::schedule(hrt, tx, call(2, 6, 100))?;
+
+        let due = Scheduler::take_due(hrt, tx, 5)?;
+        assert_eq!(
+            due.iter().map(|call| call.id).collect::<Vec<_>>(),
+            vec![0, 1]
+        );
+        assert!(Scheduler::take_due(hrt, tx, 5)?.is_empty());
+        assert_eq!(Scheduler::take_due(hrt, tx, 6)?.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn level_gas_is_bounded() -> Result<()> {
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+        tx.begin();
+
+        // The level is filled by several schedulers
+        let schedulers = MAX_GAS_PER_LEVEL / MAX_GAS_PER_SCHEDULER;
+        for n in 0..schedulers {
+            let call = call_by(&scheduler(n), n as u64, 5, MAX_GAS_PER_SCHEDULER);
+            Scheduler::schedule(hrt, tx, call)?;
+        }
+        let call = call_by(&scheduler(schedulers), schedulers as u64, 5, 1);
+        assert!(matches!(
+            Scheduler::schedule(hrt, tx, call),
+            Err(Error::SchedulerLevelFull)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn schedulers_have_their_own_quota() -> Result<()> {
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+        tx.begin();
+
+        // A scheduler cannot take more than its quota of a level...
+        assert!(matches!(
+            Scheduler::schedule(hrt, tx, call(0, 5, MAX_GAS_PER_SCHEDULER + 1)),
+            Err(Error::SchedulerQuotaExceeded)
+        ));
+        Scheduler::schedule(hrt, tx, call(1, 5, MAX_GAS_PER_SCHEDULER))?;
+        assert!(matches!(
+            Scheduler::schedule(hrt, tx, call(2, 5, 1)),
+            Err(Error::SchedulerQuotaExceeded)
+        ));
+
+        // ...such that other schedulers can still schedule calls for it
+        Scheduler::schedule(
+            hrt,
+            tx,
+            call_by(&scheduler(1), 3, 5, MAX_GAS_PER_SCHEDULER),
+        )?;
+        assert_eq!(Scheduler::take_due(hrt, tx, 5)?.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn due_gas_sums_committed_calls() -> Result<()> {
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+        tx.begin();
+
+        Scheduler::schedule(hrt, tx, call(0, 5, 100))?;
+        Scheduler::schedule(hrt, tx, call(1, 5, 200))?;
+        assert_eq!(Scheduler::due_gas(hrt, 5)?, 0);
+
+        tx.commit(hrt)?;
+        assert_eq!(Scheduler::due_gas(hrt, 5)?, 300);
+        assert_eq!(Scheduler::due_gas(hrt, 6)?, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn gas_of_all_runs_is_reserved() -> Result<()> {
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+        tx.begin();
+
+        // Runs at levels 5 and 15
+        Scheduler::schedule(hrt, tx, call(0, 5, MAX_GAS_PER_SCHEDULER))?;
+        assert!(matches!(
+            Scheduler::schedule(hrt, tx, call(1, 15, 1)),
+            Err(Error::SchedulerQuotaExceeded)
+        ));
+
+        let due = Scheduler::take_due(hrt, tx, 5)?;
+        let next = due[0].clone().next().expect("Expected a second run");
+        Scheduler::reschedule(hrt, tx, next)?;
+        assert_eq!(Scheduler::take_due(hrt, tx, 15)?.len(), 1);
+
+        // The reservations are released once the calls are run
+        Scheduler::schedule(hrt, tx, call(2, 15, MAX_GAS_PER_SCHEDULER))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn recurring_calls_have_distinct_receipts() {
+        let first = call(0, 5, 100);
+        let second = first.clone().next().expect("Expected a second run");
+
+        assert_eq!(second.level, 15);
+        assert_ne!(first.receipt_hash(), second.receipt_hash());
+        assert!(second.next().is_none());
+    }
+}
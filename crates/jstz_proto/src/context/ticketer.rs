@@ -0,0 +1,25 @@
+use jstz_core::{host::HostRuntime, kv::Storage};
+use tezos_crypto_rs::hash::ContractKt1Hash;
+use tezos_smart_rollup::storage::path::RefPath;
+
+use crate::Result;
+
+pub(crate) const TICKETER_PATH: RefPath = RefPath::assert_from(b"/ticketer");
+
+const TICKETERS_PATH: RefPath = RefPath::assert_from(b"/ticketers");
+
+/// The ticketer is the L1 contract (the `jstz_bridge`) that mints and burns
+/// the tickets backing the native jstz balances.
+pub struct Ticketer;
+
+impl Ticketer {
+    pub fn get(rt: &impl HostRuntime) -> Result<Option<ContractKt1Hash>> {
+        Ok(Storage::get(rt, &TICKETER_PATH)?)
+    }
+
+    /// The allowlist of ticketers whose tickets (other than the native
+    /// ticketer's) can be deposited to jstz, each backing its own assets.
+    pub fn allowlist(rt: &impl HostRuntime) -> Result<Vec<ContractKt1Hash>> {
+        Ok(Storage::get(rt, &TICKETERS_PATH)?.unwrap_or_default())
+    }
+}
@@ -1,45 +1,113 @@
-use boa_engine::{JsError, JsNativeError};
+use boa_engine::{
+    object::ObjectData, JsError, JsNativeError, JsNativeErrorKind, JsObject,
+};
+use boa_gc::{empty_trace, Finalize, Trace};
 use derive_more::{Display, Error, From};
 
 #[derive(Display, Debug, Error, From)]
 pub enum Error {
-    CoreError { source: jstz_core::Error },
-    CryptoError { source: jstz_crypto::Error },
+    CoreError {
+        source: jstz_core::Error,
+    },
+    CryptoError {
+        source: jstz_crypto::Error,
+    },
+    /// An exception thrown by a script, with the stack trace of the thrown
+    /// `Error`, if any
+    #[display(fmt = "{}", source)]
+    #[from(ignore)]
+    ScriptError {
+        source: JsError,
+        stack: Option<String>,
+    },
     BalanceOverflow,
     InvalidNonce,
     InvalidAddress,
     RefererShouldNotBeSet,
-    GasLimitExceeded,
+    /// The gas limit was exceeded while executing `api`
+    #[display(fmt = "GasLimitExceeded: {}", api)]
+    #[from(ignore)]
+    GasLimitExceeded {
+        api: String,
+    },
     InvalidHttpRequest,
+    TicketerNotSet,
+    InvalidWithdrawal,
+    SourceMismatch,
+    UnauthorizedUpgrade,
+    SchedulerLevelFull,
+    InvalidAsset,
+    OperationExpired,
+    RollupAddressMismatch,
+    InsufficientAllowance,
+    InvalidPreimage,
+    /// Step `step` of a batch failed, rolling back the whole batch
+    #[display(fmt = "BatchStepFailed: step {}: {}", step, source)]
+    #[from(ignore)]
+    BatchStepFailed {
+        step: usize,
+        source: Box<Error>,
+    },
+    InsufficientFunds,
+    AddressAlreadyInUse,
+    /// A run of a smart function responded with a non-2xx status code
+    #[display(fmt = "RunFunctionFailed: status {}", status_code)]
+    #[from(ignore)]
+    RunFunctionFailed {
+        status_code: u16,
+    },
+    SchedulerQuotaExceeded,
 }
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// An error raised into a script, carried as the `cause` of the `EvalError` it
+/// is raised as, such that it is only reported for that very exception
+#[derive(Finalize)]
+struct Raised(Option<Error>);
+
+// Raised errors are not traced: JS values they may hold stay rooted until the
+// error is dropped
+unsafe impl Trace for Raised {
+    empty_trace!();
+}
+
+/// Raises `error` into a script as an `EvalError` with `message`. If the script
+/// fails with it, it is reported as is (see [`take_raised`]).
+fn raise(message: String, error: Error) -> JsError {
+    let raised = JsObject::from_proto_and_data(
+        None,
+        ObjectData::native_object(Raised(Some(error))),
+    );
+
+    JsNativeError::eval()
+        .with_message(message)
+        .with_cause(JsError::from_opaque(raised.into()))
+        .into()
+}
+
+/// Takes the error raised into a script as `error`, if any
+pub(crate) fn take_raised(error: &JsNativeError) -> Option<Error> {
+    if error.kind != JsNativeErrorKind::Eval {
+        return None;
+    }
+
+    let cause = error.cause()?.as_opaque()?.as_object()?;
+    let mut raised = cause.downcast_mut::<Raised>()?;
+    raised.0.take()
+}
+
 impl From<Error> for JsError {
     fn from(value: Error) -> Self {
-        match value {
-            Error::CoreError { source } => source.into(),
-            Error::CryptoError { source } => JsNativeError::eval()
-                .with_message(format!("CryptoError: {}", source))
-                .into(),
-            Error::BalanceOverflow => {
-                JsNativeError::eval().with_message("BalanceOverflow").into()
-            }
-            Error::InvalidNonce => {
-                JsNativeError::eval().with_message("InvalidNonce").into()
-            }
-            Error::InvalidAddress => {
-                JsNativeError::eval().with_message("InvalidAddress").into()
-            }
-            Error::RefererShouldNotBeSet => JsNativeError::eval()
-                .with_message("RefererShouldNotBeSet")
-                .into(),
-            Error::GasLimitExceeded => JsNativeError::eval()
-                .with_message("GasLimitExceeded")
-                .into(),
-            Error::InvalidHttpRequest => JsNativeError::eval()
-                .with_message("InvalidHttpRequest")
-                .into(),
-        }
+        let message = match value {
+            Error::CoreError { source } => return source.into(),
+            Error::ScriptError { source, .. } => return source,
+            Error::CryptoError { ref source } => format!("CryptoError: {}", source),
+            // Other errors are displayed as their name, or their name and
+            // details
+            ref error => error.to_string(),
+        };
+
+        raise(message, value)
     }
 }
 
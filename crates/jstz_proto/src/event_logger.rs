@@ -0,0 +1,47 @@
+use std::fmt::Display;
+
+use jstz_core::host::HostRuntime;
+use serde::{Deserialize, Serialize};
+
+use crate::{context::account::Address, receipt::Event};
+
+pub const EVENT_PREFIX: &str = "[JSTZ:SMART_FUNCTION:EVENT] ";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EventRecord {
+    pub address: Address,
+    pub request_id: String,
+    pub topic: String,
+    pub payload: serde_json::Value,
+}
+
+impl Display for EventRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(
+            &serde_json::to_string(self)
+                .expect("Failed to convert EventRecord to string"),
+        )
+    }
+}
+
+impl EventRecord {
+    pub fn new(request_id: String, event: &Event) -> Self {
+        EventRecord {
+            address: event.address.clone(),
+            request_id,
+            topic: event.topic.clone(),
+            payload: serde_json::from_str(&event.payload)
+                .unwrap_or_else(|_| serde_json::Value::String(event.payload.clone())),
+        }
+    }
+
+    pub fn try_from_string(json: &str) -> Option<Self> {
+        serde_json::from_str(json).ok()
+    }
+}
+
+pub fn log_event(hrt: &impl HostRuntime, request_id: String, event: &Event) {
+    let event_record = EventRecord::new(request_id, event).to_string();
+
+    hrt.write_debug(&(EVENT_PREFIX.to_string() + &event_record + "\n"));
+}
@@ -0,0 +1,29 @@
+use jstz_core::{host::HostRuntime, kv::Transaction};
+
+use crate::{
+    context::{account::Address, allowance::Allowance},
+    operation::Approve,
+    receipt, Result,
+};
+
+pub fn execute(
+    hrt: &impl HostRuntime,
+    tx: &mut Transaction,
+    source: &Address,
+    approval: Approve,
+) -> Result<receipt::Approval> {
+    let Approve {
+        spender,
+        amount,
+        asset,
+    } = approval;
+
+    Allowance::approve(hrt, tx, source, &spender, asset.as_ref(), amount)?;
+
+    Ok(receipt::Approval {
+        owner: source.clone(),
+        spender,
+        amount,
+        asset: Allowance::canonical_asset(hrt, asset.as_ref())?.cloned(),
+    })
+}
@@ -1,6 +1,10 @@
 use jstz_core::{host::HostRuntime, kv::Transaction};
 
-use crate::{context::account::Account, operation::external::Deposit, Result};
+use crate::{
+    context::account::Account,
+    operation::external::{AssetDeposit, Deposit},
+    Result,
+};
 
 pub fn execute(
     hrt: &mut impl HostRuntime,
@@ -11,3 +15,17 @@ pub fn execute(
 
     Account::deposit(hrt, tx, &reciever, amount)
 }
+
+pub fn execute_asset(
+    hrt: &mut impl HostRuntime,
+    tx: &mut Transaction,
+    deposit: AssetDeposit,
+) -> Result<()> {
+    let AssetDeposit {
+        asset,
+        amount,
+        receiver,
+    } = deposit;
+
+    Account::deposit_asset(hrt, tx, &receiver, &asset, amount)
+}
@@ -1,48 +1,146 @@
 use jstz_core::{host::HostRuntime, kv::Transaction};
 
 use crate::{
-    operation::{self, ExternalOperation, Operation, SignedOperation},
+    context::account::Address,
+    operation::{self, ExternalOperation, Operation, OperationHash, SignedOperation},
     receipt::{self, Receipt},
-    Result,
+    Error, Result,
 };
 
+pub mod approve;
 pub mod deposit;
+pub mod scheduler;
 pub mod smart_function;
+pub mod transfer;
+pub mod withdraw;
 
-fn execute_operation_inner(
+fn execute_batch(
     hrt: &mut impl HostRuntime,
     tx: &mut Transaction,
-    signed_operation: SignedOperation,
-) -> Result<receipt::Content> {
-    let operation = signed_operation.verify()?;
-    let operation_hash = operation.hash();
+    source: &Address,
+    steps: Vec<operation::Content>,
+    operation_hash: &OperationHash,
+) -> Result<Vec<receipt::Content>> {
+    steps
+        .into_iter()
+        .enumerate()
+        .map(|(step, content)| {
+            let result = match execute_content(hrt, tx, source, content, operation_hash) {
+                // A run responding with an error fails the batch, even though
+                // the run itself succeeded
+                Ok(receipt::Content::RunFunction(run))
+                    if !run.status_code.is_success() =>
+                {
+                    Err(Error::RunFunctionFailed {
+                        status_code: run.status_code.as_u16(),
+                    })
+                }
+                result => result,
+            };
 
-    operation.verify_nonce(hrt, tx)?;
+            result.map_err(|err| Error::BatchStepFailed {
+                step,
+                source: Box::new(err),
+            })
+        })
+        .collect()
+}
 
-    match operation {
-        Operation {
-            source,
-            content: operation::Content::DeployFunction(deployment),
-            ..
-        } => {
-            let result = smart_function::deploy::execute(hrt, tx, &source, deployment)?;
+fn execute_content(
+    hrt: &mut impl HostRuntime,
+    tx: &mut Transaction,
+    source: &Address,
+    content: operation::Content,
+    operation_hash: &OperationHash,
+) -> Result<receipt::Content> {
+    match content {
+        operation::Content::DeployFunction(deployment) => {
+            let result = smart_function::deploy::execute(hrt, tx, source, deployment)?;
 
             Ok(receipt::Content::DeployFunction(result))
         }
 
-        Operation {
-            content: operation::Content::RunFunction(run),
-            source,
-            ..
-        } => {
+        operation::Content::RevealDeployFunction(deployment) => {
             let result =
-                smart_function::run::execute(hrt, tx, &source, run, operation_hash)?;
+                smart_function::deploy::execute_reveal(hrt, tx, source, deployment)?;
+
+            Ok(receipt::Content::DeployFunction(result))
+        }
+
+        operation::Content::RunFunction(run) => {
+            let result = smart_function::run::execute(
+                hrt,
+                tx,
+                source,
+                run,
+                operation_hash.clone(),
+            )?;
 
             Ok(receipt::Content::RunFunction(result))
         }
+
+        operation::Content::UpgradeFunction(upgrade) => {
+            let result = smart_function::upgrade::execute(hrt, tx, source, upgrade)?;
+
+            Ok(receipt::Content::UpgradeFunction(result))
+        }
+
+        operation::Content::Withdraw(withdrawal) => {
+            withdraw::execute(hrt, tx, source, withdrawal)?;
+
+            Ok(receipt::Content::Withdraw)
+        }
+
+        operation::Content::Transfer(transfer) => {
+            let result = transfer::execute(hrt, tx, source, transfer)?;
+
+            Ok(receipt::Content::Transfer(result))
+        }
+
+        operation::Content::Approve(approval) => {
+            let result = approve::execute(hrt, tx, source, approval)?;
+
+            Ok(receipt::Content::Approve(result))
+        }
+
+        operation::Content::Batch(steps) => {
+            // All steps are executed in a single transaction, which is
+            // rolled back if any step fails
+            tx.begin();
+            match execute_batch(hrt, tx, source, steps, operation_hash) {
+                Ok(receipts) => {
+                    tx.commit(hrt)?;
+                    Ok(receipt::Content::Batch(receipts))
+                }
+                Err(err) => {
+                    tx.rollback()?;
+                    Err(err)
+                }
+            }
+        }
     }
 }
 
+fn execute_operation_inner(
+    hrt: &mut impl HostRuntime,
+    tx: &mut Transaction,
+    signed_operation: SignedOperation,
+) -> Result<receipt::Content> {
+    let operation = signed_operation.verify()?;
+    let operation_hash = operation.hash();
+
+    // Foreign and expired operations are rejected without consuming the nonce
+    operation.verify_rollup_address(hrt)?;
+    operation.verify_expiry(hrt)?;
+    operation.verify_nonce(hrt, tx)?;
+
+    let Operation {
+        source, content, ..
+    } = operation;
+
+    execute_content(hrt, tx, &source, content, &operation_hash)
+}
+
 pub fn execute_external_operation(
     hrt: &mut impl HostRuntime,
     tx: &mut Transaction,
@@ -50,6 +148,9 @@ pub fn execute_external_operation(
 ) -> Result<()> {
     match external_operation {
         ExternalOperation::Deposit(deposit) => deposit::execute(hrt, tx, deposit),
+        ExternalOperation::AssetDeposit(deposit) => {
+            deposit::execute_asset(hrt, tx, deposit)
+        }
     }
 }
 
@@ -62,3 +163,1061 @@ pub fn execute_operation(
     let inner = execute_operation_inner(hrt, tx, signed_operation);
     Receipt::new(hash, inner)
 }
+
+#[cfg(test)]
+mod test {
+    use jstz_crypto::{
+        keypair_from_passphrase_with_key_type, public_key::PublicKey,
+        secret_key::SecretKey, KeyType,
+    };
+    use proptest::prelude::*;
+    use tezos_crypto_rs::hash::SmartRollupHash;
+    use tezos_smart_rollup_mock::MockHost;
+
+    use super::*;
+    use crate::{
+        context::{
+            account::{Account, Address, Modules, Nonce, ParsedCode},
+            allowance::Allowance,
+            block::BlockInfo,
+        },
+        executor::smart_function::Script,
+        operation::{Approve, Content, DeployFunction, RunFunction, Transfer, Withdraw},
+        receipt::ReceiptError,
+    };
+
+    fn key_type() -> impl Strategy<Value = KeyType> {
+        prop_oneof![
+            Just(KeyType::Ed25519),
+            Just(KeyType::Secp256k1),
+            Just(KeyType::P256)
+        ]
+    }
+
+    fn keypair(key_type: KeyType, passphrase: &str) -> (SecretKey, PublicKey, Address) {
+        let (sk, pk) = keypair_from_passphrase_with_key_type(key_type, passphrase)
+            .expect("Could not generate keypair");
+        let address = Address::try_from(&pk).expect("Could not hash public key");
+        (sk, pk, address)
+    }
+
+    fn sign(sk: &SecretKey, pk: PublicKey, op: Operation) -> SignedOperation {
+        let signature = sk.sign(op.hash()).expect("Could not sign operation");
+        SignedOperation::new(pk, signature, op)
+    }
+
+    fn rollup_address() -> SmartRollupHash {
+        MockHost::default().reveal_metadata().address()
+    }
+
+    fn deploy(source: Address) -> Operation {
+        Operation {
+            source,
+            nonce: Nonce::default(),
+            content: Content::DeployFunction(DeployFunction {
+                function_code: ParsedCode::try_from(String::new())
+                    .expect("Could not parse code"),
+                account_credit: 0,
+                admin: None,
+                salt: None,
+                modules: Modules::default(),
+            }),
+            rollup_address: rollup_address(),
+            valid_until_level: None,
+        }
+    }
+
+    fn withdraw(source: Address, amount: u64) -> Operation {
+        Operation {
+            source: source.clone(),
+            nonce: Nonce::default(),
+            content: Content::Withdraw(Withdraw {
+                receiver: source,
+                amount,
+            }),
+            rollup_address: rollup_address(),
+            valid_until_level: None,
+        }
+    }
+
+    fn withdraw_batch(source: Address, amounts: &[u64]) -> Operation {
+        let steps = amounts
+            .iter()
+            .map(|&amount| {
+                Content::Withdraw(Withdraw {
+                    receiver: source.clone(),
+                    amount,
+                })
+            })
+            .collect();
+
+        Operation {
+            source,
+            nonce: Nonce::default(),
+            content: Content::Batch(steps),
+            rollup_address: rollup_address(),
+            valid_until_level: None,
+        }
+    }
+
+    #[test]
+    fn batch_produces_receipt_per_step() {
+        let (sk, pk, address) = keypair(KeyType::Ed25519, "batch");
+
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+        tx.begin();
+        Account::deposit(hrt, tx, &address, 30).unwrap();
+
+        let op = withdraw_batch(address.clone(), &[10, 20]);
+        let receipt = execute_operation(hrt, tx, sign(&sk, pk, op));
+
+        match receipt.inner {
+            Ok(receipt::Content::Batch(steps)) => assert_eq!(steps.len(), 2),
+            other => panic!("Unexpected receipt: {other:?}"),
+        }
+        assert_eq!(Account::balance(hrt, tx, &address).unwrap(), 0);
+    }
+
+    #[test]
+    fn failing_step_rolls_back_batch() {
+        let (sk, pk, address) = keypair(KeyType::Ed25519, "batch");
+
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+        tx.begin();
+        Account::deposit(hrt, tx, &address, 30).unwrap();
+
+        let op = withdraw_batch(address.clone(), &[10, 30]);
+        let receipt = execute_operation(hrt, tx, sign(&sk, pk, op));
+
+        assert_eq!(
+            receipt.inner.unwrap_err(),
+            ReceiptError::BatchStepFailed {
+                step: 1,
+                error: Box::new(ReceiptError::InsufficientFunds)
+            }
+        );
+        assert_eq!(Account::balance(hrt, tx, &address).unwrap(), 30);
+        // The nonce is consumed even though the batch failed
+        assert_eq!(
+            *Account::nonce(hrt, tx, &address).unwrap(),
+            Nonce::default().next()
+        );
+    }
+
+    #[test]
+    fn failing_run_step_fails_batch() {
+        let (sk, pk, address) = keypair(KeyType::Ed25519, "batch");
+
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+        tx.begin();
+        Account::deposit(hrt, tx, &address, 30).unwrap();
+
+        let code = ParsedCode::try_from(
+            "export default () => new Response(null, { status: 500 });".to_string(),
+        )
+        .expect("Could not parse code");
+        let function =
+            Script::deploy(hrt, tx, &address, code, Modules::default(), 0, None, None)
+                .expect("Could not deploy code");
+
+        let Content::Batch(mut steps) = withdraw_batch(address.clone(), &[10]).content
+        else {
+            unreachable!()
+        };
+        steps.push(run(address.clone(), &function).content);
+        let op = Operation {
+            content: Content::Batch(steps),
+            ..withdraw_batch(address.clone(), &[])
+        };
+        let receipt = execute_operation(hrt, tx, sign(&sk, pk, op));
+
+        assert_eq!(
+            receipt.inner.unwrap_err(),
+            ReceiptError::BatchStepFailed {
+                step: 1,
+                error: Box::new(ReceiptError::RunFunctionFailed { status_code: 500 })
+            }
+        );
+        assert_eq!(Account::balance(hrt, tx, &address).unwrap(), 30);
+    }
+
+    #[test]
+    fn unsalted_deploys_of_same_code_in_batch_are_rejected() {
+        let (sk, pk, address) = keypair(KeyType::Ed25519, "batch");
+
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+        tx.begin();
+
+        let op = Operation {
+            content: Content::Batch(vec![
+                deploy(address.clone()).content,
+                deploy(address.clone()).content,
+            ]),
+            ..deploy(address)
+        };
+        let receipt = execute_operation(hrt, tx, sign(&sk, pk, op));
+
+        assert_eq!(
+            receipt.inner.unwrap_err(),
+            ReceiptError::BatchStepFailed {
+                step: 1,
+                error: Box::new(ReceiptError::AddressAlreadyInUse)
+            }
+        );
+    }
+
+    #[test]
+    fn withdraw_debits_source() {
+        let (sk, pk, address) = keypair(KeyType::Ed25519, "withdraw");
+
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+        tx.begin();
+        Account::deposit(hrt, tx, &address, 30).unwrap();
+
+        let op = withdraw(address.clone(), 10);
+        let receipt = execute_operation(hrt, tx, sign(&sk, pk, op));
+
+        assert!(matches!(receipt.inner, Ok(receipt::Content::Withdraw)));
+        assert_eq!(Account::balance(hrt, tx, &address).unwrap(), 20);
+    }
+
+    #[test]
+    fn withdraw_without_funds_is_rejected() {
+        let (sk, pk, address) = keypair(KeyType::Ed25519, "withdraw");
+
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+        tx.begin();
+        Account::deposit(hrt, tx, &address, 5).unwrap();
+
+        let op = withdraw(address.clone(), 10);
+        let receipt = execute_operation(hrt, tx, sign(&sk, pk, op));
+
+        assert_eq!(receipt.inner.unwrap_err(), ReceiptError::InsufficientFunds);
+        assert_eq!(Account::balance(hrt, tx, &address).unwrap(), 5);
+    }
+
+    #[test]
+    fn transfer_moves_balance_to_destination() {
+        let (sk, pk, source) = keypair(KeyType::Ed25519, "source");
+        let (_, _, destination) = keypair(KeyType::Ed25519, "destination");
+
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+        tx.begin();
+        Account::deposit(hrt, tx, &source, 30).unwrap();
+
+        let op = Operation {
+            source: source.clone(),
+            nonce: Nonce::default(),
+            content: Content::Transfer(Transfer {
+                destination: destination.clone(),
+                amount: 10,
+            }),
+            rollup_address: rollup_address(),
+            valid_until_level: None,
+        };
+        let receipt = execute_operation(hrt, tx, sign(&sk, pk, op));
+
+        match receipt.inner {
+            Ok(receipt::Content::Transfer(transfer)) => {
+                assert_eq!(transfer.from, source);
+                assert_eq!(transfer.to, destination);
+                assert_eq!(transfer.amount, 10);
+            }
+            other => panic!("Unexpected receipt: {other:?}"),
+        }
+        assert_eq!(Account::balance(hrt, tx, &source).unwrap(), 20);
+        assert_eq!(Account::balance(hrt, tx, &destination).unwrap(), 10);
+    }
+
+    #[test]
+    fn approve_lets_spender_transfer_from_user_balance() {
+        let (sk, pk, owner) = keypair(KeyType::Ed25519, "owner");
+        let (_, _, destination) = keypair(KeyType::Ed25519, "destination");
+
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+        tx.begin();
+        Account::deposit(hrt, tx, &owner, 30).unwrap();
+
+        let code = ParsedCode::try_from(format!(
+            r#"
+                export default () => {{
+                    Ledger.transferFrom("{owner}", "{destination}", 15);
+                    return new Response();
+                }};
+            "#
+        ))
+        .expect("Could not parse code");
+        let spender =
+            Script::deploy(hrt, tx, &owner, code, Modules::default(), 0, None, None)
+                .expect("Could not deploy code");
+
+        let op = Operation {
+            source: owner.clone(),
+            nonce: Nonce::default(),
+            content: Content::Approve(Approve {
+                spender: spender.clone(),
+                amount: 20,
+                asset: None,
+            }),
+            rollup_address: rollup_address(),
+            valid_until_level: None,
+        };
+        let receipt = execute_operation(hrt, tx, sign(&sk, pk.clone(), op));
+        match receipt.inner {
+            Ok(receipt::Content::Approve(approval)) => assert_eq!(
+                approval,
+                receipt::Approval {
+                    owner: owner.clone(),
+                    spender: spender.clone(),
+                    amount: 20,
+                    asset: None
+                }
+            ),
+            other => panic!("Unexpected receipt: {other:?}"),
+        }
+
+        let op = Operation {
+            nonce: Nonce::default().next(),
+            ..run(owner.clone(), &spender)
+        };
+        let receipt = execute_operation(hrt, tx, sign(&sk, pk.clone(), op));
+        assert!(receipt.inner.is_ok());
+        assert_eq!(Account::balance(hrt, tx, &owner).unwrap(), 15);
+        assert_eq!(Account::balance(hrt, tx, &destination).unwrap(), 15);
+        assert_eq!(Allowance::get(hrt, tx, &owner, &spender, None).unwrap(), 5);
+
+        // The remaining allowance does not cover another transfer
+        let op = Operation {
+            nonce: Nonce::default().next().next(),
+            ..run(owner.clone(), &spender)
+        };
+        let receipt = execute_operation(hrt, tx, sign(&sk, pk, op));
+        assert_eq!(
+            receipt.inner.unwrap_err(),
+            ReceiptError::InsufficientAllowance
+        );
+        assert_eq!(Account::balance(hrt, tx, &owner).unwrap(), 15);
+    }
+
+    #[test]
+    fn salted_deploy_has_predictable_address() {
+        let (sk, pk, source) = keypair(KeyType::Ed25519, "salted");
+
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+        tx.begin();
+
+        let op = deploy(source.clone());
+        let Content::DeployFunction(deployment) = op.content.clone() else {
+            unreachable!()
+        };
+        let address = Script::salted_address(
+            &source,
+            "salt",
+            &deployment.function_code,
+            &deployment.modules,
+        )
+        .expect("Could not derive address");
+
+        // The address can be funded before the deployment
+        Account::deposit(hrt, tx, &address, 10).unwrap();
+
+        let op = Operation {
+            content: Content::DeployFunction(DeployFunction {
+                salt: Some("salt".to_string()),
+                ..deployment
+            }),
+            ..op
+        };
+        let receipt = execute_operation(hrt, tx, sign(&sk, pk, op));
+
+        match receipt.inner {
+            Ok(receipt::Content::DeployFunction(deployed)) => {
+                assert_eq!(deployed.address, address)
+            }
+            other => panic!("Unexpected receipt: {other:?}"),
+        }
+        assert!(Account::function_code(hrt, tx, &address).unwrap().is_some());
+        assert_eq!(Account::balance(hrt, tx, &address).unwrap(), 10);
+    }
+
+    #[test]
+    fn salted_redeploy_is_rejected() {
+        let (sk, pk, source) = keypair(KeyType::Ed25519, "salted");
+
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+        tx.begin();
+
+        let salted = |nonce: Nonce| {
+            let op = deploy(source.clone());
+            let Content::DeployFunction(deployment) = op.content.clone() else {
+                unreachable!()
+            };
+            Operation {
+                nonce,
+                content: Content::DeployFunction(DeployFunction {
+                    account_credit: 10,
+                    salt: Some("salt".to_string()),
+                    ..deployment
+                }),
+                ..op
+            }
+        };
+
+        let receipt =
+            execute_operation(hrt, tx, sign(&sk, pk.clone(), salted(Nonce::default())));
+        let Ok(receipt::Content::DeployFunction(deployed)) = receipt.inner else {
+            panic!("Unexpected receipt: {:?}", receipt.inner)
+        };
+
+        let receipt =
+            execute_operation(hrt, tx, sign(&sk, pk, salted(Nonce::default().next())));
+        assert_eq!(
+            receipt.inner.unwrap_err(),
+            ReceiptError::AddressAlreadyInUse
+        );
+        assert_eq!(Account::balance(hrt, tx, &deployed.address).unwrap(), 10);
+    }
+
+    #[test]
+    fn deploy_stores_modules_by_normalized_path() {
+        let (sk, pk, source) = keypair(KeyType::Ed25519, "modules");
+
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+        tx.begin();
+
+        let module = ParsedCode::try_from("export const one = 1;".to_string())
+            .expect("Could not parse code");
+        let op = deploy(source);
+        let Content::DeployFunction(deployment) = op.content.clone() else {
+            unreachable!()
+        };
+        let op = Operation {
+            content: Content::DeployFunction(DeployFunction {
+                modules: Modules([("./lib/one.js".to_string(), module.clone())].into()),
+                ..deployment
+            }),
+            ..op
+        };
+        let receipt = execute_operation(hrt, tx, sign(&sk, pk, op));
+
+        let address = match receipt.inner {
+            Ok(receipt::Content::DeployFunction(deployed)) => deployed.address,
+            other => panic!("Unexpected receipt: {other:?}"),
+        };
+        assert_eq!(
+            Modules::get(hrt, tx, &address).unwrap(),
+            Modules([("lib/one.js".to_string(), module)].into())
+        );
+    }
+
+    #[test]
+    fn run_does_not_share_module_state_between_calls() {
+        let (sk, pk, source) = keypair(KeyType::Ed25519, "module state");
+
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+        tx.begin();
+
+        let deploy_code = |hrt: &mut MockHost, tx: &mut Transaction, code: String| {
+            let code = ParsedCode::try_from(code).expect("Could not parse code");
+            Script::deploy(hrt, tx, &source, code, Modules::default(), 0, None, None)
+                .expect("Could not deploy code")
+        };
+
+        // Module-level state would be observed by later calls if the callee's
+        // realm were reused
+        let callee = deploy_code(
+            hrt,
+            tx,
+            r#"
+                let calls = 0;
+                export default (request) => {
+                    calls += 1;
+                    const failed = new URL(request.url).pathname === "/fail";
+                    return new Response(String(calls), { status: failed ? 500 : 200 });
+                };
+            "#
+            .to_string(),
+        );
+        let caller = deploy_code(
+            hrt,
+            tx,
+            format!(
+                r#"
+                    const call = async (path) => {{
+                        const request = new Request("tezos://{callee}" + path);
+                        return (await SmartFunction.call(request)).text();
+                    }};
+                    export default async () => {{
+                        const calls = [];
+                        for (const path of ["/", "/", "/fail", "/"]) {{
+                            calls.push(await call(path));
+                        }}
+                        return new Response(calls.join(","));
+                    }};
+                "#
+            ),
+        );
+
+        let op = Operation {
+            source: source.clone(),
+            nonce: Nonce::default(),
+            content: Content::RunFunction(RunFunction {
+                uri: format!("tezos://{caller}/").parse().unwrap(),
+                method: http::Method::GET,
+                headers: http::HeaderMap::new(),
+                body: None,
+                gas_limit: 1_000_000,
+            }),
+            rollup_address: rollup_address(),
+            valid_until_level: None,
+        };
+        let receipt = execute_operation(hrt, tx, sign(&sk, pk, op));
+
+        // Every call evaluates the callee's module afresh
+        match receipt.inner {
+            Ok(receipt::Content::RunFunction(run)) => {
+                assert_eq!(run.body, Some(b"1,1,1,1".to_vec()))
+            }
+            other => panic!("Unexpected receipt: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn run_receipt_reports_calls_and_effects() {
+        let (sk, pk, source) = keypair(KeyType::Ed25519, "journal");
+
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+        tx.begin();
+
+        let deploy_code = |hrt: &mut MockHost, tx: &mut Transaction, code: String| {
+            let code = ParsedCode::try_from(code).expect("Could not parse code");
+            Script::deploy(hrt, tx, &source, code, Modules::default(), 10, None, None)
+                .expect("Could not deploy code")
+        };
+
+        let callee = deploy_code(
+            hrt,
+            tx,
+            r#"
+                export default (request) => {
+                    Kv.set("callee", 1);
+                    const failed = new URL(request.url).pathname === "/fail";
+                    return new Response(null, { status: failed ? 500 : 200 });
+                };
+            "#
+            .to_string(),
+        );
+        let caller = deploy_code(
+            hrt,
+            tx,
+            format!(
+                r#"
+                    export default async () => {{
+                        Kv.set("caller", 1);
+                        Ledger.transfer("{source}", 3);
+                        await SmartFunction.call(new Request("tezos://{callee}/"));
+                        await SmartFunction.call(new Request("tezos://{callee}/fail"));
+                        return new Response();
+                    }};
+                "#
+            ),
+        );
+
+        let op = Operation {
+            source: source.clone(),
+            nonce: Nonce::default(),
+            content: Content::RunFunction(RunFunction {
+                uri: format!("tezos://{caller}/").parse().unwrap(),
+                method: http::Method::GET,
+                headers: http::HeaderMap::new(),
+                body: None,
+                gas_limit: 1_000_000,
+            }),
+            rollup_address: rollup_address(),
+            valid_until_level: None,
+        };
+        let receipt = execute_operation(hrt, tx, sign(&sk, pk, op));
+
+        // The failed call is reported, but its KV write is discarded
+        let run = match receipt.inner {
+            Ok(receipt::Content::RunFunction(run)) => run,
+            other => panic!("Unexpected receipt: {other:?}"),
+        };
+        assert_eq!(run.calls, vec![callee.clone(), callee.clone()]);
+        assert_eq!(
+            run.kv_writes,
+            vec![
+                receipt::KvWrite {
+                    address: caller.clone(),
+                    key: "caller".to_string()
+                },
+                receipt::KvWrite {
+                    address: callee,
+                    key: "callee".to_string()
+                }
+            ]
+        );
+        assert_eq!(
+            run.transfers,
+            vec![receipt::Transfer {
+                from: caller,
+                to: source,
+                amount: 3,
+                asset: None
+            }]
+        );
+    }
+
+    fn run(source: Address, address: &Address) -> Operation {
+        Operation {
+            source,
+            nonce: Nonce::default(),
+            content: Content::RunFunction(RunFunction {
+                uri: format!("tezos://{address}/").parse().unwrap(),
+                method: http::Method::GET,
+                headers: http::HeaderMap::new(),
+                body: None,
+                gas_limit: 1_000_000,
+            }),
+            rollup_address: rollup_address(),
+            valid_until_level: None,
+        }
+    }
+
+    #[test]
+    fn run_reports_thrown_errors_with_their_stack() {
+        let (sk, pk, source) = keypair(KeyType::Ed25519, "stack");
+
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+        tx.begin();
+
+        let code = ParsedCode::try_from(
+            r#"
+                export default () => {
+                    const error = new TypeError("boom");
+                    error.stack = "at handler";
+                    throw error;
+                };
+            "#
+            .to_string(),
+        )
+        .expect("Could not parse code");
+        let address =
+            Script::deploy(hrt, tx, &source, code, Modules::default(), 0, None, None)
+                .expect("Could not deploy code");
+
+        let receipt = execute_operation(hrt, tx, sign(&sk, pk, run(source, &address)));
+
+        assert_eq!(
+            receipt.inner.unwrap_err(),
+            ReceiptError::JsError {
+                name: "TypeError".to_string(),
+                message: "boom".to_string(),
+                stack: Some("at handler".to_string())
+            }
+        );
+    }
+
+    #[test]
+    fn run_reports_uncaught_host_errors_as_is() {
+        let (sk, pk, source) = keypair(KeyType::Ed25519, "host error");
+
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+        tx.begin();
+
+        let deploy_code = |hrt: &mut MockHost, tx: &mut Transaction, code: &str| {
+            let code =
+                ParsedCode::try_from(code.to_string()).expect("Could not parse code");
+            Script::deploy(hrt, tx, &source, code, Modules::default(), 0, None, None)
+                .expect("Could not deploy code")
+        };
+
+        // The smart function has no funds to transfer
+        let uncaught = deploy_code(
+            hrt,
+            tx,
+            r#"
+                export default () => {
+                    Ledger.transfer(Ledger.selfAddress, 1);
+                    return new Response();
+                };
+            "#,
+        );
+        let rethrown = deploy_code(
+            hrt,
+            tx,
+            r#"
+                export default () => {
+                    try {
+                        Ledger.transfer(Ledger.selfAddress, 1);
+                    } catch (error) {
+                        throw new Error(`Transfer failed: ${error.message}`);
+                    }
+                };
+            "#,
+        );
+
+        // The caught host error itself is rethrown
+        let rethrown_as_is = deploy_code(
+            hrt,
+            tx,
+            r#"
+                export default () => {
+                    try {
+                        Ledger.transfer(Ledger.selfAddress, 1);
+                    } catch (error) {
+                        throw error;
+                    }
+                };
+            "#,
+        );
+        // A different error of the same kind and message as the host error
+        let imitated = deploy_code(
+            hrt,
+            tx,
+            r#"
+                export default () => {
+                    try {
+                        Ledger.transfer(Ledger.selfAddress, 1);
+                    } catch (error) {
+                        throw new EvalError(error.message);
+                    }
+                };
+            "#,
+        );
+
+        let mut nonce = Nonce::default();
+        let mut execute = |address: &Address| {
+            let op = Operation {
+                nonce,
+                ..run(source.clone(), address)
+            };
+            nonce = nonce.next();
+            execute_operation(hrt, tx, sign(&sk, pk.clone(), op))
+                .inner
+                .unwrap_err()
+        };
+
+        assert_eq!(execute(&uncaught), ReceiptError::BalanceOverflow);
+        assert_eq!(
+            execute(&rethrown),
+            ReceiptError::JsError {
+                name: "Error".to_string(),
+                message: "Transfer failed: BalanceOverflow".to_string(),
+                stack: None
+            }
+        );
+        assert_eq!(execute(&rethrown_as_is), ReceiptError::BalanceOverflow);
+        assert_eq!(
+            execute(&imitated),
+            ReceiptError::JsError {
+                name: "EvalError".to_string(),
+                message: "BalanceOverflow".to_string(),
+                stack: None
+            }
+        );
+    }
+
+    const BLOCK_CODE: &str = r#"
+        export default () =>
+            new Response(
+                `${Date.now()},${Jstz.block.level},${Jstz.block.timestamp}`,
+            );
+    "#;
+
+    fn run_body(hrt: &mut MockHost, tx: &mut Transaction, seed: &str) -> String {
+        let (sk, pk, source) = keypair(KeyType::Ed25519, seed);
+
+        let code =
+            ParsedCode::try_from(BLOCK_CODE.to_string()).expect("Could not parse code");
+        let address =
+            Script::deploy(hrt, tx, &source, code, Modules::default(), 0, None, None)
+                .expect("Could not deploy code");
+
+        let receipt = execute_operation(hrt, tx, sign(&sk, pk, run(source, &address)));
+        match receipt.inner {
+            Ok(receipt::Content::RunFunction(run)) => {
+                String::from_utf8(run.body.expect("Expected a body")).unwrap()
+            }
+            other => panic!("Unexpected receipt: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn run_observes_the_current_block() {
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+        tx.begin();
+
+        BlockInfo {
+            level: 42,
+            timestamp: 1_700_000_000,
+        }
+        .set(hrt)
+        .unwrap();
+
+        assert_eq!(run_body(hrt, tx, "block"), "1700000000000,42,1700000000000");
+    }
+
+    #[test]
+    fn run_without_block_does_not_observe_an_earlier_block() {
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+        tx.begin();
+        BlockInfo {
+            level: 42,
+            timestamp: 1_700_000_000,
+        }
+        .set(hrt)
+        .unwrap();
+        run_body(hrt, tx, "block");
+
+        // A host that has seen no block
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+        tx.begin();
+
+        assert_eq!(
+            run_body(hrt, tx, "no block"),
+            "1690797026000,0,1690797026000"
+        );
+    }
+
+    #[test]
+    fn run_rejects_free_scheduled_calls() {
+        let (sk, pk, source) = keypair(KeyType::Ed25519, "schedule");
+
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+        tx.begin();
+
+        let code = ParsedCode::try_from(
+            r#"
+                export default () => {
+                    Jstz.schedule(new Request(`tezos://${Ledger.selfAddress}/`), {
+                        atLevel: 10,
+                        gasLimit: 0,
+                        every: 1,
+                        times: 1000000,
+                    });
+                    return new Response();
+                };
+            "#
+            .to_string(),
+        )
+        .expect("Could not parse code");
+        let address =
+            Script::deploy(hrt, tx, &source, code, Modules::default(), 0, None, None)
+                .expect("Could not deploy code");
+
+        let receipt = execute_operation(hrt, tx, sign(&sk, pk, run(source, &address)));
+
+        assert_eq!(
+            receipt.inner.unwrap_err(),
+            ReceiptError::JsError {
+                name: "RangeError".to_string(),
+                message: "`gasLimit` must be at least 1".to_string(),
+                stack: None
+            }
+        );
+    }
+
+    #[test]
+    fn run_rolls_back_scripts_that_catch_gas_exhaustion() {
+        let (sk, pk, source) = keypair(KeyType::Ed25519, "gas");
+
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+        tx.begin();
+
+        let code = ParsedCode::try_from(
+            r#"
+                export default () => {
+                    Kv.set("before", 1);
+                    try {
+                        Kv.set("large", "x".repeat(10000));
+                    } catch (error) {}
+                    return new Response();
+                };
+            "#
+            .to_string(),
+        )
+        .expect("Could not parse code");
+        let address =
+            Script::deploy(hrt, tx, &source, code, Modules::default(), 0, None, None)
+                .expect("Could not deploy code");
+
+        let op = Operation {
+            source: source.clone(),
+            nonce: Nonce::default(),
+            content: Content::RunFunction(RunFunction {
+                uri: format!("tezos://{address}/").parse().unwrap(),
+                method: http::Method::GET,
+                headers: http::HeaderMap::new(),
+                body: None,
+                gas_limit: 10_000,
+            }),
+            rollup_address: rollup_address(),
+            valid_until_level: None,
+        };
+        let receipt = execute_operation(hrt, tx, sign(&sk, pk, op));
+
+        assert!(matches!(
+            receipt.inner,
+            Err(ReceiptError::GasLimitExceeded { api }) if api == "Kv.set"
+        ));
+        let kv = jstz_api::Kv::new(address.to_string());
+        assert!(!kv.has(hrt, tx, "before").unwrap());
+        assert!(!kv.has(hrt, tx, "large").unwrap());
+    }
+
+    #[test]
+    fn expired_operation_is_rejected() {
+        let (sk, pk, address) = keypair(KeyType::Ed25519, "expiry");
+
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+        tx.begin();
+        BlockInfo {
+            level: 11,
+            timestamp: 0,
+        }
+        .set(hrt)
+        .unwrap();
+
+        let op = Operation {
+            valid_until_level: Some(10),
+            ..deploy(address.clone())
+        };
+        let receipt = execute_operation(hrt, tx, sign(&sk, pk.clone(), op));
+
+        assert_eq!(receipt.inner.unwrap_err(), ReceiptError::OperationExpired);
+        assert_eq!(
+            *Account::nonce(hrt, tx, &address).unwrap(),
+            Nonce::default()
+        );
+
+        let op = Operation {
+            valid_until_level: Some(11),
+            ..deploy(address)
+        };
+        let receipt = execute_operation(hrt, tx, sign(&sk, pk, op));
+
+        assert!(receipt.inner.is_ok());
+    }
+
+    #[test]
+    fn foreign_rollup_operation_is_rejected() {
+        let (sk, pk, address) = keypair(KeyType::Ed25519, "foreign");
+
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+        tx.begin();
+
+        let mut foreign_address = rollup_address();
+        foreign_address.0[0] ^= 0xff;
+
+        let op = Operation {
+            rollup_address: foreign_address,
+            ..deploy(address.clone())
+        };
+        let receipt = execute_operation(hrt, tx, sign(&sk, pk, op));
+
+        assert_eq!(
+            receipt.inner.unwrap_err(),
+            ReceiptError::RollupAddressMismatch
+        );
+        assert_eq!(
+            *Account::nonce(hrt, tx, &address).unwrap(),
+            Nonce::default()
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn forged_source_is_rejected(
+            signer_key_type in key_type(),
+            victim_key_type in key_type(),
+            signer in "[a-z]{1,16}",
+            victim in "[a-z]{1,16}",
+            amount in 1..1000u64,
+        ) {
+            let (sk, pk, signer_address) = keypair(signer_key_type, &signer);
+            let (_, _, victim_address) = keypair(victim_key_type, &victim);
+            prop_assume!(signer_address != victim_address);
+
+            let hrt = &mut MockHost::default();
+            let tx = &mut Transaction::default();
+            tx.begin();
+            Account::deposit(hrt, tx, &victim_address, amount).unwrap();
+
+            let op = Operation {
+                source: victim_address.clone(),
+                nonce: Nonce::default(),
+                content: Content::Withdraw(Withdraw {
+                    receiver: signer_address,
+                    amount,
+                }),
+                rollup_address: rollup_address(),
+                valid_until_level: None,
+            };
+            let receipt = execute_operation(hrt, tx, sign(&sk, pk, op));
+
+            prop_assert_eq!(receipt.inner.unwrap_err(), ReceiptError::SourceMismatch);
+            prop_assert_eq!(Account::balance(hrt, tx, &victim_address).unwrap(), amount);
+            prop_assert_eq!(*Account::nonce(hrt, tx, &victim_address).unwrap(), Nonce::default());
+        }
+
+        #[test]
+        fn matching_source_is_accepted(
+            key_type in key_type(),
+            signer in "[a-z]{1,16}",
+        ) {
+            let (sk, pk, address) = keypair(key_type, &signer);
+
+            let hrt = &mut MockHost::default();
+            let tx = &mut Transaction::default();
+            tx.begin();
+
+            let receipt = execute_operation(hrt, tx, sign(&sk, pk, deploy(address)));
+
+            prop_assert!(receipt.inner.is_ok());
+        }
+
+        #[test]
+        fn foreign_signature_is_rejected(
+            key_type in key_type(),
+            signer in "[a-z]{1,16}",
+            other in "[a-z]{1,16}",
+        ) {
+            let (_, pk, address) = keypair(key_type, &signer);
+            let (other_sk, _, other_address) = keypair(key_type, &other);
+            prop_assume!(address != other_address);
+
+            let hrt = &mut MockHost::default();
+            let tx = &mut Transaction::default();
+            tx.begin();
+
+            // The public key matches the source, but the signature does not
+            let receipt = execute_operation(hrt, tx, sign(&other_sk, pk, deploy(address)));
+
+            prop_assert!(receipt.inner.is_err());
+            prop_assert_ne!(receipt.inner.unwrap_err(), ReceiptError::SourceMismatch);
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use jstz_core::{host::HostRuntime, kv::Transaction};
+
+use crate::{
+    context::scheduler::Scheduler,
+    executor::smart_function,
+    receipt::{self, Receipt},
+    Result,
+};
+
+/// Runs the calls scheduled for `level`, returning one receipt per call.
+/// Recurring calls are rescheduled for their next run.
+pub fn execute_due(
+    hrt: &mut impl HostRuntime,
+    tx: &mut Transaction,
+    level: u32,
+) -> Result<Vec<Receipt>> {
+    let calls = Scheduler::take_due(hrt, tx, level)?;
+
+    let mut receipts = Vec::with_capacity(calls.len());
+    for call in calls {
+        let hash = call.receipt_hash();
+        let result = smart_function::run::execute(
+            hrt,
+            tx,
+            &call.scheduler,
+            call.run.clone(),
+            hash.clone(),
+        )
+        .map(receipt::Content::RunFunction);
+        receipts.push(Receipt::new(hash, result));
+
+        // The gas of the following runs was reserved when scheduling the call
+        if let Some(next) = call.next() {
+            Scheduler::reschedule(hrt, tx, next)?;
+        }
+    }
+
+    Ok(receipts)
+}
@@ -3,7 +3,8 @@ use std::{io::Read, ops::BitXor};
 use boa_engine::{
     js_string,
     object::{builtins::JsPromise, FunctionObjectBuilder},
-    Context, JsArgs, JsError, JsNativeError, JsResult, JsValue, NativeFunction, Source,
+    Context, JsArgs, JsError, JsNativeError, JsResult, JsString, JsValue, NativeFunction,
+    Source,
 };
 use boa_gc::{Finalize, Trace};
 use derive_more::{Deref, DerefMut};
@@ -16,14 +17,24 @@ use jstz_api::{
     js_log::set_js_logger,
 };
 use jstz_core::{
-    host::HostRuntime, host_defined, kv::Transaction, native::JsNativeObject, runtime,
-    Module, Realm,
+    gas,
+    host::HostRuntime,
+    host_defined, journal,
+    kv::Transaction,
+    native::JsNativeObject,
+    realm::{resolve_specifier, ModuleBundle},
+    runtime, Module, Realm,
 };
+use jstz_crypto::hash::Blake2b;
 use tezos_smart_rollup::prelude::debug_msg;
 
 use crate::{
     api::{self, TraceData},
-    context::account::{Account, Address, Amount, ParsedCode},
+    context::{
+        account::{Account, Address, Amount, Modules, ParsedCode},
+        block::BlockInfo,
+    },
+    error,
     operation::OperationHash,
     request_logger::{log_request_end, log_request_start},
     Error, Result,
@@ -31,6 +42,10 @@ use crate::{
 
 use crate::js_logger::JsonLogger;
 
+/// Reported as the API exceeding the gas limit when the script's own
+/// instructions exceed it
+const SCRIPT_GAS_API: &str = "Script";
+
 pub mod headers {
 
     use super::*;
@@ -148,6 +163,12 @@ pub fn register_jstz_apis(
         },
         context,
     );
+    realm.register_api(
+        api::JstzApi {
+            address: address.clone(),
+        },
+        context,
+    );
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Deref, DerefMut, Trace, Finalize)]
@@ -182,10 +203,50 @@ impl Script {
         address: &Address,
         context: &mut Context<'_>,
     ) -> Result<Self> {
+        let Modules(modules) = Modules::get(hrt, tx, address)?;
         let src =
             Account::function_code(hrt, tx, address)?.ok_or(Error::InvalidAddress)?;
 
-        Ok(Self::parse(Source::from_bytes(&src), context)?)
+        let script = Self::parse(Source::from_bytes(&src), context)?;
+
+        // The modules are resolved by the module loader when the script is
+        // evaluated
+        {
+            let context = &mut script.realm().context_handle(context);
+            host_defined!(context, mut host_defined);
+
+            let sources = modules
+                .into_iter()
+                .map(|(path, code)| (path, code.into()))
+                .collect();
+            host_defined.insert(
+                ModuleBundle::new(sources).with_library_loader(Self::load_library_module),
+            );
+        }
+
+        Ok(script)
+    }
+
+    /// Loads the source of a module of the smart function at `address`, imported
+    /// as a library by another smart function
+    fn load_library_module(address: &str, path: &str) -> JsResult<String> {
+        let address = Address::from_base58(address).map_err(|_| {
+            JsNativeError::typ().with_message(format!("Invalid address: {address}"))
+        })?;
+
+        let source = runtime::with_js_hrt_and_tx(|hrt, tx| -> Result<Option<String>> {
+            if path.is_empty() {
+                return Ok(Account::function_code(hrt, tx, &address)?.cloned());
+            }
+            let Modules(mut modules) = Modules::get(hrt, tx, &address)?;
+            Ok(modules.remove(path).map(String::from))
+        })?;
+
+        source.ok_or_else(|| {
+            JsNativeError::error()
+                .with_message(format!("Cannot find module '{path}' of {address}"))
+                .into()
+        })
     }
 
     pub fn parse<R: Read>(
@@ -224,23 +285,82 @@ impl Script {
         self.realm().eval_module(self, context)
     }
 
-    /// Deploys a script
+    /// Address of the script deployed by `deployer` with `salt`. Unlike the
+    /// addresses of unsalted deployments, it does not depend on the deployer's
+    /// nonce and can thus be computed before the deployment.
+    pub fn salted_address(
+        deployer: &Address,
+        salt: &str,
+        code: &ParsedCode,
+        modules: &Modules,
+    ) -> Result<Address> {
+        let mut code = code.to_string();
+        // Deployments without modules keep the address they had before modules
+        // were introduced
+        for (path, module) in Self::normalize_modules(modules.clone())?.0 {
+            code.push_str(&format!("\0{}\0{}", path, module));
+        }
+        let code_hash = Blake2b::from(code.as_bytes());
+
+        // The `0xff` prefix separates salted addresses from unsalted ones,
+        // whose preimage starts with the (base58) deployer address
+        let mut preimage = vec![0xff];
+        preimage.extend_from_slice(deployer.to_base58().as_bytes());
+        preimage.extend_from_slice(code_hash.as_ref());
+        preimage.extend_from_slice(salt.as_bytes());
+
+        Ok(Address::digest(&preimage)?)
+    }
+
+    /// Normalizes the paths of `modules`, which must be relative to the code
+    /// and cannot escape it
+    fn normalize_modules(Modules(modules): Modules) -> Result<Modules> {
+        let modules = modules
+            .into_iter()
+            .map(|(path, code)| {
+                let normalized = resolve_specifier(None, &path).ok_or_else(|| {
+                    JsNativeError::typ()
+                        .with_message(format!("Invalid module path '{}'", path))
+                })?;
+                Ok((normalized, code))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Modules(modules))
+    }
+
+    /// Deploys a script, at the salted address if a `salt` is given
     pub fn deploy(
         hrt: &impl HostRuntime,
         tx: &mut Transaction,
         source: &Address,
         code: ParsedCode,
+        modules: Modules,
         balance: Amount,
+        admin: Option<Address>,
+        salt: Option<&str>,
     ) -> Result<Address> {
-        let nonce = Account::nonce(hrt, tx, source)?;
-
-        let address = Address::digest(format!("{}{}{}", source, code, nonce).as_bytes())?;
+        let address = match salt {
+            Some(salt) => Self::salted_address(source, salt, &code, &modules)?,
+            None => {
+                let nonce = Account::nonce(hrt, tx, source)?;
+                Address::digest(format!("{}{}{}", source, code, nonce).as_bytes())?
+            }
+        };
+        let modules = Self::normalize_modules(modules)?;
 
-        let account = Account::create(hrt, tx, &address, balance, Some(code));
+        let account = Account::create(hrt, tx, &address, balance, Some(code), admin);
         if account.is_ok() {
+            if !modules.0.is_empty() {
+                modules.set(tx, &address)?;
+            }
             debug_msg!(hrt, "[📜] Smart function deployed: {address}\n");
         } else if let Err(Error::InvalidAddress) = account {
             debug_msg!(hrt, "[📜] Smart function was already deployed: {address}\n");
+            // E.g. a salted deployment repeated with the same salt and code, or
+            // unsalted deployments of the same code by the same source at the
+            // same nonce (within a batch)
+            return Err(Error::AddressAlreadyInUse);
         } else {
             // Unreachable?
             debug_msg!(hrt, "[📜] Smart function deployment failed. \n");
@@ -262,6 +382,7 @@ impl Script {
 
         // 1. Begin a new transaction
         runtime::with_js_tx(|tx| tx.begin());
+        journal::begin();
 
         // 2. Initialize host defined data
 
@@ -290,26 +411,38 @@ impl Script {
         // 4. Ensure that the transaction is committed
         try_apply_to_value_or_promise(
             result,
-            |value, _context| {
+            |value, context| {
+                // A script that exceeded the gas limit may have caught the error,
+                // but its effects are rolled back regardless
+                let within_gas_limit = gas::check(context, SCRIPT_GAS_API);
+
                 runtime::with_js_hrt_and_tx(|hrt, tx| -> JsResult<()> {
                     let response = Response::try_from_js(value)?;
 
                     // If status code is 2xx, commit transaction
-                    if response.ok() {
+                    if response.ok() && within_gas_limit.is_ok() {
                         tx.commit(hrt)?;
+                        journal::commit();
                     } else {
                         tx.rollback()?;
+                        journal::rollback();
                     }
 
                     Ok(())
-                })
+                })?;
+
+                within_gas_limit
+            },
+            |_context| {
+                journal::rollback();
+                Ok(runtime::with_js_tx(|tx| tx.rollback())?)
             },
-            |_context| Ok(runtime::with_js_tx(|tx| tx.rollback())?),
             context,
         )
     }
 
-    /// Loads, initializes and runs the script
+    /// Loads, initializes and runs the script. Every call evaluates the script's
+    /// module in a fresh realm, so no module state outlives a call.
     pub fn load_init_run(
         address: Address,
         operation_hash: OperationHash,
@@ -317,7 +450,6 @@ impl Script {
         context: &mut Context<'_>,
     ) -> JsResult<JsValue> {
         // 1. Load script
-
         let script = runtime::with_js_hrt_and_tx(|hrt, tx| {
             Script::load(hrt, tx, &address, context)
         })?;
@@ -369,6 +501,28 @@ pub mod run {
         builder.body(body).map_err(|_| Error::InvalidHttpRequest)
     }
 
+    /// The error of a script that failed with `error`. Errors raised by host
+    /// APIs are reported as is. Thrown `Error` objects are converted to native
+    /// errors so that their name and message can be reported in the receipt,
+    /// along with their stack.
+    fn script_error(error: JsError, context: &mut Context<'_>) -> Error {
+        let stack = error
+            .as_opaque()
+            .and_then(JsValue::as_object)
+            .and_then(|error| error.get(js_string!("stack"), context).ok())
+            .and_then(|stack| stack.as_string().map(JsString::to_std_string_escaped));
+
+        let error = error.try_native(context).map_or(error, JsError::from);
+        if let Some(raised) = error.as_native().and_then(error::take_raised) {
+            return raised;
+        }
+
+        Error::ScriptError {
+            source: error,
+            stack,
+        }
+    }
+
     pub fn execute(
         hrt: &mut impl HostRuntime,
         tx: &mut Transaction,
@@ -386,6 +540,12 @@ pub mod run {
 
         // 1. Initialize runtime (with Web APIs to construct request)
         let rt = &mut jstz_core::Runtime::new(gas_limit)?;
+        // Scripts observe the time of the current block. It is set on every
+        // execution so that no time leaks from an earlier one.
+        match BlockInfo::get(hrt)? {
+            Some(block) => runtime::set_utc_now(block.timestamp),
+            None => runtime::reset_utc_now(),
+        }
         register_web_apis(&rt.realm().clone(), rt);
 
         // 2. Extract address from request
@@ -403,32 +563,47 @@ pub mod run {
         headers::test_and_set_referrer(&request.deref(), source)?;
 
         // 5. Run :)
-        let result: JsValue = {
-            let rt = &mut *rt;
-            runtime::enter_js_host_context(hrt, tx, || {
-                jstz_core::future::block_on(async move {
-                    let result = Script::load_init_run(
-                        address,
-                        operation_hash,
-                        request.inner(),
-                        rt,
-                    )?;
-
-                    rt.resolve_value(&result).await
+        let ((result, host_gas), journal) = journal::enter_journal_context(|| {
+            gas::enter_gas_context(gas_limit, || {
+                let rt = &mut *rt;
+                runtime::enter_js_host_context(hrt, tx, || {
+                    jstz_core::future::block_on(async move {
+                        let result = Script::load_init_run(
+                            address,
+                            operation_hash,
+                            request.inner(),
+                            rt,
+                        )?;
+
+                        rt.resolve_value(&result).await
+                    })
                 })
             })
+        });
+
+        // Gas used by the script's instructions and by the host APIs it called
+        let gas_used = (gas_limit - rt.instructions_remaining()) + host_gas.used;
+
+        // Host APIs fail once the gas limit is exceeded, but the script may have
+        // caught the error, so the gas limit is checked regardless of the result
+        if let Some(api) = host_gas.exhausted_by {
+            return Err(Error::GasLimitExceeded {
+                api: api.to_string(),
+            });
+        }
+        if gas_used > gas_limit || (result.is_err() && rt.instructions_remaining() == 0) {
+            return Err(Error::GasLimitExceeded {
+                api: SCRIPT_GAS_API.to_string(),
+            });
         }
-        .map_err(|err| {
-            if rt.instructions_remaining() == 0 {
-                Error::GasLimitExceeded
-            } else {
-                err.into()
-            }
-        })?;
+
+        let result: JsValue = result.map_err(|err| script_error(err, rt))?;
 
         debug_msg!(
             hrt,
-            "🚀 Smart function executed successfully with value: {:?} (in {:?} instructions)\n", result, gas_limit - rt.instructions_remaining()
+            "🚀 Smart function executed successfully with value: {:?} (using {:?} gas)\n",
+            result,
+            gas_used
         );
 
         // 6. Serialize response
@@ -440,13 +615,27 @@ pub mod run {
             body,
             status_code: http_parts.status,
             headers: http_parts.headers,
+            gas_used,
+            calls: journal
+                .calls
+                .iter()
+                .map(|address| Address::from_base58(address))
+                .collect::<std::result::Result<_, _>>()?,
+            kv_writes: journal
+                .kv_writes
+                .into_iter()
+                .map(receipt::KvWrite::try_from)
+                .collect::<Result<_>>()?,
+            transfers: journal.records::<receipt::Transfer>().cloned().collect(),
+            approvals: journal.records::<receipt::Approval>().cloned().collect(),
+            events: journal.records::<receipt::Event>().cloned().collect(),
         })
     }
 }
 
 pub mod deploy {
     use super::*;
-    use crate::{operation, receipt};
+    use crate::{operation, receipt, reveal};
 
     pub fn execute(
         hrt: &impl HostRuntime,
@@ -457,10 +646,82 @@ pub mod deploy {
         let operation::DeployFunction {
             function_code,
             account_credit,
+            admin,
+            salt,
+            modules,
         } = deployment;
 
-        let address = Script::deploy(hrt, tx, source, function_code, account_credit)?;
+        let address = Script::deploy(
+            hrt,
+            tx,
+            source,
+            function_code,
+            modules,
+            account_credit,
+            admin,
+            salt.as_deref(),
+        )?;
 
         Ok(receipt::DeployFunction { address })
     }
+
+    /// Deploys a smart function whose code is revealed from preimages
+    pub fn execute_reveal(
+        hrt: &mut impl HostRuntime,
+        tx: &mut Transaction,
+        source: &Address,
+        deployment: operation::RevealDeployFunction,
+    ) -> Result<receipt::DeployFunction> {
+        let operation::RevealDeployFunction {
+            root_hash,
+            account_credit,
+            admin,
+            salt,
+        } = deployment;
+
+        let code = reveal::reveal(hrt, &root_hash)?;
+        let function_code = String::from_utf8(code)
+            .map_err(|_| Error::InvalidPreimage)?
+            .try_into()?;
+
+        execute(
+            hrt,
+            tx,
+            source,
+            operation::DeployFunction {
+                function_code,
+                account_credit,
+                admin,
+                salt,
+                modules: Modules::default(),
+            },
+        )
+    }
+}
+
+pub mod upgrade {
+    use super::*;
+    use crate::{operation, receipt};
+
+    pub fn execute(
+        hrt: &impl HostRuntime,
+        tx: &mut Transaction,
+        source: &Address,
+        upgrade: operation::UpgradeFunction,
+    ) -> Result<receipt::UpgradeFunction> {
+        let operation::UpgradeFunction {
+            address,
+            function_code,
+        } = upgrade;
+
+        let version =
+            Account::upgrade_function_code(hrt, tx, &address, source, function_code)?;
+
+        debug_msg!(
+            hrt,
+            "[📜] Smart function upgraded: {address} (version {version})\n"
+        );
+
+        Ok(receipt::UpgradeFunction { version })
+    }
 }
@@ -0,0 +1,28 @@
+use jstz_core::{host::HostRuntime, kv::Transaction};
+
+use crate::{
+    context::account::{Account, Address},
+    operation::Transfer,
+    receipt, Result,
+};
+
+pub fn execute(
+    hrt: &impl HostRuntime,
+    tx: &mut Transaction,
+    source: &Address,
+    transfer: Transfer,
+) -> Result<receipt::Transfer> {
+    let Transfer {
+        destination,
+        amount,
+    } = transfer;
+
+    Account::transfer(hrt, tx, source, &destination, amount)?;
+
+    Ok(receipt::Transfer {
+        from: source.clone(),
+        to: destination,
+        amount,
+        asset: None,
+    })
+}
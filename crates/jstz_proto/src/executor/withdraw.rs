@@ -0,0 +1,29 @@
+use jstz_core::{host::HostRuntime, kv::Transaction};
+
+use crate::{
+    context::{
+        account::{Account, Address},
+        outbox::{Outbox, Withdrawal},
+    },
+    operation::Withdraw,
+    Error, Result,
+};
+
+pub fn execute(
+    hrt: &impl HostRuntime,
+    tx: &mut Transaction,
+    source: &Address,
+    withdrawal: Withdraw,
+) -> Result<()> {
+    let Withdraw { receiver, amount } = withdrawal;
+
+    // Tickets of amount 0 cannot be created on L1, and smart functions have
+    // no L1 counterpart to receive them
+    if amount == 0 || receiver.is_smart_function() {
+        return Err(Error::InvalidWithdrawal);
+    }
+
+    Account::withdraw(hrt, tx, source, amount)?;
+
+    Outbox::push_withdrawal(hrt, tx, Withdrawal { receiver, amount })
+}
@@ -2,10 +2,12 @@ pub mod api;
 mod error;
 
 pub mod context;
+pub mod event_logger;
 pub mod executor;
 pub mod js_logger;
 pub mod operation;
 pub mod receipt;
 pub mod request_logger;
+pub mod reveal;
 
 pub use error::{Error, Result};
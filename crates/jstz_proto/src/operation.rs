@@ -1,11 +1,20 @@
-use http::{HeaderMap, Method, Uri};
+use http::{HeaderMap, HeaderName, Method, Uri};
 use jstz_api::http::body::HttpBody;
-use jstz_core::{host::HostRuntime, kv::Transaction};
+use jstz_core::{
+    host::HostRuntime,
+    kv::{Storage, Transaction},
+};
 use jstz_crypto::{hash::Blake2b, public_key::PublicKey, signature::Signature};
 use serde::{Deserialize, Serialize};
+use tezos_crypto_rs::hash::SmartRollupHash;
 
 use crate::{
-    context::account::{Account, Address, Amount, Nonce, ParsedCode},
+    context::{
+        account::{Account, Address, Amount, Modules, Nonce, ParsedCode},
+        asset::Asset,
+        block::BlockInfo,
+    },
+    reveal::{RootHash, MAX_REVEALED_SIZE},
     Error, Result,
 };
 
@@ -14,6 +23,13 @@ pub struct Operation {
     pub source: Address,
     pub nonce: Nonce,
     pub content: Content,
+    /// Address of the rollup the operation is meant for. Binding the signed
+    /// payload to the rollup prevents replaying the operation on another rollup.
+    #[serde(with = "rollup_address")]
+    pub rollup_address: SmartRollupHash,
+    /// Last level at which the operation can be included. If unset, the
+    /// operation never expires.
+    pub valid_until_level: Option<u32>,
 }
 
 pub type OperationHash = Blake2b;
@@ -46,43 +62,311 @@ impl Operation {
         }
     }
 
+    /// Verify that the operation targets the rollup `rt` is running in
+    pub fn verify_rollup_address(&self, rt: &impl HostRuntime) -> Result<()> {
+        if self.rollup_address == rt.reveal_metadata().address() {
+            Ok(())
+        } else {
+            Err(Error::RollupAddressMismatch)
+        }
+    }
+
+    /// Verify that the operation has not expired at the current level
+    pub fn verify_expiry(&self, rt: &impl HostRuntime) -> Result<()> {
+        let Some(valid_until_level) = self.valid_until_level else {
+            return Ok(());
+        };
+
+        let level = BlockInfo::get(rt)?.map_or(0, |block| block.level);
+        if level <= valid_until_level {
+            Ok(())
+        } else {
+            Err(Error::OperationExpired)
+        }
+    }
+
     /// Computes the operation hash.
     /// This is the hash which the client should sign
     pub fn hash(&self) -> OperationHash {
+        Blake2b::from(&self.encode())
+    }
+
+    /// Canonical binary encoding of the operation, used for hashing and signing.
+    ///
+    /// The layout is independent of any serialisation library so that clients
+    /// written in other languages can reproduce it:
+    ///  - a version byte ([`OPERATION_ENCODING_VERSION`])
+    ///  - the rollup address and the optional expiry level (a big-endian `u32`)
+    ///  - integers are big-endian `u64`s
+    ///  - strings and byte sequences are prefixed by their length as a big-endian `u32`
+    ///  - enums (addresses, contents, optional values) are prefixed by a tag byte
+    ///  - headers are encoded as a length-prefixed list of `(name, value)` pairs,
+    ///    sorted by name, with the values of a repeated name in their order
+    ///  - batches are encoded as a length-prefixed list of their steps
+    ///  - modules are encoded as a length-prefixed list of `(path, code)` pairs,
+    ///    sorted by path
+    pub fn encode(&self) -> Vec<u8> {
         let Operation {
             source,
             nonce,
             content,
+            rollup_address,
+            valid_until_level,
         } = self;
+
+        let mut encoder = Encoder::default();
+        encoder.u8(OPERATION_ENCODING_VERSION);
+        encoder.bytes(&rollup_address.0);
+        match valid_until_level {
+            None => encoder.u8(0),
+            Some(level) => {
+                encoder.u8(1);
+                encoder.u32(*level);
+            }
+        }
+        encoder.address(source);
+        encoder.u64(nonce.value());
+        encoder.content(content);
+
+        encoder.finish()
+    }
+}
+
+/// (De)serialises rollup addresses as base58 strings (`sr1...`)
+mod rollup_address {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use tezos_crypto_rs::hash::SmartRollupHash;
+
+    pub fn serialize<S: Serializer>(
+        address: &SmartRollupHash,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&address.to_base58_check())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<SmartRollupHash, D::Error> {
+        let address = String::deserialize(deserializer)?;
+        SmartRollupHash::from_base58_check(&address).map_err(D::Error::custom)
+    }
+}
+
+/// Version of the canonical operation encoding. Must be bumped whenever
+/// the layout of [`Operation::encode`] changes.
+pub const OPERATION_ENCODING_VERSION: u8 = 4;
+
+#[derive(Default)]
+struct Encoder(Vec<u8>);
+
+impl Encoder {
+    fn u8(&mut self, value: u8) {
+        self.0.push(value)
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.0.extend_from_slice(&value.to_be_bytes())
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.0.extend_from_slice(&value.to_be_bytes())
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.u32(bytes.len() as u32);
+        self.0.extend_from_slice(bytes)
+    }
+
+    fn address(&mut self, address: &Address) {
+        match address {
+            Address::Tz1(_) => self.u8(0),
+            Address::Tz2(_) => self.u8(1),
+            Address::Tz3(_) => self.u8(2),
+            Address::Kt1(_) => self.u8(3),
+        }
+        self.bytes(address.as_bytes())
+    }
+
+    fn headers(&mut self, headers: &HeaderMap) {
+        // The order of distinct names is not significant, but the order of the
+        // values of a repeated name is
+        let mut names: Vec<&HeaderName> = headers.keys().collect();
+        names.sort_by_key(|name| name.as_str());
+
+        self.u32(headers.len() as u32);
+        for name in names {
+            for value in headers.get_all(name) {
+                self.bytes(name.as_str().as_bytes());
+                self.bytes(value.as_bytes());
+            }
+        }
+    }
+
+    fn modules(&mut self, Modules(modules): &Modules) {
+        self.u32(modules.len() as u32);
+        for (path, code) in modules {
+            self.bytes(path.as_bytes());
+            self.bytes(code.to_string().as_bytes());
+        }
+    }
+
+    fn content(&mut self, content: &Content) {
         match content {
             Content::DeployFunction(DeployFunction {
                 function_code,
                 account_credit,
-            }) => Blake2b::from(
-                format!("{}{}{}{}", source, nonce, function_code, account_credit)
-                    .as_bytes(),
-            ),
+                admin,
+                salt,
+                modules,
+            }) => {
+                self.u8(0);
+                self.bytes(function_code.to_string().as_bytes());
+                self.u64(*account_credit);
+                match admin {
+                    None => self.u8(0),
+                    Some(admin) => {
+                        self.u8(1);
+                        self.address(admin);
+                    }
+                }
+                match salt {
+                    None => self.u8(0),
+                    Some(salt) => {
+                        self.u8(1);
+                        self.bytes(salt.as_bytes());
+                    }
+                }
+                self.modules(modules);
+            }
             Content::RunFunction(RunFunction {
                 uri,
                 method,
                 headers,
                 body,
-                ..
-            }) => Blake2b::from(
-                format!(
-                    "{}{}{}{}{:?}{:?}",
-                    source, nonce, uri, method, headers, body
-                )
-                .as_bytes(),
-            ),
+                gas_limit,
+            }) => {
+                self.u8(1);
+                self.bytes(uri.to_string().as_bytes());
+                self.bytes(method.as_str().as_bytes());
+                self.headers(headers);
+                match body {
+                    None => self.u8(0),
+                    Some(body) => {
+                        self.u8(1);
+                        self.bytes(body);
+                    }
+                }
+                self.u64(*gas_limit as u64);
+            }
+            Content::Withdraw(Withdraw { receiver, amount }) => {
+                self.u8(2);
+                self.address(receiver);
+                self.u64(*amount);
+            }
+            Content::UpgradeFunction(UpgradeFunction {
+                address,
+                function_code,
+            }) => {
+                self.u8(3);
+                self.address(address);
+                self.bytes(function_code.to_string().as_bytes());
+            }
+            Content::Transfer(Transfer {
+                destination,
+                amount,
+            }) => {
+                self.u8(5);
+                self.address(destination);
+                self.u64(*amount);
+            }
+            Content::Approve(Approve {
+                spender,
+                amount,
+                asset,
+            }) => {
+                self.u8(7);
+                self.address(spender);
+                self.u64(*amount);
+                match asset {
+                    None => self.u8(0),
+                    Some(asset) => {
+                        self.u8(1);
+                        self.bytes(asset.to_string().as_bytes());
+                    }
+                }
+            }
+            Content::Batch(steps) => {
+                self.u8(4);
+                self.u32(steps.len() as u32);
+                for step in steps {
+                    self.content(step);
+                }
+            }
+            Content::RevealDeployFunction(RevealDeployFunction {
+                root_hash,
+                account_credit,
+                admin,
+                salt,
+            }) => {
+                self.u8(6);
+                self.bytes(root_hash.to_string().as_bytes());
+                self.u64(*account_credit);
+                match admin {
+                    None => self.u8(0),
+                    Some(admin) => {
+                        self.u8(1);
+                        self.address(admin);
+                    }
+                }
+                match salt {
+                    None => self.u8(0),
+                    Some(salt) => {
+                        self.u8(1);
+                        self.bytes(salt.as_bytes());
+                    }
+                }
+            }
         }
     }
+
+    fn finish(self) -> Vec<u8> {
+        self.0
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
 pub struct DeployFunction {
     pub function_code: ParsedCode,
     pub account_credit: Amount,
+    /// Address allowed to upgrade the smart function. If unset, the code is immutable.
+    pub admin: Option<Address>,
+    /// If set, the smart function is deployed at an address derived from the
+    /// source, the salt and the code (see [`Script::salted_address`]) instead
+    /// of the source's nonce.
+    ///
+    /// [`Script::salted_address`]: crate::executor::smart_function::Script::salted_address
+    pub salt: Option<String>,
+    /// Modules the code can import with relative specifiers
+    #[serde(default)]
+    pub modules: Modules,
+}
+
+/// A deployment of a smart function whose code is too large for an external
+/// message. The code is revealed from preimages (see [`crate::reveal`]).
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct RevealDeployFunction {
+    /// Hash of the root preimage of the code
+    pub root_hash: RootHash,
+    pub account_credit: Amount,
+    pub admin: Option<Address>,
+    pub salt: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct UpgradeFunction {
+    /// Address of the smart function to upgrade
+    pub address: Address,
+    pub function_code: ParsedCode,
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
@@ -97,10 +381,73 @@ pub struct RunFunction {
     pub gas_limit: usize,
 }
 
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct Withdraw {
+    /// L1 address receiving the withdrawn tickets
+    pub receiver: Address,
+    pub amount: Amount,
+}
+
+/// A transfer of the native asset from the source to another account
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct Transfer {
+    pub destination: Address,
+    pub amount: Amount,
+}
+
+/// An allowance for `spender` to transfer up to `amount` of `asset` from the
+/// balance of the source (see `Ledger.approve`), replacing any previous
+/// allowance
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct Approve {
+    pub spender: Address,
+    pub amount: Amount,
+    /// The asset of the allowance, or `None` for the native asset
+    pub asset: Option<Asset>,
+}
+
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
 pub enum Content {
     DeployFunction(DeployFunction),
     RunFunction(RunFunction),
+    Withdraw(Withdraw),
+    UpgradeFunction(UpgradeFunction),
+    Transfer(Transfer),
+    RevealDeployFunction(RevealDeployFunction),
+    Approve(Approve),
+    /// Steps executed atomically in order: if any step fails, the effects of
+    /// all steps are rolled back
+    Batch(Vec<Content>),
+}
+
+impl Content {
+    /// Gas limit of the content. Only smart function calls are metered.
+    pub fn gas_limit(&self) -> usize {
+        match self {
+            Content::RunFunction(RunFunction { gas_limit, .. }) => *gas_limit,
+            Content::Batch(steps) => steps.iter().map(Content::gas_limit).sum(),
+            Content::DeployFunction(_)
+            | Content::Withdraw(_)
+            | Content::UpgradeFunction(_)
+            | Content::Transfer(_)
+            | Content::RevealDeployFunction(_)
+            | Content::Approve(_) => 0,
+        }
+    }
+
+    /// Maximum number of bytes revealed from preimages by the content
+    pub fn reveal_limit(&self) -> usize {
+        match self {
+            Content::RevealDeployFunction(_) => MAX_REVEALED_SIZE,
+            Content::Batch(steps) => steps.iter().map(Content::reveal_limit).sum(),
+            Content::DeployFunction(_)
+            | Content::RunFunction(_)
+            | Content::Withdraw(_)
+            | Content::UpgradeFunction(_)
+            | Content::Transfer(_)
+            | Content::Approve(_) => 0,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
@@ -123,7 +470,29 @@ impl SignedOperation {
         self.inner.hash()
     }
 
+    /// Nonce of the (unverified) operation
+    pub fn nonce(&self) -> Nonce {
+        self.inner.nonce
+    }
+
+    /// Total gas limit of the (unverified) operation's content
+    pub fn gas_limit(&self) -> usize {
+        self.inner.content.gas_limit()
+    }
+
+    /// Maximum number of bytes revealed by the (unverified) operation's content
+    pub fn reveal_limit(&self) -> usize {
+        self.inner.content.reveal_limit()
+    }
+
+    /// Verifies that the operation was signed by its source
     pub fn verify(self) -> Result<Operation> {
+        // The public key must belong to the source, otherwise anyone could
+        // sign an operation on behalf of any account
+        if Address::try_from(&self.public_key)? != self.inner.source {
+            return Err(Error::SourceMismatch);
+        }
+
         // FIXME: Adding signature verification kills to the rollup???!??!?!?!
         let hash = self.inner.hash();
         self.signature.verify(&self.public_key, hash.as_ref())?;
@@ -132,6 +501,95 @@ impl SignedOperation {
     }
 }
 
+/// Maximum number of chunks of an operation, such that the hashes of the
+/// chunks fit in the header of the operation
+pub const MAX_CHUNKS: usize = 100;
+
+/// Announces a signed operation too large for a single inbox message, sent in
+/// chunks after the header. The header carries the signature of the operation,
+/// so that only an existing account can start a reassembly, for its next
+/// operation, and the hashes of the chunks, so that each chunk is checked as it
+/// arrives.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct ChunkedOperationHeader {
+    pub public_key: PublicKey,
+    /// Signature of the operation's hash
+    pub signature: Signature,
+    pub hash: OperationHash,
+    /// Nonce of the operation, which must be the next nonce of the signer
+    pub nonce: Nonce,
+    /// Hashes of the chunks, in order
+    pub chunk_hashes: Vec<Blake2b>,
+}
+
+impl ChunkedOperationHeader {
+    /// The header of `operation`, serialized and split into `chunks`
+    pub fn new(operation: &SignedOperation, chunks: &[&[u8]]) -> Self {
+        Self {
+            public_key: operation.public_key.clone(),
+            signature: operation.signature.clone(),
+            hash: operation.hash(),
+            nonce: operation.inner.nonce,
+            chunk_hashes: chunks.iter().map(|chunk| Blake2b::from(*chunk)).collect(),
+        }
+    }
+
+    /// Identifies the reassembly started by the header. Headers of the same
+    /// operation with a different signer or chunks are reassembled separately,
+    /// so that a forged header cannot hold back the genuine chunks.
+    pub fn id(&self) -> Blake2b {
+        let mut bytes = self.public_key.to_base58().into_bytes();
+        bytes.extend_from_slice(self.hash.as_ref());
+        bytes.extend_from_slice(&self.nonce.value().to_be_bytes());
+        for chunk_hash in &self.chunk_hashes {
+            bytes.extend_from_slice(chunk_hash.as_ref());
+        }
+        Blake2b::from(&bytes)
+    }
+
+    /// Verifies that the operation was signed by the header's public key, the
+    /// key of an existing account whose next nonce is the operation's. Returns
+    /// the address of the signer.
+    pub fn verify(&self, hrt: &impl HostRuntime) -> Result<Address> {
+        let Self {
+            public_key,
+            signature,
+            hash,
+            nonce,
+            ..
+        } = self;
+        signature.verify(public_key, hash.as_ref())?;
+
+        let signer = Address::try_from(public_key)?;
+        let account: Account =
+            Storage::get(hrt, &Account::path(&signer)?)?.ok_or(Error::InvalidAddress)?;
+        if account.nonce != *nonce {
+            return Err(Error::InvalidNonce);
+        }
+
+        Ok(signer)
+    }
+}
+
+/// A part of a chunked operation. The chunks of an operation may arrive in any
+/// order after its header, over several levels.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct OperationChunk {
+    /// Id of the header of the chunked operation
+    pub header: Blake2b,
+    pub index: u32,
+    /// Bytes of the serialized signed operation
+    pub bytes: Vec<u8>,
+}
+
+/// The contents of an external inbox message
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ExternalMessageContent {
+    Operation(SignedOperation),
+    ChunkedOperation(ChunkedOperationHeader),
+    Chunk(OperationChunk),
+}
+
 pub mod external {
     use super::*;
 
@@ -140,9 +598,113 @@ pub mod external {
         pub amount: Amount,
         pub reciever: Address,
     }
+
+    /// A deposit of tickets from an allowlisted ticketer, other than the
+    /// native asset
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+    pub struct AssetDeposit {
+        pub asset: Asset,
+        pub amount: Amount,
+        pub receiver: Address,
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub enum ExternalOperation {
     Deposit(external::Deposit),
+    AssetDeposit(external::AssetDeposit),
+}
+
+#[cfg(test)]
+mod test {
+    use http::{HeaderName, HeaderValue};
+
+    use super::*;
+
+    fn run_function(headers: HeaderMap, gas_limit: usize) -> Operation {
+        Operation {
+            source: Address::from_base58("tz1XQjK1b3P72kMcHsoPhnAg3dvX1n8Ainty")
+                .expect("Could not parse pkh"),
+            nonce: Nonce::default(),
+            content: Content::RunFunction(RunFunction {
+                uri: Uri::from_static("tezos://tz1XQjK1b3P72kMcHsoPhnAg3dvX1n8Ainty/"),
+                method: Method::POST,
+                headers,
+                body: Some(b"hello".to_vec()),
+                gas_limit,
+            }),
+            rollup_address: SmartRollupHash(vec![0; 20]),
+            valid_until_level: None,
+        }
+    }
+
+    fn headers(entries: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in entries {
+            headers.append(
+                HeaderName::from_static(name),
+                HeaderValue::from_static(value),
+            );
+        }
+        headers
+    }
+
+    #[test]
+    fn hash_covers_gas_limit() {
+        let op1 = run_function(HeaderMap::new(), 1000);
+        let op2 = run_function(HeaderMap::new(), 1001);
+
+        assert_ne!(op1.hash(), op2.hash());
+    }
+
+    #[test]
+    fn hash_covers_rollup_address_and_expiry() {
+        let op = run_function(HeaderMap::new(), 1000);
+        let foreign = Operation {
+            rollup_address: SmartRollupHash(vec![1; 20]),
+            ..run_function(HeaderMap::new(), 1000)
+        };
+        let expiring = Operation {
+            valid_until_level: Some(42),
+            ..run_function(HeaderMap::new(), 1000)
+        };
+
+        assert_ne!(op.hash(), foreign.hash());
+        assert_ne!(op.hash(), expiring.hash());
+    }
+
+    #[test]
+    fn rollup_address_is_serialized_as_base58() {
+        let op = run_function(HeaderMap::new(), 1000);
+
+        let json = serde_json::to_value(&op).unwrap();
+        assert_eq!(
+            json["rollup_address"],
+            serde_json::json!(op.rollup_address.to_base58_check())
+        );
+        assert_eq!(serde_json::from_value::<Operation>(json).unwrap(), op);
+    }
+
+    #[test]
+    fn hash_is_independent_of_header_name_order() {
+        let op1 = run_function(headers(&[("a", "1"), ("b", "2"), ("a", "3")]), 1000);
+        let op2 = run_function(headers(&[("b", "2"), ("a", "1"), ("a", "3")]), 1000);
+
+        assert_eq!(op1.hash(), op2.hash());
+    }
+
+    #[test]
+    fn hash_covers_order_of_repeated_header_values() {
+        let op1 = run_function(headers(&[("a", "1"), ("a", "3")]), 1000);
+        let op2 = run_function(headers(&[("a", "3"), ("a", "1")]), 1000);
+
+        assert_ne!(op1.hash(), op2.hash());
+    }
+
+    #[test]
+    fn encoding_is_versioned() {
+        let op = run_function(HeaderMap::new(), 1000);
+
+        assert_eq!(op.encode()[0], OPERATION_ENCODING_VERSION);
+    }
 }
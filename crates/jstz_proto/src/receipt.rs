@@ -1,10 +1,169 @@
+use boa_engine::JsError;
+use derive_more::Display;
 use http::{HeaderMap, StatusCode};
 use jstz_api::http::body::HttpBody;
+use jstz_core::journal;
 use serde::{Deserialize, Serialize};
 
-use crate::{context::account::Address, operation::OperationHash, Result};
+use crate::{
+    context::{
+        account::{Address, Amount},
+        asset::Asset,
+    },
+    operation::OperationHash,
+    Error, Result,
+};
 
-pub type ReceiptResult<T> = std::result::Result<T, String>;
+pub type ReceiptResult<T> = std::result::Result<T, ReceiptError>;
+
+/// A serializable mirror of [`Error`], reported in the receipt of a failed
+/// operation
+#[derive(Display, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReceiptError {
+    #[display(fmt = "KvError: {}", description)]
+    KvError {
+        description: String,
+    },
+    #[display(fmt = "HostError: {}", description)]
+    HostError {
+        description: String,
+    },
+    #[display(fmt = "PathError: {}", description)]
+    PathError {
+        description: String,
+    },
+    /// An exception thrown by a script
+    #[display(fmt = "{}: {}", name, message)]
+    JsError {
+        /// Name of the exception, e.g. `TypeError`
+        name: String,
+        message: String,
+        /// Stack trace, if the thrown `Error` has a `stack` property
+        stack: Option<String>,
+    },
+    #[display(fmt = "SerializationError: {}", description)]
+    SerializationError {
+        description: String,
+    },
+    #[display(fmt = "CryptoError: {}", description)]
+    CryptoError {
+        description: String,
+    },
+    BalanceOverflow,
+    InvalidNonce,
+    InvalidAddress,
+    RefererShouldNotBeSet,
+    #[display(fmt = "GasLimitExceeded: {}", api)]
+    GasLimitExceeded {
+        api: String,
+    },
+    InvalidHttpRequest,
+    TicketerNotSet,
+    InvalidWithdrawal,
+    SourceMismatch,
+    UnauthorizedUpgrade,
+    SchedulerLevelFull,
+    InvalidAsset,
+    OperationExpired,
+    RollupAddressMismatch,
+    InsufficientAllowance,
+    InvalidPreimage,
+    #[display(fmt = "BatchStepFailed: step {}: {}", step, error)]
+    BatchStepFailed {
+        step: usize,
+        error: Box<ReceiptError>,
+    },
+    InsufficientFunds,
+    AddressAlreadyInUse,
+    #[display(fmt = "RunFunctionFailed: status {}", status_code)]
+    RunFunctionFailed {
+        status_code: u16,
+    },
+    SchedulerQuotaExceeded,
+}
+
+impl From<JsError> for ReceiptError {
+    fn from(error: JsError) -> Self {
+        match error.as_native() {
+            Some(native) => ReceiptError::JsError {
+                name: native.kind.to_string(),
+                message: native.message().to_string(),
+                stack: None,
+            },
+            // A thrown value that isn't an `Error` object
+            None => ReceiptError::JsError {
+                name: "Error".to_string(),
+                message: error.to_string(),
+                stack: None,
+            },
+        }
+    }
+}
+
+impl From<jstz_core::Error> for ReceiptError {
+    fn from(error: jstz_core::Error) -> Self {
+        match error {
+            jstz_core::Error::KvError { source } => ReceiptError::KvError {
+                description: source.to_string(),
+            },
+            jstz_core::Error::HostError { source } => ReceiptError::HostError {
+                description: source.to_string(),
+            },
+            jstz_core::Error::PathError { source } => ReceiptError::PathError {
+                description: source.to_string(),
+            },
+            jstz_core::Error::JsError { source } => source.into(),
+            jstz_core::Error::SerializationError { description } => {
+                ReceiptError::SerializationError { description }
+            }
+        }
+    }
+}
+
+impl From<Error> for ReceiptError {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::CoreError { source } => source.into(),
+            Error::ScriptError { source, stack } => match source.into() {
+                ReceiptError::JsError { name, message, .. } => ReceiptError::JsError {
+                    name,
+                    message,
+                    stack,
+                },
+                error => error,
+            },
+            Error::CryptoError { source } => ReceiptError::CryptoError {
+                description: source.to_string(),
+            },
+            Error::BalanceOverflow => ReceiptError::BalanceOverflow,
+            Error::InvalidNonce => ReceiptError::InvalidNonce,
+            Error::InvalidAddress => ReceiptError::InvalidAddress,
+            Error::RefererShouldNotBeSet => ReceiptError::RefererShouldNotBeSet,
+            Error::GasLimitExceeded { api } => ReceiptError::GasLimitExceeded { api },
+            Error::InvalidHttpRequest => ReceiptError::InvalidHttpRequest,
+            Error::TicketerNotSet => ReceiptError::TicketerNotSet,
+            Error::InvalidWithdrawal => ReceiptError::InvalidWithdrawal,
+            Error::SourceMismatch => ReceiptError::SourceMismatch,
+            Error::UnauthorizedUpgrade => ReceiptError::UnauthorizedUpgrade,
+            Error::SchedulerLevelFull => ReceiptError::SchedulerLevelFull,
+            Error::InvalidAsset => ReceiptError::InvalidAsset,
+            Error::OperationExpired => ReceiptError::OperationExpired,
+            Error::RollupAddressMismatch => ReceiptError::RollupAddressMismatch,
+            Error::InsufficientAllowance => ReceiptError::InsufficientAllowance,
+            Error::InvalidPreimage => ReceiptError::InvalidPreimage,
+            Error::BatchStepFailed { step, source } => ReceiptError::BatchStepFailed {
+                step,
+                error: Box::new((*source).into()),
+            },
+            Error::InsufficientFunds => ReceiptError::InsufficientFunds,
+            Error::AddressAlreadyInUse => ReceiptError::AddressAlreadyInUse,
+            Error::RunFunctionFailed { status_code } => {
+                ReceiptError::RunFunctionFailed { status_code }
+            }
+            Error::SchedulerQuotaExceeded => ReceiptError::SchedulerQuotaExceeded,
+        }
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Receipt {
@@ -14,7 +173,7 @@ pub struct Receipt {
 
 impl Receipt {
     pub fn new(hash: OperationHash, inner: Result<Content>) -> Self {
-        let inner = inner.map_err(|e| e.to_string());
+        let inner = inner.map_err(ReceiptError::from);
         Self { hash, inner }
     }
 
@@ -28,6 +187,12 @@ pub struct DeployFunction {
     pub address: Address,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpgradeFunction {
+    /// Version number of the new code
+    pub version: usize,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RunFunction {
     pub body: HttpBody,
@@ -35,10 +200,75 @@ pub struct RunFunction {
     pub status_code: StatusCode,
     #[serde(with = "http_serde::header_map")]
     pub headers: HeaderMap,
+    /// Gas consumed by the script and the host APIs it called
+    pub gas_used: usize,
+    /// Addresses of the smart functions called by the script, in call order
+    pub calls: Vec<Address>,
+    /// KV keys written (set or deleted) by the committed calls
+    pub kv_writes: Vec<KvWrite>,
+    /// Ledger transfers performed by the committed calls
+    pub transfers: Vec<Transfer>,
+    /// Allowances set by the committed calls, including the allowances
+    /// spent by `Ledger.transferFrom`
+    pub approvals: Vec<Approval>,
+    /// Events emitted by the committed calls, in emission order
+    pub events: Vec<Event>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KvWrite {
+    /// Address of the smart function owning the KV store
+    pub address: Address,
+    pub key: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Transfer {
+    pub from: Address,
+    pub to: Address,
+    pub amount: Amount,
+    /// The transferred asset, or `None` for the native asset
+    pub asset: Option<Asset>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Approval {
+    pub owner: Address,
+    pub spender: Address,
+    /// The new allowance of `spender`
+    pub amount: Amount,
+    /// The asset of the allowance, or `None` for the native asset
+    pub asset: Option<Asset>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Event {
+    /// Address of the smart function emitting the event
+    pub address: Address,
+    pub topic: String,
+    /// JSON-encoded payload of the event
+    pub payload: String,
+}
+
+impl TryFrom<journal::KvWrite> for KvWrite {
+    type Error = Error;
+
+    fn try_from(kv_write: journal::KvWrite) -> Result<Self> {
+        Ok(Self {
+            address: Address::from_base58(&kv_write.address)?,
+            key: kv_write.key,
+        })
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Content {
     DeployFunction(DeployFunction),
     RunFunction(RunFunction),
+    UpgradeFunction(UpgradeFunction),
+    Withdraw,
+    Transfer(Transfer),
+    Approve(Approval),
+    /// Receipts of each step of a batch, in order
+    Batch(Vec<Content>),
 }
@@ -0,0 +1,199 @@
+//! Payloads revealed from preimages
+//!
+//! Payloads too large for an external message (e.g. the code of bundled smart
+//! functions) are split into pages forming a Merkle tree, in the DAC format.
+//! The pages are written to the preimages directory of the rollup node and
+//! the kernel reveals the payload from the hash of the root page.
+
+use std::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+use jstz_core::host::HostRuntime;
+use serde::{Deserialize, Serialize};
+use tezos_smart_rollup::dac::{
+    pages::{prepare_preimages, reveal_loop, V0SliceContentPage, MAX_PAGE_SIZE},
+    PreimageHash,
+};
+
+use crate::{Error, Result};
+
+/// Size of a preimage hash (a tag byte followed by a Blake2b hash)
+const PREIMAGE_HASH_SIZE: usize = 33;
+
+/// Size of the prefix of a page (a version tag and the size of its content)
+const PAGE_PREFIX_SIZE: usize = 5;
+
+/// Maximum number of hashes in a hash page
+const MAX_HASHES_PER_PAGE: usize =
+    (MAX_PAGE_SIZE - PAGE_PREFIX_SIZE) / PREIMAGE_HASH_SIZE;
+
+/// Maximum size of a revealed payload. Bounds the ticks spent revealing a
+/// payload, which cannot be known before the payload is revealed.
+pub const MAX_REVEALED_SIZE: usize = 256 * 1024;
+
+/// Maximum number of content pages of a revealed payload
+pub const MAX_REVEALED_PAGES: usize =
+    MAX_REVEALED_SIZE.div_ceil(MAX_PAGE_SIZE - PAGE_PREFIX_SIZE);
+
+/// Maximum depth of the Merkle tree of a payload. The content pages of a
+/// payload of [`MAX_REVEALED_SIZE`] fit under a single root hash page, which
+/// bounds the number of hash pages as well.
+const MAX_DAC_LEVELS: usize = 2;
+
+const _: () = assert!(MAX_REVEALED_PAGES <= MAX_HASHES_PER_PAGE);
+
+/// Hash of the root page of a revealed payload, encoded in hex
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RootHash(String);
+
+impl RootHash {
+    fn to_bytes(&self) -> Result<[u8; PREIMAGE_HASH_SIZE]> {
+        hex::decode(&self.0)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or(Error::InvalidPreimage)
+    }
+}
+
+impl From<PreimageHash> for RootHash {
+    fn from(hash: PreimageHash) -> Self {
+        Self(hex::encode(hash.as_ref()))
+    }
+}
+
+impl Display for RootHash {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for RootHash {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let root_hash = Self(s.to_string());
+        root_hash.to_bytes()?;
+        Ok(root_hash)
+    }
+}
+
+/// Splits `payload` into pages, calling `handle` with the hash and content of
+/// each page. Returns the hash of the root page.
+pub fn prepare(
+    payload: &[u8],
+    handle: impl FnMut(PreimageHash, Vec<u8>),
+) -> Result<RootHash> {
+    if payload.len() > MAX_REVEALED_SIZE {
+        return Err(Error::InvalidPreimage);
+    }
+
+    let root_hash =
+        prepare_preimages(payload, handle).map_err(|_| Error::InvalidPreimage)?;
+
+    Ok(root_hash.into())
+}
+
+/// Reveals the payload whose root page hash is `root_hash`
+pub fn reveal(rt: &mut impl HostRuntime, root_hash: &RootHash) -> Result<Vec<u8>> {
+    let root_hash = root_hash.to_bytes()?;
+
+    let mut buffer = vec![0; MAX_PAGE_SIZE * MAX_DAC_LEVELS];
+    let mut payload = Vec::new();
+    let mut pages = 0;
+
+    reveal_loop(
+        rt,
+        0,
+        &root_hash,
+        &mut buffer,
+        MAX_DAC_LEVELS,
+        &mut |_, content: V0SliceContentPage| {
+            pages += 1;
+            if pages > MAX_REVEALED_PAGES {
+                return Err("Revealed payload has too many pages");
+            }
+            if payload.len() + content.as_ref().len() > MAX_REVEALED_SIZE {
+                return Err("Revealed payload is too large");
+            }
+            payload.extend_from_slice(content.as_ref());
+            Ok(())
+        },
+    )
+    .map_err(|_| Error::InvalidPreimage)?;
+
+    Ok(payload)
+}
+
+#[cfg(test)]
+mod test {
+    use tezos_smart_rollup_mock::MockHost;
+
+    use super::*;
+
+    #[test]
+    fn reveal_prepared_payload() {
+        let mut host = MockHost::default();
+        let payload: Vec<u8> = (0..10 * MAX_PAGE_SIZE).map(|i| i as u8).collect();
+
+        let root_hash = prepare(&payload, |_, page| {
+            host.set_preimage(page);
+        })
+        .unwrap();
+
+        assert_eq!(payload, reveal(&mut host, &root_hash).unwrap());
+    }
+
+    #[test]
+    fn reveal_largest_payload() {
+        let mut host = MockHost::default();
+        let payload = vec![1; MAX_REVEALED_SIZE];
+
+        let root_hash = prepare(&payload, |_, page| {
+            host.set_preimage(page);
+        })
+        .unwrap();
+
+        assert_eq!(payload, reveal(&mut host, &root_hash).unwrap());
+    }
+
+    #[test]
+    fn oversized_payloads_are_not_revealed() {
+        assert!(matches!(
+            prepare(&vec![1; MAX_REVEALED_SIZE + 1], |_, _| ()),
+            Err(Error::InvalidPreimage)
+        ));
+
+        // Preimages prepared without the size check, one slightly too large and
+        // one deeper than a single hash page
+        for size in [MAX_REVEALED_SIZE + 1, 2 * MAX_REVEALED_SIZE] {
+            let mut host = MockHost::default();
+            let root_hash: RootHash = prepare_preimages(&vec![1; size], |_, page| {
+                host.set_preimage(page);
+            })
+            .unwrap()
+            .into();
+
+            assert!(matches!(
+                reveal(&mut host, &root_hash),
+                Err(Error::InvalidPreimage)
+            ));
+        }
+    }
+
+    #[test]
+    fn invalid_root_hashes_are_rejected() {
+        // Not hex, and too short
+        assert!("zz".repeat(PREIMAGE_HASH_SIZE).parse::<RootHash>().is_err());
+        assert!("00".parse::<RootHash>().is_err());
+
+        // Root hashes deserialized from operations are checked when revealed
+        let mut host = MockHost::default();
+        let root_hash: RootHash = serde_json::from_str("\"00\"").unwrap();
+        assert!(matches!(
+            reveal(&mut host, &root_hash),
+            Err(Error::InvalidPreimage)
+        ));
+    }
+}
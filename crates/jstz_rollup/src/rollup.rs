@@ -78,7 +78,10 @@ impl JstzRollup {
             operator,
             "jstz_rollup",
             "wasm_2_0_0",
-            "(pair bytes (ticket unit))",
+            // Unit tickets (including the bridge's) are deposited through the
+            // default entrypoint, FA2.1 token tickets through `%deposit_token`
+            "(or (pair %default bytes (ticket unit)) \
+             (pair %deposit_token bytes (ticket (pair nat (option bytes)))))",
             &hex::encode(installer),
         )?;
 
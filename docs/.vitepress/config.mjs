@@ -40,6 +40,7 @@ export default defineConfig({
           { text: "KV", link: "/api/kv" },
           { text: "SmartFunction", link: "/api/smart_function" },
           { text: "Ledger", link: "/api/ledger" },
+          { text: "Jstz", link: "/api/jstz" },
           { text: "Headers", link: "/api/headers" },
           { text: "Request", link: "/api/request" },
           { text: "Response", link: "/api/response" },
@@ -25,7 +25,7 @@ const init = () => {
   if (globalThis.jstz !== undefined) {
     jstz.Account.setBalance(Ledger.selfAddress, 1000);
   } else {
-    if (Ledger.balance(Ledger.selfAddress) === 0) {
+    if (Ledger.balance(Ledger.selfAddress) === 0n) {
       throw new Error(
         `Must fund address ${Ledger.selfAddress} before running tests`,
       );
@@ -55,10 +55,10 @@ const doTest = () => {
     } catch (e) {}
   });
 
-  test(function ledgerBalanceIsANumber() {
+  test(function ledgerBalanceIsABigInt() {
     const balance = Ledger.balance(Ledger.selfAddress);
 
-    assert(typeof balance === "number");
+    assert(typeof balance === "bigint");
   });
 
   test(function ledgerBalanceThrowsErrorForInvalidAddress() {
@@ -71,7 +71,7 @@ const doTest = () => {
   test(function ledgerBalanceIsZeroForNewAddress() {
     const balance = Ledger.balance(ADDRESS1);
 
-    assertEquals(balance, 0);
+    assertEquals(balance, 0n);
   });
 
   test(function ledgerBalanceIsNonZeroForFundedAddress() {
@@ -83,7 +83,7 @@ const doTest = () => {
   test(function ledgerTransferShouldTransferFunds() {
     const from = Ledger.selfAddress;
     const to = ADDRESS1;
-    const amount = 100;
+    const amount = 100n;
 
     const fromBalanceBefore = Ledger.balance(from);
     const toBalanceBefore = Ledger.balance(to);
@@ -122,7 +122,44 @@ const doTest = () => {
     try {
       Ledger.transfer(ADDRESS1, "foo");
       assert(false);
-    } catch (e) {}
+    } catch (e) {
+      assert(e instanceof TypeError);
+    }
+  });
+
+  test(function ledgerTransferShouldThrowErrorForFractionalAmount() {
+    try {
+      Ledger.transfer(ADDRESS1, 1.5);
+      assert(false);
+    } catch (e) {
+      assert(e instanceof RangeError);
+    }
+  });
+
+  test(function ledgerTransferShouldThrowErrorForUnsafeAmount() {
+    try {
+      Ledger.transfer(ADDRESS1, 2 ** 53);
+      assert(false);
+    } catch (e) {
+      assert(e instanceof RangeError);
+    }
+  });
+
+  test(function ledgerTransferShouldThrowErrorForOverflowingBigInt() {
+    try {
+      Ledger.transfer(ADDRESS1, 2n ** 64n);
+      assert(false);
+    } catch (e) {
+      assert(e instanceof RangeError);
+    }
+  });
+
+  test(function ledgerTransferShouldAcceptSafeIntegers() {
+    const toBalanceBefore = Ledger.balance(ADDRESS1);
+
+    Ledger.transfer(ADDRESS1, 1);
+
+    assertEquals(Ledger.balance(ADDRESS1), toBalanceBefore + 1n);
   });
 };
 
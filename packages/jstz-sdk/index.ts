@@ -1,23 +1,54 @@
 import * as jstz from "jstz_sdk";
 
 namespace ffi {
-  export type Address = { Tz1: string };
-
-  export type Signature = { Ed25519: string };
-
-  export type PublicKey = { Ed25519: string };
+  export type Address =
+    | { Tz1: string }
+    | { Tz2: string }
+    | { Tz3: string }
+    | { Kt1: string };
+
+  export type Signature =
+    | { Ed25519: string }
+    | { Secp256k1: string }
+    | { P256: string };
+
+  export type PublicKey =
+    | { Ed25519: string }
+    | { Secp256k1: string }
+    | { P256: string };
 
   export type Operation = {
     source: Address;
     nonce: number;
     content: OperationContent;
+    rollup_address: string;
+    valid_until_level: number | null;
   };
 
   export type Headers = Record<string, string>;
   export type Body = Uint8Array;
 
   export type OperationContent =
-    | { DeployFunction: { function_code: string; account_credit: number } }
+    | {
+        DeployFunction: {
+          function_code: string;
+          account_credit: number;
+          admin: Address | null;
+          salt: string | null;
+          modules: Record<string, string>;
+        };
+      }
+    | { UpgradeFunction: { address: Address; function_code: string } }
+    | { Transfer: { destination: Address; amount: number } }
+    | { Approve: { spender: Address; amount: number; asset: Asset | null } }
+    | {
+        RevealDeployFunction: {
+          root_hash: string;
+          account_credit: number;
+          admin: Address | null;
+          salt: string | null;
+        };
+      }
     | {
         RunFunction: {
           uri: string;
@@ -26,7 +57,8 @@ namespace ffi {
           body: Body | null;
           gas_limit: number;
         };
-      };
+      }
+    | { Batch: OperationContent[] };
 
   export type SignedOperation = {
     public_key: PublicKey;
@@ -39,7 +71,68 @@ namespace ffi {
     inner: ReceiptResult;
   };
 
-  export type ReceiptResult = { Ok: ReceiptContent } | { Err: string };
+  export type ReceiptResult =
+    | { Ok: ReceiptContent }
+    | { Err: ReceiptError };
+
+  export type ReceiptError =
+    | "BalanceOverflow"
+    | "InvalidNonce"
+    | "InvalidAddress"
+    | "RefererShouldNotBeSet"
+    | "InvalidHttpRequest"
+    | "TicketerNotSet"
+    | "InvalidWithdrawal"
+    | "SourceMismatch"
+    | "UnauthorizedUpgrade"
+    | "SchedulerLevelFull"
+    | "InvalidAsset"
+    | "OperationExpired"
+    | "RollupAddressMismatch"
+    | "InsufficientAllowance"
+    | "InvalidPreimage"
+    | "InsufficientFunds"
+    | "AddressAlreadyInUse"
+    | "SchedulerQuotaExceeded"
+    | { BatchStepFailed: { step: number; error: ReceiptError } }
+    | { KvError: { description: string } }
+    | { HostError: { description: string } }
+    | { PathError: { description: string } }
+    | { JsError: { name: string; message: string; stack: string | null } }
+    | { SerializationError: { description: string } }
+    | { CryptoError: { description: string } }
+    | { GasLimitExceeded: { api: string } }
+    | { RunFunctionFailed: { status_code: number } };
+
+  export type KvWrite = {
+    address: Address;
+    key: string;
+  };
+
+  export type Asset = {
+    ticketer: string;
+    content: "Unit" | { Token: { id: number; metadata: number[] | null } };
+  };
+
+  export type Transfer = {
+    from: Address;
+    to: Address;
+    amount: number;
+    asset: Asset | null;
+  };
+
+  export type Approval = {
+    owner: Address;
+    spender: Address;
+    amount: number;
+    asset: Asset | null;
+  };
+
+  export type Event = {
+    address: Address;
+    topic: string;
+    payload: string;
+  };
 
   export type ReceiptContent =
     | {
@@ -47,27 +140,48 @@ namespace ffi {
           body: Body;
           status_code: number;
           headers: Headers;
+          gas_used: number;
+          calls: Address[];
+          kv_writes: KvWrite[];
+          transfers: Transfer[];
+          approvals: Approval[];
+          events: Event[];
         };
       }
     | {
         DeployFunction: {
           address: Address;
         };
-      };
+      }
+    | {
+        UpgradeFunction: {
+          version: number;
+        };
+      }
+    | { Transfer: Transfer }
+    | { Approve: Approval }
+    | { Batch: ReceiptContent[] };
 }
 
 export type Address = string;
 
-const ADDRESS_REGEX = /^tz1[a-zA-Z0-9]{33}$/;
+const ADDRESS_REGEX = /^(tz[123]|KT1)[a-zA-Z0-9]{33}$/;
 
 export function isAddress(value: unknown): value is Address {
   return typeof value === "string" && value.match(ADDRESS_REGEX) !== null;
 }
 
+/** Smart function addresses start with `KT1`, account addresses with `tz` */
+export function isSmartFunction(address: Address): boolean {
+  return address.startsWith("KT1");
+}
+
 interface Operation {
   source: Address;
   nonce: number;
   content: OperationContent;
+  rollupAddress: string;
+  validUntilLevel?: number;
 }
 
 export type JstzHeaders = Record<string, string>;
@@ -83,6 +197,33 @@ export type JstzResponse = {
   statusCode: number;
   headers: JstzHeaders;
   body: JstzBody;
+  gasUsed: number;
+  calls: Address[];
+  kvWrites: JstzKvWrite[];
+  transfers: JstzTransfer[];
+  approvals: JstzApproval[];
+  events: JstzEvent[];
+};
+export type JstzKvWrite = {
+  address: Address;
+  key: string;
+};
+export type JstzTransfer = {
+  from: Address;
+  to: Address;
+  amount: number;
+  asset: ffi.Asset | null;
+};
+export type JstzApproval = {
+  owner: Address;
+  spender: Address;
+  amount: number;
+  asset: ffi.Asset | null;
+};
+export type JstzEvent = {
+  address: Address;
+  topic: string;
+  payload: unknown;
 };
 
 type OperationContent =
@@ -90,6 +231,32 @@ type OperationContent =
       kind: "deploy";
       functionCode: string;
       initialBalance: number;
+      admin?: Address;
+      salt?: string;
+      modules?: Record<string, string>;
+    }
+  | {
+      kind: "revealDeploy";
+      rootHash: string;
+      initialBalance: number;
+      admin?: Address;
+      salt?: string;
+    }
+  | {
+      kind: "upgrade";
+      address: Address;
+      functionCode: string;
+    }
+  | {
+      kind: "transfer";
+      destination: Address;
+      amount: number;
+    }
+  | {
+      kind: "approve";
+      spender: Address;
+      amount: number;
+      asset?: ffi.Asset;
     }
   | ({
       kind: "run";
@@ -109,14 +276,75 @@ type SignedOperation = {
 };
 
 const encodeAddress = (address: Address): ffi.Address => {
+  if (address.startsWith("tz2")) {
+    return { Tz2: address };
+  }
+  if (address.startsWith("tz3")) {
+    return { Tz3: address };
+  }
+  if (isSmartFunction(address)) {
+    return { Kt1: address };
+  }
   return { Tz1: address };
 };
 
+/**
+ * Error thrown when an operation fails. `kind` is the variant of the
+ * receipt error (e.g. `"InvalidNonce"` or `"JsError"`) and `details` holds
+ * its fields, if any.
+ */
+export class JstzReceiptError extends Error {
+  kind: string;
+  details?: Record<string, unknown>;
+
+  constructor(error: ffi.ReceiptError) {
+    if (typeof error === "string") {
+      super(error);
+      this.kind = error;
+      return;
+    }
+
+    const [kind, details] = Object.entries(error)[0];
+    super(
+      "JsError" in error
+        ? `${error.JsError.name}: ${error.JsError.message}`
+        : `${kind}: ${Object.values(details).join(", ")}`,
+    );
+    this.kind = kind;
+    this.details = details;
+  }
+}
+
+const decodeAddress = (address: ffi.Address): Address => {
+  if ("Tz2" in address) {
+    return address.Tz2;
+  }
+  if ("Tz3" in address) {
+    return address.Tz3;
+  }
+  if ("Kt1" in address) {
+    return address.Kt1;
+  }
+  return address.Tz1;
+};
+
 const encodeSignature = (signature: string): ffi.Signature => {
+  if (signature.startsWith("spsig1")) {
+    return { Secp256k1: signature };
+  }
+  if (signature.startsWith("p2sig")) {
+    return { P256: signature };
+  }
   return { Ed25519: signature };
 };
 
 const encodePublicKey = (publicKey: string): ffi.PublicKey => {
+  if (publicKey.startsWith("sppk")) {
+    return { Secp256k1: publicKey };
+  }
+  if (publicKey.startsWith("p2pk")) {
+    return { P256: publicKey };
+  }
   return { Ed25519: publicKey };
 };
 
@@ -129,6 +357,42 @@ const encodeOperationContent = (
         DeployFunction: {
           function_code: content.functionCode,
           account_credit: content.initialBalance,
+          admin:
+            content.admin === undefined ? null : encodeAddress(content.admin),
+          salt: content.salt ?? null,
+          modules: content.modules ?? {},
+        },
+      };
+    case "revealDeploy":
+      return {
+        RevealDeployFunction: {
+          root_hash: content.rootHash,
+          account_credit: content.initialBalance,
+          admin:
+            content.admin === undefined ? null : encodeAddress(content.admin),
+          salt: content.salt ?? null,
+        },
+      };
+    case "upgrade":
+      return {
+        UpgradeFunction: {
+          address: encodeAddress(content.address),
+          function_code: content.functionCode,
+        },
+      };
+    case "transfer":
+      return {
+        Transfer: {
+          destination: encodeAddress(content.destination),
+          amount: content.amount,
+        },
+      };
+    case "approve":
+      return {
+        Approve: {
+          spender: encodeAddress(content.spender),
+          amount: content.amount,
+          asset: content.asset ?? null,
         },
       };
     case "run":
@@ -145,12 +409,14 @@ const encodeOperationContent = (
 };
 
 const encodeOperation = (operation: Operation): ffi.Operation => {
-  const { source, nonce, content } = operation;
+  const { source, nonce, content, rollupAddress, validUntilLevel } = operation;
 
   return {
     source: encodeAddress(source),
     nonce,
     content: encodeOperationContent(content),
+    rollup_address: rollupAddress,
+    valid_until_level: validUntilLevel ?? null,
   };
 };
 
@@ -177,10 +443,27 @@ const signOperation = (user: User, operation: Operation): SignedOperation => {
 
 export class Jstz {
   private endpoint: string;
+  private rollupAddress?: string;
   constructor(endpoint: string) {
     this.endpoint = endpoint;
   }
 
+  async getRollupAddress(): Promise<string> {
+    if (this.rollupAddress === undefined) {
+      const res = await fetch(
+        `http://${this.endpoint}/operations/rollup_address`,
+      );
+
+      if (res.status !== 200) {
+        throw new Error("Failed to fetch rollup address");
+      }
+
+      this.rollupAddress = (await res.json()) as string;
+    }
+
+    return this.rollupAddress;
+  }
+
   async getNonce(source: Address): Promise<number> {
     const res = await fetch(`http://${this.endpoint}/accounts/${source}/nonce`);
 
@@ -237,16 +520,62 @@ export class Jstz {
     user: User,
     functionCode: string,
     initialBalance: number = 0,
+    admin?: Address,
+    salt?: string,
+    modules?: Record<string, string>,
   ): Promise<Address> {
     const nonce = await this.getNonce(user.address);
+    const rollupAddress = await this.getRollupAddress();
+
+    const content: OperationContent = {
+      kind: "deploy",
+      functionCode,
+      initialBalance,
+      admin,
+      salt,
+      modules,
+    };
+
+    const operation: Operation = {
+      source: user.address,
+      nonce,
+      rollupAddress,
+      content,
+    };
+
+    const receipt = await this.postSignedOperation(
+      signOperation(user, operation),
+    );
+
+    if ("Err" in receipt.inner) {
+      throw new JstzReceiptError(receipt.inner.Err);
+    }
+
+    const receiptContent = receipt.inner["Ok"];
+
+    if (!("DeployFunction" in receiptContent)) {
+      throw new Error("Unexpected receipt kind");
+    }
+
+    return decodeAddress(receiptContent.DeployFunction.address);
+  }
+
+  async upgrade(
+    user: User,
+    address: Address,
+    functionCode: string,
+  ): Promise<number> {
+    const nonce = await this.getNonce(user.address);
+    const rollupAddress = await this.getRollupAddress();
 
     const operation: Operation = {
       source: user.address,
       nonce,
+      rollupAddress,
       content: {
-        kind: "deploy",
+        kind: "upgrade",
+        address,
         functionCode,
-        initialBalance,
       },
     };
 
@@ -255,24 +584,117 @@ export class Jstz {
     );
 
     if ("Err" in receipt.inner) {
-      throw new Error(receipt.inner.Err);
+      throw new JstzReceiptError(receipt.inner.Err);
     }
 
     const receiptContent = receipt.inner["Ok"];
 
-    if (!("DeployFunction" in receiptContent)) {
+    if (!("UpgradeFunction" in receiptContent)) {
+      throw new Error("Unexpected receipt kind");
+    }
+
+    return receiptContent.UpgradeFunction.version;
+  }
+
+  async transfer(
+    user: User,
+    destination: Address,
+    amount: number,
+  ): Promise<JstzTransfer> {
+    const nonce = await this.getNonce(user.address);
+    const rollupAddress = await this.getRollupAddress();
+
+    const operation: Operation = {
+      source: user.address,
+      nonce,
+      rollupAddress,
+      content: {
+        kind: "transfer",
+        destination,
+        amount,
+      },
+    };
+
+    const receipt = await this.postSignedOperation(
+      signOperation(user, operation),
+    );
+
+    if ("Err" in receipt.inner) {
+      throw new JstzReceiptError(receipt.inner.Err);
+    }
+
+    const receiptContent = receipt.inner["Ok"];
+
+    if (!("Transfer" in receiptContent)) {
+      throw new Error("Unexpected receipt kind");
+    }
+
+    const { from, to, amount: transferred, asset } = receiptContent.Transfer;
+    return {
+      from: decodeAddress(from),
+      to: decodeAddress(to),
+      amount: transferred,
+      asset,
+    };
+  }
+
+  async approve(
+    user: User,
+    spender: Address,
+    amount: number,
+    asset?: ffi.Asset,
+  ): Promise<JstzApproval> {
+    const nonce = await this.getNonce(user.address);
+    const rollupAddress = await this.getRollupAddress();
+
+    const operation: Operation = {
+      source: user.address,
+      nonce,
+      rollupAddress,
+      content: {
+        kind: "approve",
+        spender,
+        amount,
+        asset,
+      },
+    };
+
+    const receipt = await this.postSignedOperation(
+      signOperation(user, operation),
+    );
+
+    if ("Err" in receipt.inner) {
+      throw new JstzReceiptError(receipt.inner.Err);
+    }
+
+    const receiptContent = receipt.inner["Ok"];
+
+    if (!("Approve" in receiptContent)) {
       throw new Error("Unexpected receipt kind");
     }
 
-    return receiptContent.DeployFunction.address.Tz1;
+    const {
+      owner,
+      spender: approved,
+      amount: allowance,
+      asset: approvedAsset,
+    } = receiptContent.Approve;
+    return {
+      owner: decodeAddress(owner),
+      spender: decodeAddress(approved),
+      amount: allowance,
+      asset: approvedAsset,
+    };
   }
 
   async run(user: User, request: JstzRequest): Promise<JstzResponse> {
     const nonce = await this.getNonce(user.address);
+    const rollupAddress = await this.getRollupAddress();
 
     const operation: Operation = {
       source: user.address,
       nonce,
+      rollupAddress,
       content: {
         kind: "run",
         ...request,
@@ -284,7 +706,7 @@ export class Jstz {
     );
 
     if ("Err" in receipt.inner) {
-      throw new Error(receipt.inner.Err);
+      throw new JstzReceiptError(receipt.inner.Err);
     }
 
     const receiptContent = receipt.inner["Ok"];
@@ -297,6 +719,37 @@ export class Jstz {
       statusCode: receiptContent.RunFunction.status_code,
       headers: receiptContent.RunFunction.headers,
       body: receiptContent.RunFunction.body,
+      gasUsed: receiptContent.RunFunction.gas_used,
+      calls: receiptContent.RunFunction.calls.map(decodeAddress),
+      kvWrites: receiptContent.RunFunction.kv_writes.map(
+        ({ address, key }) => ({
+          address: decodeAddress(address),
+          key,
+        }),
+      ),
+      transfers: receiptContent.RunFunction.transfers.map(
+        ({ from, to, amount, asset }) => ({
+          from: decodeAddress(from),
+          to: decodeAddress(to),
+          amount,
+          asset,
+        }),
+      ),
+      approvals: receiptContent.RunFunction.approvals.map(
+        ({ owner, spender, amount, asset }) => ({
+          owner: decodeAddress(owner),
+          spender: decodeAddress(spender),
+          amount,
+          asset,
+        }),
+      ),
+      events: receiptContent.RunFunction.events.map(
+        ({ address, topic, payload }) => ({
+          address: decodeAddress(address),
+          topic,
+          payload: JSON.parse(payload),
+        }),
+      ),
     };
   }
 }
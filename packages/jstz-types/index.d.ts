@@ -195,23 +195,64 @@ declare interface Kv {
 
 declare var Kv: Kv;
 
-declare type Mutez = number;
+declare type Mutez = bigint;
+
+/** An amount of mutez, either as a `bigint` or as a non-negative safe integer */
+declare type MutezLike = Mutez | number;
+
+declare type Asset = string;
 
 declare interface Ledger {
   readonly selfAddress: Address;
-  balance(address: Address): Mutez;
-  transfer(address: Address, amount: Mutez): void;
+  balance(address: Address, asset?: Asset): Mutez;
+  transfer(address: Address, amount: MutezLike, asset?: Asset): void;
+  withdraw(address: Address, amount: MutezLike): void;
+  approve(spender: Address, amount: MutezLike, asset?: Asset): void;
+  allowance(owner: Address, spender: Address, asset?: Asset): Mutez;
+  transferFrom(
+    owner: Address,
+    address: Address,
+    amount: MutezLike,
+    asset?: Asset,
+  ): void;
 }
 
 declare var Ledger: Ledger;
 
 declare interface SmartFunction {
-  create(code: String): Promise<Address>;
+  create(
+    code: String,
+    initialBalance?: MutezLike,
+    admin?: Address,
+    salt?: string,
+  ): Promise<Address>;
+  upgrade(code: String, address?: Address): number;
   call(request: Request): Promise<Response>;
+  isSmartFunction(address: Address): boolean;
 }
 
 declare var SmartFunction: SmartFunction;
 
+declare interface Block {
+  readonly level: number;
+  readonly timestamp: number;
+}
+
+declare interface ScheduleOptions {
+  atLevel: number;
+  gasLimit: number;
+  every?: number;
+  times?: number;
+}
+
+declare interface Jstz {
+  readonly block: Block;
+  schedule(request: Request, options: ScheduleOptions): string[];
+  emit(topic: string, payload?: unknown): void;
+}
+
+declare var Jstz: Jstz;
+
 declare function fetch(request: Request): Promise<Response>;
 
 declare function atob(s: string): string;